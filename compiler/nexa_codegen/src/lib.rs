@@ -10,6 +10,8 @@ pub use module::{add_global_string, declare_main};
 pub use target::{generate_executable, host_target_triple};
 pub use types::TypeMapper;
 
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::module::Linkage;
@@ -18,7 +20,22 @@ use inkwell::types::{BasicType, BasicTypeEnum};
 use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue, PointerValue};
 use inkwell::AddressSpace;
 use nexa_parser::ast::*;
-use std::collections::HashMap;
+use nexa_typecheck::TypeInfo;
+use std::collections::{HashMap, HashSet};
+
+/// class 对象中虚表指针字段的名称
+const VTABLE_FIELD: &str = "__vtable";
+
+/// 模块初始化函数的名称 (非入口模块中按 `mangle_symbol` 改名)
+const MODULE_INIT: &str = "__nexa_module_init";
+
+/// 非入口模块中符号的链接名
+///
+/// 模块路径和名称都以长度作前缀 (`_NX<len><path><len><name>`)，
+/// 不同模块中的同名定义不会冲突，且同一符号在导出方和导入方得到相同的名称。
+pub fn mangle_symbol(module_path: &str, name: &str) -> String {
+    format!("_NX{}{}{}{}", module_path.len(), module_path, name.len(), name)
+}
 
 /// 代码生成器
 /// 将 AST 转换为 LLVM IR
@@ -31,19 +48,101 @@ pub struct CodeGenerator<'ctx> {
     builder: Builder<'ctx>,
     /// 当前函数
     current_function: Option<FunctionValue<'ctx>>,
-    /// 局部变量映射 (变量名 -> 指针值)
-    variables: HashMap<String, PointerValue<'ctx>>,
-    /// 变量类型映射 (变量名 -> LLVM 类型)
-    variable_types: HashMap<String, inkwell::types::BasicTypeEnum<'ctx>>,
+    /// 局部变量 (变量名 -> 地址和存储类型)
+    variables: HashMap<String, (PointerValue<'ctx>, BasicTypeEnum<'ctx>)>,
     /// 类型映射器
     #[allow(dead_code)]
     type_mapper: TypeMapper<'ctx>,
     /// Struct 类型定义映射
     struct_types: HashMap<String, inkwell::types::StructType<'ctx>>,
-    /// Struct 字段定义映射 (struct名 -> 字段列表)
-    struct_field_lists: HashMap<String, Vec<String>>,
+    /// Struct 字段定义映射 (struct名 -> 字段名和字段类型列表)
+    struct_field_lists: HashMap<String, Vec<(String, Type)>>,
+    /// class 定义 (class 值以对象指针表示，继承关系用于生成布局和虚表)
+    class_defs: HashMap<String, StructDefinition>,
+    /// 接口定义 (接口值以 {对象指针, 接口表指针} 表示)
+    interface_defs: HashMap<String, InterfaceDefinition>,
+    /// 枚举定义 (本地名称 -> 定义)，命名空间导入的枚举以 `ns.Enum` 为键
+    enum_defs: HashMap<String, EnumDefinition>,
+    /// 类型别名 (本地名称 -> 目标类型)
+    type_aliases: HashMap<String, Type>,
     /// 导入的符号表 (模块路径 -> 符号)
     imported_symbols: HashMap<String, nexa_parser::module::SymbolTable>,
+    /// 类型检查结果 (表达式 -> 类型)
+    type_info: TypeInfo,
+    /// 循环上下文栈 (最内层循环在栈顶)
+    loop_stack: Vec<LoopContext<'ctx>>,
+    /// try 作用域栈 (最内层 try 在栈顶)
+    try_stack: Vec<TryScope>,
+    /// 已生成的闭包函数数量 (用于命名)
+    closure_count: usize,
+    /// 当前函数中放在堆上单元里、与闭包共享的变量
+    cells: HashSet<String>,
+    /// 当前函数的返回类型 (用于 return 时的值转换)
+    return_type: Option<Type>,
+    /// 模块级全局变量 (变量名 -> (全局地址, LLVM 类型))
+    globals: HashMap<String, (PointerValue<'ctx>, BasicTypeEnum<'ctx>)>,
+    /// 模块初始化函数，执行顶层语句，在 main 开始时调用
+    module_init: Option<FunctionValue<'ctx>>,
+    /// 当前模块在导入图中的路径，设置后外部可见的定义改用带模块路径的链接名
+    module_path: Option<String>,
+    /// 是否按非入口模块生成
+    library: bool,
+    /// 入口模块依赖的各模块初始化函数 (按依赖顺序)
+    dependency_inits: Vec<String>,
+    /// 导入符号的本地名称 -> LLVM 中的链接名
+    link_names: HashMap<String, String>,
+    /// 泛型函数定义，按类型实参实例化后生成
+    generic_functions: HashMap<String, Function>,
+    /// 泛型 struct / class 定义
+    generic_structs: HashMap<String, StructDefinition>,
+    /// 泛型接口定义
+    generic_interfaces: HashMap<String, InterfaceDefinition>,
+    /// 正在生成的泛型实例的类型实参：类型参数名 -> 具体类型
+    type_arguments: HashMap<String, Type>,
+    /// 已声明、尚未生成函数体的泛型实例函数及其类型实参
+    pending_instances: Vec<(Function, HashMap<String, Type>)>,
+    /// 已声明、尚未填充虚表的泛型 class 实例
+    pending_vtables: Vec<String>,
+}
+
+/// 循环上下文：break / continue 的跳转目标
+#[derive(Debug, Clone)]
+struct LoopContext<'ctx> {
+    /// 循环标签 (outer: for ...)
+    label: Option<String>,
+    /// break 跳转的结束块
+    break_block: BasicBlock<'ctx>,
+    /// continue 跳转的块 (条件块或更新块)
+    continue_block: BasicBlock<'ctx>,
+    /// 进入循环时的 try 作用域深度
+    try_depth: usize,
+}
+
+/// 闭包捕获的外层变量
+#[derive(Debug, Clone)]
+struct CapturedVariable<'ctx> {
+    name: String,
+    /// 变量放在堆上的单元中，环境里保存单元的地址；否则保存变量值的副本
+    in_cell: bool,
+    ty: BasicTypeEnum<'ctx>,
+    ptr: PointerValue<'ctx>,
+}
+
+/// switch 的匹配值
+enum SwitchSubject<'ctx> {
+    /// 整数和指针统一转换为 i64 比较
+    Int(inkwell::values::IntValue<'ctx>),
+    /// 联合类型的值和类型，按标签和负载比较
+    Union(BasicValueEnum<'ctx>, Type),
+}
+
+/// try 作用域：return / break / continue 跳出时需要执行的清理
+#[derive(Debug, Clone)]
+struct TryScope {
+    /// 是否有需要弹出的运行时异常帧
+    has_frame: bool,
+    /// finally 块
+    finally_body: Option<Statement>,
 }
 
 impl<'ctx> CodeGenerator<'ctx> {
@@ -58,11 +157,66 @@ impl<'ctx> CodeGenerator<'ctx> {
             builder,
             current_function: None,
             variables: HashMap::new(),
-            variable_types: HashMap::new(),
             type_mapper,
             struct_types: HashMap::new(),
             struct_field_lists: HashMap::new(),
+            class_defs: HashMap::new(),
+            interface_defs: HashMap::new(),
+            enum_defs: HashMap::new(),
+            type_aliases: HashMap::new(),
+            return_type: None,
+            globals: HashMap::new(),
+            module_init: None,
+            module_path: None,
+            library: false,
+            dependency_inits: Vec::new(),
+            link_names: HashMap::new(),
+            generic_functions: HashMap::new(),
+            generic_structs: HashMap::new(),
+            generic_interfaces: HashMap::new(),
+            type_arguments: HashMap::new(),
+            pending_instances: Vec::new(),
+            pending_vtables: Vec::new(),
             imported_symbols: HashMap::new(),
+            type_info: TypeInfo::default(),
+            loop_stack: Vec::new(),
+            try_stack: Vec::new(),
+            closure_count: 0,
+            cells: HashSet::new(),
+        }
+    }
+
+    /// 设置类型检查结果
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info = type_info;
+    }
+
+    /// 获取表达式经类型检查推导出的类型
+    ///
+    /// 泛型实例中的类型参数替换为类型实参，用到的泛型实例在此时生成。
+    fn expression_type(&mut self, expr: &Expression) -> Result<Option<Type>, CodegenError> {
+        match self.type_info.type_of(expr).cloned() {
+            Some(ty) => self.concrete_type(&ty).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 查询变量声明的类型：有注解时是注解的类型，否则是初始化表达式推导出的类型
+    fn binding_type(&mut self, declaration: &Span) -> Result<Option<Type>, CodegenError> {
+        match self.type_info.binding_type_of(declaration).cloned() {
+            Some(ty) => self.concrete_type(&ty).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 获取表达式所属的 struct 名称
+    fn struct_name_of(&mut self, expr: &Expression) -> Result<Option<String>, CodegenError> {
+        let Some(ty) = self.expression_type(expr)? else { return Ok(None) };
+        let ty = self.resolve_type(&ty);
+        // 可空的对象 (如 `Point | null`) 同样以对象本身表示
+        match self.plain_union_member(&ty).unwrap_or(ty) {
+            Type::Struct(name) => Ok(Some(name)),
+            _ => Ok(None),
         }
     }
 
@@ -83,6 +237,40 @@ impl<'ctx> CodeGenerator<'ctx> {
         self.imported_symbols.insert(module_path, symbols);
     }
 
+    /// 设置当前模块在导入图中的路径
+    ///
+    /// 设置后按非入口模块生成：外部可见的定义改用 `mangle_symbol` 生成的名称，
+    /// 并总是生成供入口模块调用的模块初始化函数。
+    pub fn set_module_path(&mut self, path: String) {
+        self.module_path = Some(path);
+        self.library = true;
+    }
+
+    /// 设置入口模块被导入时使用的模块路径
+    ///
+    /// 入口模块处在 (允许的) 循环导入上时，环上的模块按该路径引用入口模块的定义，
+    /// 因此除 main 以外外部可见的定义同样改用 `mangle_symbol` 生成的名称。
+    pub fn set_entry_module_path(&mut self, path: String) {
+        self.module_path = Some(path);
+    }
+
+    /// 设置入口模块依赖的模块 (按依赖顺序)，main 开始时依次执行它们的顶层语句
+    pub fn set_dependencies(&mut self, paths: &[String]) {
+        self.dependency_inits = paths.iter().map(|path| mangle_symbol(path, MODULE_INIT)).collect();
+    }
+
+    /// 按本地名称查找函数，导入的函数解析为其链接名
+    fn lookup_function(&self, name: &str) -> Option<FunctionValue<'ctx>> {
+        let link_name = self.link_names.get(name).map(String::as_str).unwrap_or(name);
+        self.module.get_function(link_name)
+    }
+
+    /// 按本地名称查找全局变量，导入的全局变量解析为其链接名
+    fn lookup_global(&self, name: &str) -> Option<inkwell::values::GlobalValue<'ctx>> {
+        let link_name = self.link_names.get(name).map(String::as_str).unwrap_or(name);
+        self.module.get_global(link_name)
+    }
+
     /// 获取上下文
     pub fn context(&self) -> &'ctx Context {
         self.context
@@ -104,6 +292,31 @@ impl<'ctx> CodeGenerator<'ctx> {
         }
     }
 
+    /// 条件值转换为 i1：整数与 0 比较，指针判断是否为空
+    fn build_truthy(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        match value {
+            BasicValueEnum::IntValue(v) if v.get_type().get_bit_width() == 1 => Ok(v),
+            BasicValueEnum::IntValue(v) => Ok(self.builder().build_int_compare(
+                inkwell::IntPredicate::NE,
+                v,
+                v.get_type().const_zero(),
+                "truthy",
+            )?),
+            BasicValueEnum::PointerValue(p) => Ok(self.builder().build_is_not_null(p, "truthy")?),
+            BasicValueEnum::FloatValue(v) => Ok(self.builder().build_float_compare(
+                inkwell::FloatPredicate::ONE,
+                v,
+                v.get_type().const_zero(),
+                "truthy",
+            )?),
+            // struct 值 (如接口值、闭包) 总是为真
+            _ => Ok(self.context.bool_type().const_all_ones()),
+        }
+    }
+
     /// 获取模块
     pub fn module(&self) -> &Module<'ctx> {
         &self.module
@@ -115,13 +328,37 @@ impl<'ctx> CodeGenerator<'ctx> {
     }
 
     /// 添加局部变量
-    pub fn add_variable(&mut self, name: String, value: PointerValue<'ctx>) {
-        self.variables.insert(name, value);
+    pub fn add_variable(
+        &mut self,
+        name: String,
+        value: PointerValue<'ctx>,
+        ty: BasicTypeEnum<'ctx>,
+    ) {
+        self.variables.insert(name, (value, ty));
+    }
+
+    /// 为局部变量分配存储：被闭包共享的变量放在堆上的单元中，
+    /// 闭包在创建它的函数返回后仍能访问，其余变量放在栈上
+    fn allocate_variable(
+        &mut self,
+        ty: BasicTypeEnum<'ctx>,
+        name: &str,
+    ) -> Result<PointerValue<'ctx>, CodegenError> {
+        if self.cells.contains(name) {
+            Ok(self.builder().build_malloc(ty, name)?)
+        } else {
+            Ok(self.builder().build_alloca(ty, name)?)
+        }
     }
 
-    /// 获取局部变量
+    /// 获取变量地址，局部变量优先于全局变量
     pub fn get_variable(&self, name: &str) -> Option<PointerValue<'ctx>> {
-        self.variables.get(name).copied()
+        self.variable_slot(name).map(|(ptr, _)| ptr)
+    }
+
+    /// 变量的地址和存储类型，局部变量优先于全局变量
+    fn variable_slot(&self, name: &str) -> Option<(PointerValue<'ctx>, BasicTypeEnum<'ctx>)> {
+        self.variables.get(name).or_else(|| self.globals.get(name)).copied()
     }
 
     /// 检查是否是导入的符号
@@ -137,28 +374,15 @@ impl<'ctx> CodeGenerator<'ctx> {
 
     /// 获取导入的符号
     pub fn get_imported_symbol(&self, name: &str) -> Option<nexa_parser::module::ExportedSymbol> {
-        for symbols in self.imported_symbols.values() {
-            if let Some(symbol) = symbols.find(name) {
-                return Some(symbol);
-            }
-        }
-        None
+        // 同名导入在模块加载时已报错，这里按模块路径顺序查找以保证结果确定
+        let mut paths: Vec<&String> = self.imported_symbols.keys().collect();
+        paths.sort();
+        paths.into_iter().find_map(|path| self.imported_symbols[path].find(name))
     }
 
     /// 清除局部变量
     pub fn clear_variables(&mut self) {
         self.variables.clear();
-        self.variable_types.clear();
-    }
-
-    /// 设置变量类型
-    pub fn set_variable_type(&mut self, name: &str, ty: inkwell::types::BasicTypeEnum<'ctx>) {
-        self.variable_types.insert(name.to_string(), ty);
-    }
-
-    /// 获取变量类型
-    pub fn get_variable_type(&self, name: &str) -> Option<inkwell::types::BasicTypeEnum<'ctx>> {
-        self.variable_types.get(name).copied()
     }
 
     /// 设置当前函数
@@ -189,6 +413,10 @@ impl<'ctx> CodeGenerator<'ctx> {
         let scanf_type = i32_type.fn_type(&[i8_ptr.into()], true);
         self.module.add_function("scanf", scanf_type, None);
 
+        // printf - 打印 i32 以外的数值
+        let printf_type = i32_type.fn_type(&[i8_ptr.into()], true);
+        self.module.add_function("printf", printf_type, None);
+
         // readln_i32 - 读取整数
         let readln_i32_type = i32_type.fn_type(&[], false);
         self.module.add_function("readln_i32", readln_i32_type, None);
@@ -205,6 +433,11 @@ impl<'ctx> CodeGenerator<'ctx> {
         let print_i32_type = i32_type.fn_type(&[i32_type.into()], false);
         self.module.add_function("std_io_print_i32", print_i32_type, None);
 
+        // snprintf - 把数值格式化为字符串
+        let snprintf_type =
+            i32_type.fn_type(&[i8_ptr.into(), i64_type.into(), i8_ptr.into()], true);
+        self.module.add_function("snprintf", snprintf_type, None);
+
         // strlen - 获取字符串长度
         let strlen_type = i32_type.fn_type(&[i8_ptr.into()], false);
         self.module.add_function("strlen", strlen_type, None);
@@ -331,6 +564,27 @@ impl<'ctx> CodeGenerator<'ctx> {
         // std_math_sqrt - 平方根
         let math_sqrt_type = f64_type.fn_type(&[f64_type.into()], false);
         self.module.add_function("std_math_sqrt", math_sqrt_type, None);
+
+        // 异常处理运行时 (基于 setjmp/longjmp)
+        let void_type = self.context.void_type();
+        self.module.add_function("nexa_try_push", i8_ptr.fn_type(&[], false), None);
+        self.module.add_function("nexa_try_pop", void_type.fn_type(&[], false), None);
+        self.module.add_function("nexa_throw", void_type.fn_type(&[i8_ptr.into()], false), None);
+        self.module.add_function(
+            "nexa_throw_i32",
+            void_type.fn_type(&[i32_type.into()], false),
+            None,
+        );
+        self.module.add_function("nexa_rethrow", void_type.fn_type(&[], false), None);
+        self.module.add_function("nexa_exception_value", i8_ptr.fn_type(&[], false), None);
+
+        // setjmp 会返回两次，必须标记 returns_twice，避免优化破坏局部变量
+        let setjmp =
+            self.module.add_function("setjmp", i32_type.fn_type(&[i8_ptr.into()], false), None);
+        let returns_twice = self
+            .context
+            .create_enum_attribute(Attribute::get_named_enum_kind_id("returns_twice"), 0);
+        setjmp.add_attribute(AttributeLoc::Function, returns_twice);
     }
 }
 
@@ -349,67 +603,147 @@ impl From<inkwell::builder::BuilderError> for CodegenError {
 impl<'ctx> CodeGenerator<'ctx> {
     /// 生成程序
     pub fn generate_program(&mut self, program: &Program) -> Result<(), CodegenError> {
-        // 处理导入声明
-        for import in &program.imports {
-            self.handle_import(import)?;
+        // 导入的 struct / class 在本模块中同样需要类型布局
+        // 同一 struct 可能同时经命名导入和命名空间导入，只生成一次
+        let mut imported_structs: Vec<(String, StructDefinition)> = Vec::new();
+        for (path, local, symbol) in self.imported_symbol_list() {
+            match symbol {
+                nexa_parser::module::ExportedSymbol::Struct(def) => {
+                    let defined = program.structs.iter().any(|s| s.name == def.name)
+                        || imported_structs.iter().any(|(_, s)| s.name == def.name);
+                    if !defined {
+                        imported_structs.push((path, def));
+                    }
+                },
+                nexa_parser::module::ExportedSymbol::Interface(def)
+                    if !def.type_parameters.is_empty() =>
+                {
+                    self.generic_interfaces.insert(def.name.clone(), def);
+                },
+                nexa_parser::module::ExportedSymbol::Interface(def) => {
+                    self.interface_defs.insert(def.name.clone(), def);
+                },
+                nexa_parser::module::ExportedSymbol::Enum(def) => {
+                    self.enum_defs.insert(local, def);
+                },
+                nexa_parser::module::ExportedSymbol::TypeAlias(def) => {
+                    self.type_aliases.insert(local, def.ty);
+                },
+                _ => {},
+            }
+        }
+
+        // 泛型定义在用到时按类型实参实例化，不直接生成
+        let all_structs = program.structs.iter().chain(imported_structs.iter().map(|(_, d)| d));
+        let (generic_structs, all_structs): (Vec<_>, Vec<_>) =
+            all_structs.partition(|s| !s.type_parameters.is_empty());
+        for struct_def in generic_structs {
+            self.generic_structs.insert(struct_def.name.clone(), struct_def.clone());
+        }
+        for func in program.functions.iter().filter(|f| !f.type_parameters.is_empty()) {
+            self.generic_functions.insert(func.name.clone(), func.clone());
+        }
+        for struct_def in all_structs.iter().filter(|s| s.is_class) {
+            self.class_defs.insert(struct_def.name.clone(), (*struct_def).clone());
+        }
+        for interface in &program.interfaces {
+            if interface.type_parameters.is_empty() {
+                self.interface_defs.insert(interface.name.clone(), interface.clone());
+            } else {
+                self.generic_interfaces.insert(interface.name.clone(), interface.clone());
+            }
+        }
+        for enum_def in &program.enums {
+            self.enum_defs.insert(enum_def.name.clone(), enum_def.clone());
+        }
+        for alias in &program.type_aliases {
+            self.type_aliases.insert(alias.name.clone(), alias.ty.clone());
+        }
+
+        // 声明中用到的泛型实例 (如 `items: List<number>`) 替换为实例类型
+        let names: Vec<String> = self.interface_defs.keys().cloned().collect();
+        for name in names {
+            let interface = self.concrete_interface(&self.interface_defs[&name].clone())?;
+            self.interface_defs.insert(name, interface);
+        }
+        let mut struct_defs = Vec::new();
+        for struct_def in all_structs {
+            let struct_def = self.concrete_struct(struct_def)?;
+            if struct_def.is_class {
+                self.class_defs.insert(struct_def.name.clone(), struct_def.clone());
+            }
+            struct_defs.push(struct_def);
+        }
+        let mut functions = Vec::new();
+        for func in program.functions.iter().filter(|f| f.type_parameters.is_empty()) {
+            functions.push(self.concrete_signature(func)?);
         }
 
         // 生成 struct 定义
-        for struct_def in &program.structs {
+        for struct_def in &struct_defs {
             self.generate_struct_definition(struct_def)?;
         }
+        self.declare_imported_globals();
+
+        // class 的构造函数和方法以隐式的 this 指针作为第一个参数
+        let local_classes: Vec<&StructDefinition> = struct_defs
+            .iter()
+            .filter(|s| s.is_class && program.structs.iter().any(|d| d.name == s.name))
+            .collect();
+        let methods: Vec<Function> = local_classes
+            .iter()
+            .flat_map(|s| {
+                s.constructor.iter().chain(&s.methods).map(|m| Self::bind_this(&s.name, m))
+            })
+            .collect();
 
         // 生成函数声明 (包括方法)
-        for func in &program.functions {
+        for func in functions.iter().chain(&methods) {
             self.generate_function_declaration(func)?;
         }
-        for func in &program.methods {
-            self.generate_function_declaration(func)?;
+        self.declare_imported_functions();
+        for (path, def) in imported_structs.iter().filter(|(_, d)| d.is_class) {
+            if def.type_parameters.is_empty() {
+                self.declare_imported_class(path, &self.class_defs[&def.name].clone());
+            }
+        }
+
+        for struct_def in local_classes {
+            self.generate_vtable(&struct_def.name)?;
+        }
+
+        // 顶层变量声明为全局变量，其余顶层语句放入模块初始化函数
+        // 非入口模块总是生成初始化函数，入口模块的 main 会依次调用
+        let init_statements = self.declare_globals(program)?;
+        if !init_statements.is_empty() || self.library {
+            let fn_type = self.context.void_type().fn_type(&[], false);
+            let linkage = if self.library { None } else { Some(Linkage::Internal) };
+            let init = self.module.add_function(MODULE_INIT, fn_type, linkage);
+            self.module_init = Some(init);
         }
 
         // 生成函数体 (包括方法)
-        for func in &program.functions {
+        for func in functions.iter().chain(&methods) {
             self.generate_function(func)?;
         }
-        for func in &program.methods {
-            self.generate_function(func)?;
+
+        if let Some(init) = self.module_init {
+            self.generate_module_init(init, &init_statements)?;
+            // 没有 main 的脚本式程序生成一个只执行顶层语句的 main
+            if !self.library && self.module.get_function("main").is_none() {
+                self.generate_script_main()?;
+            }
         }
+        self.generate_instances()?;
 
         // 处理导出声明
         for export in &program.exports {
             self.handle_export(export)?;
         }
+        self.internalize_private_definitions(program);
 
-        Ok(())
-    }
-
-    /// 处理导入声明
-    fn handle_import(&mut self, import: &ImportDeclaration) -> Result<(), CodegenError> {
-        // 对于标准库导入，声明外部函数
-        // 格式: std.io.println -> 调用 C 运行时函数
-        if import.module_path.starts_with("std.") {
-            let parts: Vec<&str> = import.module_path.split('.').collect();
-            if parts.len() >= 2 {
-                let module = parts[1];
-
-                // 为每个导入的符号创建外部函数声明
-                for spec in &import.imports {
-                    let fn_name = format!("{}_{}", module, spec.name);
-                    let i8_ptr = self.context.ptr_type(AddressSpace::default());
-                    let i32_type = self.context.i32_type();
-                    let fn_type = i32_type.fn_type(&[i8_ptr.into()], false);
-
-                    // 检查函数是否已存在
-                    if self.module.get_function(&fn_name).is_none() {
-                        self.module.add_function(&fn_name, fn_type, None);
-                    }
-                }
-
-                // 处理命名空间导入 (import * as ns from "std/io")
-                if let Some(_alias) = &import.alias {
-                    // 命名空间导入暂不处理
-                }
-            }
+        if let Some(path) = self.module_path.clone() {
+            self.mangle_definitions(&path);
         }
 
         Ok(())
@@ -427,194 +761,1092 @@ impl<'ctx> CodeGenerator<'ctx> {
                     }
                 }
             },
-            ExportKind::Default(_expr) => {
-                // export default 表达式 - 暂不处理
+            ExportKind::Default(name) => {
+                // export default 导出的是模块中声明的名称，导入方按该名称链接
+                if let Some(fn_value) = self.module.get_function(name) {
+                    fn_value.set_linkage(Linkage::External);
+                }
             },
             ExportKind::ReExport(_module_path) => {
-                // export * from "module" - 暂不处理
+                // export * from "module" - 符号由定义它的模块生成，导入方直接链接到该模块
             },
         }
 
         Ok(())
     }
 
-    /// 生成 struct 定义
-    fn generate_struct_definition(
+    /// 为顶层变量声明创建全局变量，返回需要在模块初始化函数中执行的语句
+    ///
+    /// 初始化表达式是字面量时直接作为全局变量的常量初值，否则全局变量先清零，
+    /// 由初始化函数按源码顺序赋值。
+    fn declare_globals<'a>(
         &mut self,
-        struct_def: &StructDefinition,
-    ) -> Result<(), CodegenError> {
-        let field_types: Vec<inkwell::types::BasicTypeEnum> =
-            struct_def.fields.iter().map(|field| self.map_type(&field.field_type)).collect();
+        program: &'a Program,
+    ) -> Result<Vec<&'a Statement>, CodegenError> {
+        let mut init_statements = Vec::new();
+        for stmt in &program.statements {
+            let Statement::VariableDeclaration { name, initializer, mutable, span, .. } = stmt
+            else {
+                init_statements.push(stmt);
+                continue;
+            };
+
+            let ty = self
+                .binding_type(span)?
+                .as_ref()
+                .map(|t| self.map_type(t))
+                .unwrap_or_else(|| self.context.i32_type().into());
+
+            // 导出的变量由导入它的模块以外部全局变量引用
+            let global = self.module.add_global(ty, None, name);
+            if !Self::is_exported(program, name) {
+                global.set_linkage(Linkage::Internal);
+            }
+            let constant =
+                initializer.as_ref().and_then(|init| self.constant_initializer(init, ty));
+            match constant {
+                Some(value) => {
+                    global.set_initializer(&value);
+                    global.set_constant(!mutable);
+                },
+                None => {
+                    global.set_initializer(&ty.const_zero());
+                    if initializer.is_some() {
+                        init_statements.push(stmt);
+                    }
+                },
+            }
+            self.globals.insert(name.clone(), (global.as_pointer_value(), ty));
+        }
+        Ok(init_statements)
+    }
 
-        let struct_type = self.context.opaque_struct_type(&struct_def.name);
-        struct_type.set_body(&field_types, false);
+    /// 顶层声明是否对其他模块可见 (与 ModuleLoader 的导出规则一致)
+    fn is_exported(program: &Program, name: &str) -> bool {
+        program.exports.iter().any(|export| match &export.kind {
+            ExportKind::Named(specs) => specs.iter().any(|s| s.name == name),
+            ExportKind::Default(default) => default == name,
+            ExportKind::ReExport(_) => false,
+        })
+    }
 
-        self.struct_types.insert(struct_def.name.clone(), struct_type);
+    /// 未导出的函数、class 方法和虚表改为内部链接，不进入其他模块的符号空间
+    fn internalize_private_definitions(&self, program: &Program) {
+        for func in &program.functions {
+            // 入口模块的 main 是程序入口
+            let entry_main = func.name == "main" && !self.library;
+            if entry_main || func.is_declare || Self::is_exported(program, &func.name) {
+                continue;
+            }
+            if let Some(fn_value) = self.module.get_function(&func.name) {
+                fn_value.set_linkage(Linkage::Internal);
+            }
+        }
+        for class in program.structs.iter().filter(|s| s.is_class) {
+            if Self::is_exported(program, &class.name) {
+                continue;
+            }
+            for method in class.constructor.iter().chain(&class.methods) {
+                if let Some(fn_value) = self.module.get_function(&method.name) {
+                    fn_value.set_linkage(Linkage::Internal);
+                }
+            }
+            let vtable = self.module.get_global(&format!("__vtable_{}", class.name));
+            if let Some(vtable) = vtable.filter(|v| v.get_linkage() == Linkage::External) {
+                vtable.set_linkage(Linkage::Internal);
+            }
+        }
+    }
 
-        // 存储字段列表
-        let field_names: Vec<String> = struct_def.fields.iter().map(|f| f.name.clone()).collect();
-        self.struct_field_lists.insert(struct_def.name.clone(), field_names);
+    /// 所有导入的符号: (模块路径, 本地名称, 符号)，按模块路径和名称排序以保证生成顺序确定
+    ///
+    /// 命名空间导入的成员以 `ns.member` 作为本地名称。
+    fn imported_symbol_list(&self) -> Vec<(String, String, nexa_parser::module::ExportedSymbol)> {
+        let mut list = Vec::new();
+        for (path, symbols) in &self.imported_symbols {
+            for local_name in symbols.symbols() {
+                if let Some(symbol) = symbols.find(&local_name) {
+                    list.push((path.clone(), local_name, symbol));
+                }
+            }
+            for alias in symbols.namespaces() {
+                for (member, symbol) in symbols.namespace(&alias).into_iter().flatten() {
+                    list.push((path.clone(), format!("{}.{}", alias, member), symbol.clone()));
+                }
+            }
+        }
+        list.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        list
+    }
 
-        Ok(())
+    /// 将导入的变量声明为外部全局变量，由导出它的模块定义
+    fn declare_imported_globals(&mut self) {
+        for (path, local_name, symbol) in self.imported_symbol_list() {
+            let nexa_parser::module::ExportedSymbol::Variable(name, ty, _) = symbol else {
+                continue;
+            };
+            let link_name = mangle_symbol(&path, &name);
+            let ty = self.map_type(&ty);
+            let global = match self.module.get_global(&link_name) {
+                Some(global) => global,
+                None => self.module.add_global(ty, None, &link_name),
+            };
+            self.globals.insert(local_name, (global.as_pointer_value(), ty));
+        }
     }
 
-    /// 生成函数声明
-    fn generate_function_declaration(&mut self, func: &Function) -> Result<(), CodegenError> {
-        let param_types: Vec<inkwell::types::BasicMetadataTypeEnum> =
-            func.parameters.iter().map(|p| self.map_type(&p.type_annotation).into()).collect();
+    /// 声明导入的函数，调用时按本地名称查找
+    ///
+    /// 导入的泛型函数没有可链接的定义，由本模块按用到的类型实参各自实例化。
+    fn declare_imported_functions(&mut self) {
+        for (path, local_name, symbol) in self.imported_symbol_list() {
+            let nexa_parser::module::ExportedSymbol::Function(func) = symbol else { continue };
+            if func.type_parameters.is_empty() {
+                self.declare_external_function(&path, &local_name, &func);
+            } else {
+                self.generic_functions.insert(local_name, *func);
+            }
+        }
+    }
 
-        let return_type = self.map_type(&func.return_type);
-        let fn_type = return_type.fn_type(&param_types, false);
+    /// 声明导入 class 的构造函数、方法和虚表，它们由导出模块定义
+    fn declare_imported_class(&mut self, path: &str, class: &StructDefinition) {
+        for method in class.constructor.iter().chain(&class.methods) {
+            let method = Self::bind_this(&class.name, method);
+            self.declare_external_function(path, &method.name, &method);
+        }
 
-        self.module.add_function(&func.name, fn_type, None);
-        Ok(())
+        let vtable = format!("__vtable_{}", class.name);
+        let link_name = mangle_symbol(path, &vtable);
+        if self.module.get_global(&link_name).is_none() {
+            let ptr_type = self.context.ptr_type(AddressSpace::default());
+            let slots = self.vtable_slots(&class.name).len() as u32;
+            self.module.add_global(ptr_type.array_type(slots), None, &link_name);
+        }
+        self.link_names.insert(vtable, link_name);
     }
 
-    /// 生成函数体
-    fn generate_function(&mut self, func: &Function) -> Result<(), CodegenError> {
-        let fn_value = self
-            .module
-            .get_function(&func.name)
-            .ok_or_else(|| CodegenError { message: format!("Function {} not found", func.name) })?;
+    /// 以导出模块中的链接名声明外部函数
+    fn declare_external_function(&mut self, path: &str, local_name: &str, func: &Function) {
+        // declare function 由运行时以原名提供
+        let link_name =
+            if func.is_declare { func.name.clone() } else { mangle_symbol(path, &func.name) };
+        if self.module.get_function(&link_name).is_none() {
+            let param_types: Vec<inkwell::types::BasicMetadataTypeEnum> =
+                func.parameters.iter().map(|p| self.map_type(&p.type_annotation).into()).collect();
+            let fn_type = self.map_type(&func.return_type).fn_type(&param_types, false);
+            self.module.add_function(&link_name, fn_type, None);
+        }
+        self.link_names.insert(local_name.to_string(), link_name);
+    }
 
-        // 创建 entry 块
-        let entry = self.context.append_basic_block(fn_value, "entry");
-        self.builder().position_at_end(entry);
+    /// 将字面量初始化表达式求值为常量
+    fn constant_initializer(
+        &mut self,
+        init: &Expression,
+        ty: BasicTypeEnum<'ctx>,
+    ) -> Option<BasicValueEnum<'ctx>> {
+        match (init, ty) {
+            (Expression::Number(n, _), BasicTypeEnum::IntType(t)) => {
+                Some(t.const_int(*n as u64, true).into())
+            },
+            (Expression::Number(n, _), BasicTypeEnum::FloatType(t)) => {
+                Some(t.const_float(*n as f64).into())
+            },
+            (Expression::Float(f, _), BasicTypeEnum::FloatType(t)) => {
+                Some(t.const_float(*f).into())
+            },
+            (Expression::Boolean(b, _), BasicTypeEnum::IntType(t)) => {
+                Some(t.const_int(*b as u64, false).into())
+            },
+            (Expression::Null(_) | Expression::Undefined(_), ty) => Some(ty.const_zero()),
+            (Expression::String(s, _), BasicTypeEnum::PointerType(_)) => {
+                let text = self.context.const_string(s.as_bytes(), true);
+                let global = self.module.add_global(text.get_type(), None, "str");
+                global.set_initializer(&text);
+                global.set_constant(true);
+                global.set_linkage(Linkage::Private);
+                Some(global.as_pointer_value().into())
+            },
+            (Expression::Unary { op: UnaryOp::Negate, operand, .. }, _) => {
+                match self.constant_initializer(operand, ty)? {
+                    BasicValueEnum::IntValue(v) => Some(v.const_neg().into()),
+                    BasicValueEnum::FloatValue(v) => {
+                        let value = v.get_constant()?.0;
+                        Some(v.get_type().const_float(-value).into())
+                    },
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
 
-        // 清空局部变量
+    /// 生成模块初始化函数：按源码顺序执行非常量的全局变量初始化和其余顶层语句
+    fn generate_module_init(
+        &mut self,
+        init: FunctionValue<'ctx>,
+        statements: &[&Statement],
+    ) -> Result<(), CodegenError> {
+        let entry = self.context.append_basic_block(init, "entry");
+        self.builder().position_at_end(entry);
         self.clear_variables();
+        self.cells = nexa_parser::cell_variables(&[], statements);
+        self.set_function(init);
+        self.return_type = Some(Type::Void);
 
-        // 设置当前函数
-        self.set_function(fn_value);
+        for stmt in statements {
+            if self.is_terminated() {
+                break;
+            }
+            match stmt {
+                Statement::VariableDeclaration { name, initializer: Some(init), span, .. } => {
+                    let declared_type = self.binding_type(span)?;
+                    let value = self.generate_coerced(init, declared_type.as_ref())?;
+                    let (ptr, _) = self.globals[name];
+                    self.builder().build_store(ptr, value)?;
+                },
+                other => self.generate_statement(other)?,
+            }
+        }
 
-        // 为参数创建 alloca
-        for (i, param) in func.parameters.iter().enumerate() {
-            let param_value = fn_value.get_nth_param(i as u32).unwrap();
-            let alloca = self.builder().build_alloca(param_value.get_type(), &param.name)?;
-            // 将参数值存储到 alloca - 需要先转换为 BasicValue
-            let basic_value = param_value;
-            self.builder().build_store(alloca, basic_value)?;
-            self.add_variable(param.name.clone(), alloca);
-            // 保存参数类型
-            self.set_variable_type(&param.name, param_value.get_type());
+        if !self.is_terminated() {
+            self.builder().build_return(None)?;
         }
+        Ok(())
+    }
 
-        // 生成函数体
-        self.generate_statement(&func.body)?;
+    /// 为没有 main 函数的程序生成入口
+    fn generate_script_main(&mut self) -> Result<(), CodegenError> {
+        let i32_type = self.context.i32_type();
+        let main = self.module.add_function("main", i32_type.fn_type(&[], false), None);
+        let entry = self.context.append_basic_block(main, "entry");
+        self.builder().position_at_end(entry);
+        self.call_module_inits()?;
+        self.builder().build_return(Some(&i32_type.const_zero()))?;
+        Ok(())
+    }
 
-        // 如果函数没有返回语句，添加一个默认返回
-        let current_block = self.builder().get_insert_block().unwrap();
-        if current_block.get_terminator().is_none() {
-            // 统一返回 i32
-            let default = self.context.i32_type().const_int(0, false);
-            self.builder().build_return(Some(&default))?;
+    /// 依次执行依赖模块和本模块的顶层语句
+    fn call_module_inits(&mut self) -> Result<(), CodegenError> {
+        let fn_type = self.context.void_type().fn_type(&[], false);
+        for name in self.dependency_inits.clone() {
+            let init = match self.module.get_function(&name) {
+                Some(init) => init,
+                None => self.module.add_function(&name, fn_type, None),
+            };
+            self.builder().build_call(init, &[], "")?;
+        }
+        if let Some(init) = self.module_init {
+            self.builder().build_call(init, &[], "")?;
         }
-
         Ok(())
     }
 
-    /// 生成语句
-    pub fn generate_statement(&mut self, stmt: &Statement) -> Result<(), CodegenError> {
-        match stmt {
-            Statement::Block(stmts, _) => {
-                for s in stmts {
-                    self.generate_statement(s)?;
+    /// 外部可见的定义改用带模块路径的链接名
+    ///
+    /// 名称改动只影响符号表，模块内对这些定义的引用不受影响。
+    fn mangle_definitions(&mut self, module_path: &str) {
+        for function in self.module.get_functions() {
+            if function.count_basic_blocks() > 0 && function.get_linkage() == Linkage::External {
+                let name = function.get_name().to_string_lossy().into_owned();
+                // 入口模块的 main 是程序入口，保留原名
+                if name == "main" && !self.library {
+                    continue;
                 }
-            },
-            Statement::VariableDeclaration {
-                name,
-                type_annotation,
-                initializer,
-                mutable: _,
-                span: _,
-            } => {
-                // 首先检查是否有类型注解
-                let type_from_annotation = if let Some(Type::Struct(struct_name)) = type_annotation
-                {
-                    Some(struct_name.clone())
-                } else {
-                    None
-                };
+                function.as_global_value().set_name(&mangle_symbol(module_path, &name));
+            }
+        }
+        for global in self.module.get_globals() {
+            if global.get_initializer().is_some() && global.get_linkage() == Linkage::External {
+                let name = global.get_name().to_string_lossy().into_owned();
+                global.set_name(&mangle_symbol(module_path, &name));
+            }
+        }
+    }
 
-                // 尝试从初始化表达式中推断 struct 类型
-                let type_from_init = if let Some(Expression::StructLiteral {
-                    name: struct_name,
-                    ..
-                }) = initializer
-                {
-                    Some(struct_name.clone())
-                } else if let Some(Expression::New { type_name: struct_name, .. }) = initializer {
-                    Some(struct_name.clone())
-                } else {
-                    None
-                };
+    /// 生成 struct 定义
+    fn generate_struct_definition(
+        &mut self,
+        struct_def: &StructDefinition,
+    ) -> Result<(), CodegenError> {
+        // class 对象以虚表指针开头，随后依次是从根类开始的各层字段
+        let fields: Vec<StructField> = if struct_def.is_class {
+            let chain = self.class_chain(&struct_def.name);
+            chain.into_iter().rev().flat_map(|def| def.fields.clone()).collect()
+        } else {
+            struct_def.fields.clone()
+        };
+        let mut field_types: Vec<inkwell::types::BasicTypeEnum> = Vec::new();
+        let mut field_names: Vec<(String, Type)> = Vec::new();
+        if struct_def.is_class {
+            field_types.push(self.context.ptr_type(AddressSpace::default()).into());
+            field_names.push((VTABLE_FIELD.to_string(), Type::Any));
+        }
+        for field in &fields {
+            field_types.push(self.map_type(&field.field_type));
+            field_names.push((field.name.clone(), field.field_type.clone()));
+        }
 
-                // 优先使用类型注解，如果没有则使用推断的类型
-                let struct_name = type_from_annotation.or(type_from_init);
+        // 泛型实例在替换字段类型前已登记了同名的不透明类型
+        let struct_type = match self.struct_types.get(&struct_def.name) {
+            Some(struct_type) => *struct_type,
+            None => self.context.opaque_struct_type(&struct_def.name),
+        };
+        struct_type.set_body(&field_types, false);
 
-                if let Some(struct_name) = struct_name {
-                    // 对于 struct 类型，需要获取 struct 类型并分配内存
-                    // 创建一个虚拟的字段列表用于获取 struct 类型
-                    let dummy_fields = vec![];
-                    let struct_type =
-                        self.get_or_create_struct_type(&struct_name, &dummy_fields)?;
+        self.struct_types.insert(struct_def.name.clone(), struct_type);
 
-                    // 创建局部变量（指针）
-                    let alloca = self.builder().build_alloca(struct_type, name)?;
+        // 存储字段列表
+        self.struct_field_lists.insert(struct_def.name.clone(), field_names);
 
-                    // 初始化
-                    if let Some(init) = initializer {
-                        let value = self.generate_expression(init)?;
-                        // 存储 struct 值
-                        self.builder().build_store(alloca, value)?;
-                    }
+        Ok(())
+    }
 
-                    self.add_variable(name.clone(), alloca);
-                    // 保存结构体类型信息（应该是指针类型）
-                    let ptr_type = self.context.ptr_type(AddressSpace::default());
-                    self.set_variable_type(name, ptr_type.into());
-                } else {
-                    // 检查是否有类型注解
-                    let has_string_type = type_annotation
-                        .as_ref()
-                        .map(|t| matches!(t, Type::String))
-                        .unwrap_or(false);
+    /// class 的继承链，从自身开始直到根类
+    fn class_chain(&self, class: &str) -> Vec<&StructDefinition> {
+        let mut chain: Vec<&StructDefinition> = Vec::new();
+        let mut current = self.class_defs.get(class);
+        while let Some(def) = current {
+            if chain.iter().any(|d| d.name == def.name) {
+                break;
+            }
+            chain.push(def);
+            current = def.extends.as_ref().and_then(|base| self.class_defs.get(base));
+        }
+        chain
+    }
+
+    /// 计算虚表槽位: (方法名, 实现函数名)
+    ///
+    /// 槽位从根类开始排列，子类重写的方法替换父类槽位中的实现，新方法追加在末尾。
+    fn vtable_slots(&self, class: &str) -> Vec<(String, String)> {
+        let mut slots: Vec<(String, String)> = Vec::new();
+        for def in self.class_chain(class).into_iter().rev() {
+            for method in &def.methods {
+                let member = method.name[def.name.len() + 1..].to_string();
+                match slots.iter_mut().find(|(m, _)| *m == member) {
+                    Some(slot) => slot.1 = method.name.clone(),
+                    None => slots.push((member, method.name.clone())),
+                }
+            }
+        }
+        slots
+    }
+
+    /// 生成 class 的虚表全局常量 `__vtable_{class}`
+    fn generate_vtable(&mut self, class: &str) -> Result<(), CodegenError> {
+        let mut entries = Vec::new();
+        for (_, function) in self.vtable_slots(class) {
+            let fn_value = self.module.get_function(&function).ok_or_else(|| CodegenError {
+                message: format!("Function {} not found", function),
+            })?;
+            entries.push(fn_value.as_global_value().as_pointer_value());
+        }
+
+        let table = self.context.ptr_type(AddressSpace::default()).const_array(&entries);
+        // 泛型 class 实例的虚表先声明，在所有方法声明之后才填充
+        let name = format!("__vtable_{}", class);
+        let global = match self.module.get_global(&name) {
+            Some(global) => global,
+            None => self.module.add_global(table.get_type(), None, &name),
+        };
+        global.set_initializer(&table);
+        global.set_constant(true);
+        // 模块可能被其他模块导入时，虚表供导入该 class 的模块使用
+        if self.module_path.is_none() {
+            global.set_linkage(Linkage::Private);
+        }
+        Ok(())
+    }
+
+    /// 沿继承链查找构造函数及其参数类型
+    fn find_constructor(&self, class: &str) -> Option<(FunctionValue<'ctx>, Vec<Type>)> {
+        self.class_chain(class).into_iter().find_map(|def| {
+            let constructor = def.constructor.as_ref()?;
+            let params = constructor.parameters.iter().map(|p| p.type_annotation.clone());
+            Some((self.lookup_function(&constructor.name)?, params.collect()))
+        })
+    }
+
+    /// 生成 class 方法调用
+    ///
+    /// `super(...)` 和 `super.method()` 静态绑定到父类实现，其余方法经虚表分派。
+    /// 不是方法调用时返回 `None`。
+    fn generate_method_call(
+        &mut self,
+        callee: &Expression,
+        arguments: &[Expression],
+    ) -> Result<Option<BasicValueEnum<'ctx>>, CodegenError> {
+        let (function, this, slot) = match callee {
+            Expression::Super(_) => {
+                let base = self.struct_name_of(callee)?.ok_or_else(|| CodegenError {
+                    message: "'super' used outside of a derived class".to_string(),
+                })?;
+                let Some((constructor, params)) = self.find_constructor(&base) else {
+                    return Ok(Some(self.context.i32_type().const_zero().into()));
+                };
+                let this = self.generate_expression(callee)?.into_pointer_value();
+                let mut args: Vec<inkwell::values::BasicMetadataValueEnum> = vec![this.into()];
+                for (arg, param) in arguments.iter().zip(&params) {
+                    args.push(self.generate_coerced(arg, Some(param))?.into());
+                }
+                self.builder().build_call(constructor, &args, "")?;
+                return Ok(Some(self.context.i32_type().const_zero().into()));
+            },
+            Expression::Member { object, member, .. } => {
+                if let Some(iface) =
+                    self.struct_name_of(object)?.filter(|n| self.interface_defs.contains_key(n))
+                {
+                    return self
+                        .generate_interface_call(object, &iface, member, callee, arguments)
+                        .map(Some);
+                }
+                let Some(class) = self.struct_name_of(object)?.filter(|c| self.is_class(c)) else {
+                    return Ok(None);
+                };
+                let slots = self.vtable_slots(&class);
+                let Some(index) = slots.iter().position(|(m, _)| m == member) else {
+                    return Ok(None);
+                };
+                let function = self.lookup_function(&slots[index].1).ok_or_else(|| {
+                    CodegenError { message: format!("Function {} not found", slots[index].1) }
+                })?;
+                let this = self.generate_expression(object)?.into_pointer_value();
+                let slot = if matches!(object.as_ref(), Expression::Super(_)) {
+                    None
+                } else {
+                    Some((class, index))
+                };
+                (function, this, slot)
+            },
+            _ => return Ok(None),
+        };
+
+        let mut args: Vec<inkwell::values::BasicMetadataValueEnum> = vec![this.into()];
+        args.extend(self.generate_arguments(callee, arguments)?);
+
+        let call = match slot {
+            Some((class, index)) => {
+                // 从对象头部取出虚表，按槽位加载函数指针
+                let ptr_type = self.context.ptr_type(AddressSpace::default());
+                let (struct_type, vtable_index) =
+                    self.struct_field(&class, VTABLE_FIELD).ok_or_else(|| CodegenError {
+                        message: format!("Class {} has no vtable", class),
+                    })?;
+                let vtable_field = self.builder().build_struct_gep(
+                    struct_type,
+                    this,
+                    vtable_index,
+                    "vtable_ptr",
+                )?;
+                let vtable = self
+                    .builder()
+                    .build_load(ptr_type, vtable_field, "vtable")?
+                    .into_pointer_value();
+                let index = self.context.i32_type().const_int(index as u64, false);
+                let entry = unsafe {
+                    self.builder().build_in_bounds_gep(ptr_type, vtable, &[index], "vtable_slot")?
+                };
+                let fn_ptr =
+                    self.builder().build_load(ptr_type, entry, "method")?.into_pointer_value();
+                self.builder().build_indirect_call(function.get_type(), fn_ptr, &args, "call")?
+            },
+            None => self.builder().build_call(function, &args, "call")?,
+        };
+
+        match call.try_as_basic_value() {
+            inkwell::values::ValueKind::Basic(value) => Ok(Some(value)),
+            _ => Ok(Some(self.context.i32_type().const_zero().into())),
+        }
+    }
+
+    /// 接口值的 LLVM 类型: {对象指针, 接口表指针}
+    fn interface_type(&self) -> inkwell::types::StructType<'ctx> {
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+        self.context.struct_type(&[ptr_type.into(), ptr_type.into()], false)
+    }
+
+    /// 获取 class 实现接口时使用的接口表 `__itable_{class}_{iface}`
+    ///
+    /// 接口表是 i64 数组：先是各接口字段在对象中的字节偏移，再是各接口方法在虚表中的槽位。
+    /// 子类的对象布局和虚表都以父类为前缀，因此按静态类型生成的接口表对子类对象同样适用。
+    fn interface_table(
+        &mut self,
+        class: &str,
+        iface: &str,
+    ) -> Result<PointerValue<'ctx>, CodegenError> {
+        let name = format!("__itable_{}_{}", class, iface);
+        if let Some(global) = self.module.get_global(&name) {
+            return Ok(global.as_pointer_value());
+        }
+
+        let interface =
+            self.interface_defs.get(iface).cloned().ok_or_else(|| CodegenError {
+                message: format!("Interface {} not found", iface),
+            })?;
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+
+        let mut entries = Vec::new();
+        for field in &interface.fields {
+            let (struct_type, index) = self.struct_field(class, &field.name).ok_or_else(|| {
+                CodegenError { message: format!("Class {} has no field {}", class, field.name) }
+            })?;
+            // 以空指针为基址的常量 GEP 得到字段偏移
+            let indices = [i32_type.const_zero(), i32_type.const_int(index as u64, false)];
+            let field_ptr = unsafe { ptr_type.const_null().const_gep(struct_type, &indices) };
+            entries.push(field_ptr.const_to_int(i64_type));
+        }
+        let slots = self.vtable_slots(class);
+        for method in &interface.methods {
+            let slot = slots.iter().position(|(m, _)| *m == method.name).ok_or_else(|| {
+                CodegenError { message: format!("Class {} has no method {}", class, method.name) }
+            })?;
+            entries.push(i64_type.const_int(slot as u64, false));
+        }
+
+        let table = i64_type.const_array(&entries);
+        let global = self.module.add_global(table.get_type(), None, &name);
+        global.set_initializer(&table);
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+        Ok(global.as_pointer_value())
+    }
+
+    /// 生成表达式并按目标类型转换
+    fn generate_coerced(
+        &mut self,
+        expr: &Expression,
+        target: Option<&Type>,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let value = self.generate_expression(expr)?;
+        let source = self.expression_type(expr)?;
+        self.coerce_value(value, source.as_ref(), target)
+    }
+
+    /// 值的类型转换：class 对象赋给接口类型时组装为接口值 (收窄时反向取出对象)，
+    /// 值放入联合类型时加上标签，从联合类型取出时读取负载
+    fn coerce_value(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        source: Option<&Type>,
+        target: Option<&Type>,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let target = target.map(|t| self.resolve_type(t));
+        let source = source.map(|t| self.resolve_type(t));
+        if let (Some(source), Some(target @ Type::Union(_))) = (&source, &target) {
+            return self.coerce_to_union(value, source, target);
+        }
+        if let (Some(source @ Type::Union(_)), Some(target)) = (&source, &target) {
+            if self.tagged_members(source).is_some() {
+                return self.union_payload(value, target);
+            }
+        }
+        // 数值类型之间的隐式拓宽 (i8 -> i32、f32 -> f64 等)
+        let numeric = (source.as_ref(), target.as_ref());
+        if let (Some(source), Some(target)) = numeric {
+            if let (Some(from), Some(to)) = (self.numeric_type(source), self.numeric_type(target)) {
+                if from != to {
+                    return self.build_numeric_cast(value, &from, &to);
+                }
+            }
+        }
+        if let (Some(Type::Struct(class)), Some(Type::Struct(iface))) = (&target, &source) {
+            if self.interface_defs.contains_key(iface) && self.is_class(class) {
+                // 接口值收窄为 class：取出对象指针
+                let value = value.into_struct_value();
+                return Ok(self.builder().build_extract_value(value, 0, "iface_data")?);
+            }
+        }
+        let (Some(Type::Struct(iface)), Some(Type::Struct(class))) = (&target, &source) else {
+            return Ok(value);
+        };
+        if iface == class || !self.interface_defs.contains_key(iface) {
+            return Ok(value);
+        }
+        if !self.is_class(class) {
+            return Err(CodegenError {
+                message: format!("Cannot convert {} to interface {}", class, iface),
+            });
+        }
+
+        let table = self.interface_table(class, iface)?;
+        let interface_type = self.interface_type();
+        let builder = self.builder();
+        let with_data =
+            builder.build_insert_value(interface_type.get_undef(), value, 0, "iface_data")?;
+        let with_table = builder.build_insert_value(with_data, table, 1, "iface")?;
+        Ok(with_table.as_basic_value_enum())
+    }
+
+    /// 按被调用者的参数类型生成实参
+    fn generate_arguments(
+        &mut self,
+        callee: &Expression,
+        arguments: &[Expression],
+    ) -> Result<Vec<inkwell::values::BasicMetadataValueEnum<'ctx>>, CodegenError> {
+        let params = match self.expression_type(callee)? {
+            Some(Type::Function(params, _)) => params,
+            _ => Vec::new(),
+        };
+        let mut values = Vec::new();
+        for (i, arg) in arguments.iter().enumerate() {
+            values.push(self.generate_coerced(arg, params.get(i))?.into());
+        }
+        Ok(values)
+    }
+
+    /// 接口值的字段地址：对象指针加上接口表中记录的字段偏移
+    fn interface_field_pointer(
+        &mut self,
+        object: &Expression,
+        iface: &str,
+        member: &str,
+    ) -> Result<(PointerValue<'ctx>, BasicTypeEnum<'ctx>), CodegenError> {
+        let field = self.interface_defs.get(iface).and_then(|def| {
+            def.fields
+                .iter()
+                .position(|f| f.name == member)
+                .map(|i| (i, def.fields[i].field_type.clone()))
+        });
+        let Some((index, field_type)) = field else {
+            return Err(CodegenError {
+                message: format!("Interface {} has no field {}", iface, member),
+            });
+        };
+
+        let (data, table) = self.split_interface_value(object)?;
+        let i8_type = self.context.i8_type();
+        let i64_type = self.context.i64_type();
+        let index = i64_type.const_int(index as u64, false);
+        let builder = self.builder();
+        let entry =
+            unsafe { builder.build_in_bounds_gep(i64_type, table, &[index], "field_entry")? };
+        let offset = builder.build_load(i64_type, entry, "field_offset")?.into_int_value();
+        let field_ptr = unsafe { builder.build_in_bounds_gep(i8_type, data, &[offset], member)? };
+        Ok((field_ptr, self.map_type(&field_type)))
+    }
+
+    /// 拆分接口值为 (对象指针, 接口表指针)
+    fn split_interface_value(
+        &mut self,
+        object: &Expression,
+    ) -> Result<(PointerValue<'ctx>, PointerValue<'ctx>), CodegenError> {
+        let value = self.generate_expression(object)?.into_struct_value();
+        let builder = self.builder();
+        let data = builder.build_extract_value(value, 0, "iface_data")?.into_pointer_value();
+        let table = builder.build_extract_value(value, 1, "iface_table")?.into_pointer_value();
+        Ok((data, table))
+    }
+
+    /// 经接口值调用方法
+    ///
+    /// 从接口表取出方法在虚表中的槽位，再从对象头部的虚表中取出实现，
+    /// 因此子类重写的方法同样生效。
+    fn generate_interface_call(
+        &mut self,
+        object: &Expression,
+        iface: &str,
+        member: &str,
+        callee: &Expression,
+        arguments: &[Expression],
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let interface =
+            self.interface_defs.get(iface).cloned().ok_or_else(|| CodegenError {
+                message: format!("Interface {} not found", iface),
+            })?;
+        let index = interface.methods.iter().position(|m| m.name == member).ok_or_else(|| {
+            CodegenError { message: format!("Interface {} has no method {}", iface, member) }
+        })?;
+        let method = &interface.methods[index];
+
+        let (data, table) = self.split_interface_value(object)?;
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+        let entry_index = i64_type.const_int((interface.fields.len() + index) as u64, false);
+        let builder = self.builder();
+        let entry = unsafe {
+            builder.build_in_bounds_gep(i64_type, table, &[entry_index], "method_entry")?
+        };
+        let slot = builder.build_load(i64_type, entry, "vtable_slot")?.into_int_value();
+        // 所有 class 对象的第一个字段都是虚表指针
+        let vtable = builder.build_load(ptr_type, data, "vtable")?.into_pointer_value();
+        let fn_entry =
+            unsafe { builder.build_in_bounds_gep(ptr_type, vtable, &[slot], "method_ptr")? };
+        let fn_ptr = builder.build_load(ptr_type, fn_entry, "method")?.into_pointer_value();
+
+        // 方法与闭包函数的签名形式相同：第一个参数为对象指针
+        let params: Vec<Type> =
+            method.parameters.iter().map(|p| p.type_annotation.clone()).collect();
+        let fn_type = self.closure_fn_type(&params, &method.return_type);
+        let mut args: Vec<inkwell::values::BasicMetadataValueEnum> = vec![data.into()];
+        args.extend(self.generate_arguments(callee, arguments)?);
+        let call = self.builder().build_indirect_call(fn_type, fn_ptr, &args, "call")?;
+
+        match call.try_as_basic_value() {
+            inkwell::values::ValueKind::Basic(value) => Ok(value),
+            _ => Ok(self.context.i32_type().const_zero().into()),
+        }
+    }
+
+    // ============ 泛型实例化 ============
+
+    /// 泛型实例的名称，如 `Box<number>`、`identity<string>`
+    fn instance_name(name: &str, args: &[Type]) -> String {
+        Type::Generic(name.to_string(), args.to_vec()).to_string().replace(' ', "")
+    }
+
+    /// 把类型中的类型参数替换为当前的类型实参，泛型类型替换为其实例
+    fn concrete_type(&mut self, ty: &Type) -> Result<Type, CodegenError> {
+        Ok(match ty.substitute(&self.type_arguments) {
+            Type::Generic(name, args) => {
+                let args = self.concrete_types(&args)?;
+                Type::Struct(self.instantiate_type(&name, &args)?)
+            },
+            Type::Array(elem) => Type::Array(Box::new(self.concrete_type(&elem)?)),
+            Type::Pointer(pointee) => Type::Pointer(Box::new(self.concrete_type(&pointee)?)),
+            Type::Function(params, ret) => {
+                Type::Function(self.concrete_types(&params)?, Box::new(self.concrete_type(&ret)?))
+            },
+            Type::Object(fields) => {
+                let mut concrete = Vec::new();
+                for (name, ty) in fields {
+                    concrete.push((name, self.concrete_type(&ty)?));
+                }
+                Type::Object(concrete)
+            },
+            Type::Union(types) => Type::Union(self.concrete_types(&types)?),
+            Type::Intersection(types) => Type::Intersection(self.concrete_types(&types)?),
+            other => other,
+        })
+    }
+
+    fn concrete_types(&mut self, types: &[Type]) -> Result<Vec<Type>, CodegenError> {
+        types.iter().map(|ty| self.concrete_type(ty)).collect()
+    }
+
+    /// 函数签名中的类型替换为具体类型
+    fn concrete_signature(&mut self, func: &Function) -> Result<Function, CodegenError> {
+        let mut func = func.clone();
+        for param in &mut func.parameters {
+            param.type_annotation = self.concrete_type(&param.type_annotation)?;
+        }
+        func.return_type = self.concrete_type(&func.return_type)?;
+        Ok(func)
+    }
 
-                    // 检查初始化表达式是否是函数调用返回字符串（readln()）
-                    let is_string_init = matches!(initializer, Some(Expression::Call { callee, .. }) if {
-                        if let Expression::Identifier(name, _) = callee.as_ref() {
-                            name == "readln"
+    /// struct / class 的字段和方法签名中的类型替换为具体类型
+    fn concrete_struct(
+        &mut self,
+        struct_def: &StructDefinition,
+    ) -> Result<StructDefinition, CodegenError> {
+        let mut struct_def = struct_def.clone();
+        for field in &mut struct_def.fields {
+            field.field_type = self.concrete_type(&field.field_type)?;
+        }
+        for method in &mut struct_def.methods {
+            *method = self.concrete_signature(method)?;
+        }
+        if let Some(constructor) = &mut struct_def.constructor {
+            *constructor = self.concrete_signature(constructor)?;
+        }
+        Ok(struct_def)
+    }
+
+    /// 接口成员的类型替换为具体类型
+    fn concrete_interface(
+        &mut self,
+        interface: &InterfaceDefinition,
+    ) -> Result<InterfaceDefinition, CodegenError> {
+        let mut interface = interface.clone();
+        for field in &mut interface.fields {
+            field.field_type = self.concrete_type(&field.field_type)?;
+        }
+        for method in &mut interface.methods {
+            for param in &mut method.parameters {
+                param.type_annotation = self.concrete_type(&param.type_annotation)?;
+            }
+            method.return_type = self.concrete_type(&method.return_type)?;
+        }
+        Ok(interface)
+    }
+
+    /// 以类型实参 `args` 实例化泛型 struct / class / 接口，返回实例名称
+    ///
+    /// 同一组类型实参只实例化一次。class 实例的构造函数和方法 (`Box<number>_get`)
+    /// 在此声明，函数体和虚表由 `generate_instances` 生成。
+    fn instantiate_type(&mut self, name: &str, args: &[Type]) -> Result<String, CodegenError> {
+        let instance = Self::instance_name(name, args);
+        if self.struct_types.contains_key(&instance) || self.interface_defs.contains_key(&instance)
+        {
+            return Ok(instance);
+        }
+
+        if let Some(generic) = self.generic_interfaces.get(name).cloned() {
+            let bindings = TypeParameter::bind(&generic.type_parameters, args);
+            let mut interface = generic;
+            interface.name = instance.clone();
+            interface.type_parameters.clear();
+            // 先登记实例，成员中引用自身的类型不再重复实例化
+            self.interface_defs.insert(instance.clone(), interface.clone());
+            let saved = std::mem::replace(&mut self.type_arguments, bindings);
+            let interface = self.concrete_interface(&interface);
+            self.type_arguments = saved;
+            self.interface_defs.insert(instance.clone(), interface?);
+            return Ok(instance);
+        }
+
+        let generic =
+            self.generic_structs.get(name).cloned().ok_or_else(|| CodegenError {
+                message: format!("Generic type {} not found", name),
+            })?;
+        let bindings = TypeParameter::bind(&generic.type_parameters, args);
+        let mut struct_def = generic;
+        struct_def.name = instance.clone();
+        struct_def.type_parameters.clear();
+        for method in &mut struct_def.methods {
+            method.name = format!("{}{}", instance, &method.name[name.len()..]);
+        }
+        if let Some(constructor) = &mut struct_def.constructor {
+            constructor.name = format!("{}_constructor", instance);
+        }
+        // 先登记实例，字段中引用自身的类型 (如 `next: Node<T>`) 不再重复实例化
+        self.struct_types.insert(instance.clone(), self.context.opaque_struct_type(&instance));
+        if struct_def.is_class {
+            self.class_defs.insert(instance.clone(), struct_def.clone());
+        }
+        let saved = std::mem::replace(&mut self.type_arguments, bindings.clone());
+        let struct_def = self.concrete_struct(&struct_def);
+        self.type_arguments = saved;
+        let struct_def = struct_def?;
+
+        if struct_def.is_class {
+            self.class_defs.insert(instance.clone(), struct_def.clone());
+        }
+        self.generate_struct_definition(&struct_def)?;
+        if struct_def.is_class {
+            for method in struct_def.constructor.iter().chain(&struct_def.methods) {
+                let method = Self::bind_this(&instance, method);
+                self.declare_instance(method, bindings.clone())?;
+            }
+            // 继承来的方法此时可能尚未声明，虚表在 generate_instances 中填充
+            let ptr_type = self.context.ptr_type(AddressSpace::default());
+            let slots = self.vtable_slots(&instance).len() as u32;
+            self.module.add_global(
+                ptr_type.array_type(slots),
+                None,
+                &format!("__vtable_{}", instance),
+            );
+            self.pending_vtables.push(instance.clone());
+        }
+        Ok(instance)
+    }
+
+    /// 以类型实参 `args` 实例化泛型函数，返回实例名称
+    fn instantiate_function(&mut self, name: &str, args: &[Type]) -> Result<String, CodegenError> {
+        let instance = Self::instance_name(name, args);
+        if self.module.get_function(&instance).is_some() {
+            return Ok(instance);
+        }
+        let generic = self.generic_functions.get(name).cloned().ok_or_else(|| CodegenError {
+            message: format!("Generic function {} not found", name),
+        })?;
+        let bindings = TypeParameter::bind(&generic.type_parameters, args);
+        let mut func = generic;
+        func.name = instance.clone();
+        func.type_parameters.clear();
+        let saved = std::mem::replace(&mut self.type_arguments, bindings.clone());
+        let func = self.concrete_signature(&func);
+        self.type_arguments = saved;
+        self.declare_instance(func?, bindings)?;
+        Ok(instance)
+    }
+
+    /// 声明泛型实例函数，函数体稍后以对应的类型实参生成
+    fn declare_instance(
+        &mut self,
+        func: Function,
+        bindings: HashMap<String, Type>,
+    ) -> Result<(), CodegenError> {
+        self.generate_function_declaration(&func)?;
+        // 实例只在本模块内使用，导入泛型的模块各自生成所需的实例
+        if let Some(fn_value) = self.module.get_function(&func.name) {
+            fn_value.set_linkage(Linkage::Internal);
+        }
+        self.pending_instances.push((func, bindings));
+        Ok(())
+    }
+
+    /// 填充泛型 class 实例的虚表并生成实例函数的函数体，生成过程中用到的新实例继续生成
+    fn generate_instances(&mut self) -> Result<(), CodegenError> {
+        loop {
+            for class in std::mem::take(&mut self.pending_vtables) {
+                self.generate_vtable(&class)?;
+            }
+            let Some((func, bindings)) = self.pending_instances.pop() else { break };
+            let saved = std::mem::replace(&mut self.type_arguments, bindings);
+            let result = self.generate_function(&func);
+            self.type_arguments = saved;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// 为 class 方法添加 this 参数
+    fn bind_this(class_name: &str, method: &Function) -> Function {
+        let this = Parameter {
+            name: "this".to_string(),
+            type_annotation: Type::Struct(class_name.to_string()),
+        };
+        let mut method = method.clone();
+        method.parameters.insert(0, this);
+        method
+    }
+
+    /// 生成函数声明
+    fn generate_function_declaration(&mut self, func: &Function) -> Result<(), CodegenError> {
+        let param_types: Vec<inkwell::types::BasicMetadataTypeEnum> =
+            func.parameters.iter().map(|p| self.map_type(&p.type_annotation).into()).collect();
+
+        let return_type = self.map_type(&func.return_type);
+        let fn_type = return_type.fn_type(&param_types, false);
+
+        // declare function 声明的运行时函数可能已作为内置函数声明
+        if func.is_declare && self.module.get_function(&func.name).is_some() {
+            return Ok(());
+        }
+        self.module.add_function(&func.name, fn_type, None);
+        Ok(())
+    }
+
+    /// 生成函数体
+    fn generate_function(&mut self, func: &Function) -> Result<(), CodegenError> {
+        if func.is_declare {
+            return Ok(());
+        }
+        let fn_value = self
+            .module
+            .get_function(&func.name)
+            .ok_or_else(|| CodegenError { message: format!("Function {} not found", func.name) })?;
+
+        // 创建 entry 块
+        let entry = self.context.append_basic_block(fn_value, "entry");
+        self.builder().position_at_end(entry);
+
+        // 清空局部变量
+        self.clear_variables();
+        self.cells = nexa_parser::cell_variables(&func.parameters, &[&func.body]);
+
+        // 设置当前函数
+        self.set_function(fn_value);
+        self.return_type = Some(func.return_type.clone());
+
+        // 顶层语句在入口模块的 main 开始时执行
+        if func.name == "main" && !self.library {
+            self.call_module_inits()?;
+        }
+
+        // 为参数创建 alloca
+        for (i, param) in func.parameters.iter().enumerate() {
+            let param_value = fn_value.get_nth_param(i as u32).unwrap();
+            let alloca = self.allocate_variable(param_value.get_type(), &param.name)?;
+            // 将参数值存储到 alloca - 需要先转换为 BasicValue
+            let basic_value = param_value;
+            self.builder().build_store(alloca, basic_value)?;
+            self.add_variable(param.name.clone(), alloca, param_value.get_type());
+        }
+
+        // 生成函数体
+        self.generate_statement(&func.body)?;
+
+        // 如果函数没有返回语句，添加一个默认返回
+        let current_block = self.builder().get_insert_block().unwrap();
+        if current_block.get_terminator().is_none() {
+            // 统一返回 i32
+            let default = self.context.i32_type().const_int(0, false);
+            self.builder().build_return(Some(&default))?;
+        }
+
+        Ok(())
+    }
+
+    /// 生成语句
+    pub fn generate_statement(&mut self, stmt: &Statement) -> Result<(), CodegenError> {
+        match stmt {
+            Statement::Block(stmts, _) => {
+                for s in stmts {
+                    // break / continue / return 之后的语句不可达，不再生成
+                    if self.is_terminated() {
+                        break;
+                    }
+                    self.generate_statement(s)?;
+                }
+            },
+            Statement::VariableDeclaration {
+                name,
+                type_annotation: _,
+                initializer,
+                mutable: _,
+                span,
+            } => {
+                // 变量类型以类型检查结果为准
+                let declared_type = self.binding_type(span)?.map(|ty| self.resolve_type(&ty));
+
+                let value_struct = match &declared_type {
+                    Some(Type::Struct(struct_name))
+                        if !self.is_class(struct_name)
+                            && !self.interface_defs.contains_key(struct_name)
+                            && !self.enum_defs.contains_key(struct_name) =>
+                    {
+                        Some(struct_name.clone())
+                    },
+                    _ => None,
+                };
+                if let Some(struct_name) = &value_struct {
+                    // 对于 struct 类型，需要获取 struct 类型并分配内存
+                    // 创建一个虚拟的字段列表用于获取 struct 类型
+                    let dummy_fields = vec![];
+                    let struct_type = self.get_or_create_struct_type(struct_name, &dummy_fields)?;
+
+                    // 创建局部变量（指针）
+                    let alloca = self.allocate_variable(struct_type.into(), name)?;
+
+                    // 初始化
+                    if let Some(init) = initializer {
+                        let value = self.generate_expression(init)?;
+                        // new 表达式返回堆上对象的指针，需要先加载出 struct 值
+                        let value = if value.is_pointer_value() {
+                            self.builder().build_load(
+                                struct_type,
+                                value.into_pointer_value(),
+                                "struct_load",
+                            )?
                         } else {
-                            false
-                        }
-                    });
+                            value
+                        };
+                        // 存储 struct 值
+                        self.builder().build_store(alloca, value)?;
+                    }
 
-                    // 如果有字符串类型注解或调用 readln()，使用指针类型
-                    let ty = if has_string_type || is_string_init {
-                        // 字符串类型使用指针
-                        self.context.ptr_type(AddressSpace::default()).into()
-                    } else {
-                        // 确定类型
-                        type_annotation
-                            .as_ref()
-                            .map(|t| self.map_type(t))
-                            .unwrap_or_else(|| self.context.i32_type().into())
-                    };
+                    self.add_variable(name.clone(), alloca, struct_type.into());
+                } else {
+                    let ty = declared_type
+                        .as_ref()
+                        .map(|t| self.map_type(t))
+                        .unwrap_or_else(|| self.context.i32_type().into());
 
                     // 创建局部变量
-                    let alloca = self.builder().build_alloca(ty, name)?;
+                    let alloca = self.allocate_variable(ty, name)?;
 
                     // 初始化
                     if let Some(init) = initializer {
-                        let value = self.generate_expression(init)?;
-                        // 转换为 BasicValueEnum 存储
-                        let basic_value = value;
-                        self.builder().build_store(alloca, basic_value)?;
+                        let value = self.generate_coerced(init, declared_type.as_ref())?;
+                        self.builder().build_store(alloca, value)?;
                     }
 
-                    self.add_variable(name.clone(), alloca);
-                    // 保存类型信息
-                    self.set_variable_type(name, ty);
+                    self.add_variable(name.clone(), alloca, ty);
                 }
             },
             Statement::Assignment { target, value, span: _ } => {
@@ -623,7 +1855,8 @@ impl<'ctx> CodeGenerator<'ctx> {
                     Expression::Identifier(name, _) => {
                         // 简单变量赋值: x = value
                         if let Some(ptr) = self.get_variable(name) {
-                            let value = self.generate_expression(value)?;
+                            let target_type = self.expression_type(target)?;
+                            let value = self.generate_coerced(value, target_type.as_ref())?;
                             self.builder().build_store(ptr, value)?;
                         } else {
                             return Err(CodegenError {
@@ -633,36 +1866,9 @@ impl<'ctx> CodeGenerator<'ctx> {
                     },
                     Expression::Member { object, member, span: _ } => {
                         // 成员赋值: obj.field = value
-                        let value = self.generate_expression(value)?;
-
-                        if let Expression::Identifier(var_name, _) = object.as_ref() {
-                            if let Some(ptr) = self.get_variable(var_name) {
-                                // 动态获取字段索引
-                                let field_index = self.get_field_index(var_name, member);
-                                let idx = field_index.unwrap_or(0) as u64;
-
-                                let i32_type = self.context.i32_type();
-                                let zero = i32_type.const_int(0, false);
-                                let field_idx = i32_type.const_int(idx, false);
-
-                                // 查找 struct 类型
-                                let struct_type = self.find_struct_type_from_variable(var_name);
-
-                                if let Some(struct_type) = struct_type {
-                                    // 使用 struct 类型进行 GEP
-                                    let field_ptr = unsafe {
-                                        self.builder().build_in_bounds_gep(
-                                            struct_type,
-                                            ptr,
-                                            &[zero, field_idx],
-                                            member.as_str(),
-                                        )?
-                                    };
-
-                                    self.builder().build_store(field_ptr, value)?;
-                                }
-                            }
-                        }
+                        let target_type = self.expression_type(target)?;
+                        let value = self.generate_coerced(value, target_type.as_ref())?;
+                        self.store_member(object, member, value)?;
                     },
                     _ => {
                         // 其他类型的赋值目标暂不支持
@@ -672,36 +1878,7 @@ impl<'ctx> CodeGenerator<'ctx> {
             Statement::If { condition, then_branch, else_branch, span: _ } => {
                 let condition_value = self.generate_expression(condition)?;
 
-                // 将条件值转换为 i1 进行条件分支
-                let condition_i1 = match condition_value {
-                    BasicValueEnum::IntValue(v) => {
-                        if v.get_type().get_bit_width() == 1 {
-                            // 已经是 i1 类型，直接使用
-                            v
-                        } else {
-                            // i32 或其他整数类型：与0比较
-                            let zero = v.get_type().const_int(0, false);
-                            self.builder().build_int_compare(
-                                inkwell::IntPredicate::NE,
-                                v,
-                                zero,
-                                "condition_cmp",
-                            )?
-                        }
-                    },
-                    _ => {
-                        // 其他类型，假设为非零
-                        let i1_type = self.context.bool_type();
-                        let one = i1_type.const_int(1, false);
-                        let zero = i1_type.const_int(0, false);
-                        self.builder().build_int_compare(
-                            inkwell::IntPredicate::NE,
-                            one,
-                            zero,
-                            "cond_to_bool",
-                        )?
-                    },
-                };
+                let condition_i1 = self.build_truthy(condition_value)?;
 
                 let function = self.current_function.unwrap();
                 let then_block = self.context.append_basic_block(function, "then");
@@ -729,7 +1906,7 @@ impl<'ctx> CodeGenerator<'ctx> {
                 // merge 块
                 self.builder().position_at_end(merge_block);
             },
-            Statement::While { condition, body, span: _ } => {
+            Statement::While { label, condition, body, span: _ } => {
                 let function = self.current_function.unwrap();
                 let cond_block = self.context.append_basic_block(function, "while_cond");
                 let body_block = self.context.append_basic_block(function, "while_body");
@@ -741,37 +1918,20 @@ impl<'ctx> CodeGenerator<'ctx> {
                 self.builder().position_at_end(cond_block);
                 let condition_value = self.generate_expression(condition)?;
 
-                let condition_i1 = match condition_value {
-                    BasicValueEnum::IntValue(v) => {
-                        if v.get_type().get_bit_width() == 1 {
-                            v
-                        } else {
-                            let zero = v.get_type().const_int(0, false);
-                            self.builder().build_int_compare(
-                                inkwell::IntPredicate::NE,
-                                v,
-                                zero,
-                                "condition_cmp",
-                            )?
-                        }
-                    },
-                    _ => {
-                        let i1_type = self.context.bool_type();
-                        let one = i1_type.const_int(1, false);
-                        let zero = i1_type.const_int(0, false);
-                        self.builder().build_int_compare(
-                            inkwell::IntPredicate::NE,
-                            one,
-                            zero,
-                            "cond_to_bool",
-                        )?
-                    },
-                };
+                let condition_i1 = self.build_truthy(condition_value)?;
                 self.builder().build_conditional_branch(condition_i1, body_block, end_block)?;
 
                 // 循环体
                 self.builder().position_at_end(body_block);
-                self.generate_statement(body)?;
+                self.loop_stack.push(LoopContext {
+                    label: label.clone(),
+                    break_block: end_block,
+                    try_depth: self.try_stack.len(),
+                    continue_block: cond_block,
+                });
+                let result = self.generate_statement(body);
+                self.loop_stack.pop();
+                result?;
                 if self.builder().get_insert_block().unwrap().get_terminator().is_none() {
                     self.builder().build_unconditional_branch(cond_block)?;
                 }
@@ -779,17 +1939,41 @@ impl<'ctx> CodeGenerator<'ctx> {
                 // 结束块
                 self.builder().position_at_end(end_block);
             },
-            Statement::Return(value, _span) => {
-                if let Some(v) = value {
-                    let ret_value = self.generate_expression(v)?;
-                    self.builder().build_return(Some(&ret_value))?;
-                } else {
-                    self.builder().build_return(None)?;
+            Statement::Return(value, _span) => {
+                let return_type = self.return_type.clone();
+                let mut ret_value = value
+                    .as_ref()
+                    .map(|v| self.generate_coerced(v, return_type.as_ref()))
+                    .transpose()?;
+                // void 函数在 LLVM 中以 i32 表示，裸 return 返回零值
+                if ret_value.is_none() {
+                    let return_type = self.current_function.unwrap().get_type().get_return_type();
+                    ret_value = return_type.map(|t| t.const_zero());
+                }
+                // 返回前执行所有外层 finally
+                self.unwind_try_scopes(0)?;
+                if !self.is_terminated() {
+                    self.builder()
+                        .build_return(ret_value.as_ref().map(|v| v as &dyn BasicValue))?;
+                }
+            },
+            Statement::Break { label, span: _ } => {
+                let target = self.find_loop("break", label.as_deref())?;
+                let (block, depth) = (target.break_block, target.try_depth);
+                self.unwind_try_scopes(depth)?;
+                if !self.is_terminated() {
+                    self.builder().build_unconditional_branch(block)?;
+                }
+            },
+            Statement::Continue { label, span: _ } => {
+                let target = self.find_loop("continue", label.as_deref())?;
+                let (block, depth) = (target.continue_block, target.try_depth);
+                self.unwind_try_scopes(depth)?;
+                if !self.is_terminated() {
+                    self.builder().build_unconditional_branch(block)?;
                 }
             },
-            Statement::Break(_span) => {},
-            Statement::Continue(_span) => {},
-            Statement::DoWhile { condition, body, span: _ } => {
+            Statement::DoWhile { label, condition, body, span: _ } => {
                 // do-while 循环
                 let function = self.current_function.unwrap();
                 let body_block = self.context.append_basic_block(function, "do_while_body");
@@ -801,8 +1985,18 @@ impl<'ctx> CodeGenerator<'ctx> {
 
                 // 生成循环体
                 self.builder().position_at_end(body_block);
-                self.generate_statement(body)?;
-                self.builder().build_unconditional_branch(cond_block)?;
+                self.loop_stack.push(LoopContext {
+                    label: label.clone(),
+                    break_block: end_block,
+                    try_depth: self.try_stack.len(),
+                    continue_block: cond_block,
+                });
+                let result = self.generate_statement(body);
+                self.loop_stack.pop();
+                result?;
+                if self.builder().get_insert_block().unwrap().get_terminator().is_none() {
+                    self.builder().build_unconditional_branch(cond_block)?;
+                }
 
                 // 生成条件
                 self.builder().position_at_end(cond_block);
@@ -821,8 +2015,21 @@ impl<'ctx> CodeGenerator<'ctx> {
                 self.builder().position_at_end(end_block);
             },
             Statement::Throw { value, span: _ } => {
-                // throw 语句 - 目前忽略
-                let _ = value;
+                // 异常值统一以字符串形式抛出，number 先转换为字符串 (类型检查保证只有这两类值)
+                let ty = self.expression_type(value)?.unwrap_or(Type::String);
+                if self.numeric_type(&ty).is_some() {
+                    let thrown = self.generate_coerced(value, Some(&Type::Number))?;
+                    self.call_runtime("nexa_throw_i32", &[thrown.into()])?;
+                } else {
+                    let thrown = self.generate_expression(value)?;
+                    if !thrown.is_pointer_value() {
+                        return Err(CodegenError {
+                            message: format!("Cannot throw value of type {:?}", thrown.get_type()),
+                        });
+                    }
+                    self.call_runtime("nexa_throw", &[thrown.into()])?;
+                }
+                self.builder().build_unreachable()?;
             },
             Statement::TryCatchFinally {
                 try_body,
@@ -831,15 +2038,18 @@ impl<'ctx> CodeGenerator<'ctx> {
                 finally_body,
                 span: _,
             } => {
-                // try-catch-finally - 目前只执行 try 部分
-                let _ = (catch_var, catch_body, finally_body);
-                self.generate_statement(try_body)?;
+                self.generate_try_catch_finally(
+                    try_body,
+                    catch_var.as_deref(),
+                    catch_body.as_deref(),
+                    finally_body.as_deref(),
+                )?;
             },
             Statement::ExpressionStatement(expr) => {
                 self.generate_expression(expr)?;
             },
             Statement::Empty(_span) => {},
-            Statement::For { initializer, condition, update, body, span: _ } => {
+            Statement::For { label, initializer, condition, update, body, span: _ } => {
                 self.generate_statement(initializer)?;
 
                 let function = self.current_function.unwrap();
@@ -855,32 +2065,7 @@ impl<'ctx> CodeGenerator<'ctx> {
                 if let Some(cond) = condition {
                     let condition_value = self.generate_expression(cond)?;
 
-                    let condition_i1 = match condition_value {
-                        BasicValueEnum::IntValue(v) => {
-                            if v.get_type().get_bit_width() == 1 {
-                                v
-                            } else {
-                                let zero = v.get_type().const_int(0, false);
-                                self.builder().build_int_compare(
-                                    inkwell::IntPredicate::NE,
-                                    v,
-                                    zero,
-                                    "condition_cmp",
-                                )?
-                            }
-                        },
-                        _ => {
-                            let i1_type = self.context.bool_type();
-                            let one = i1_type.const_int(1, false);
-                            let zero = i1_type.const_int(0, false);
-                            self.builder().build_int_compare(
-                                inkwell::IntPredicate::NE,
-                                one,
-                                zero,
-                                "cond_to_bool",
-                            )?
-                        },
-                    };
+                    let condition_i1 = self.build_truthy(condition_value)?;
                     self.builder().build_conditional_branch(condition_i1, body_block, end_block)?;
                 } else {
                     self.builder().build_unconditional_branch(body_block)?;
@@ -888,7 +2073,15 @@ impl<'ctx> CodeGenerator<'ctx> {
 
                 // 循环体
                 self.builder().position_at_end(body_block);
-                self.generate_statement(body)?;
+                self.loop_stack.push(LoopContext {
+                    label: label.clone(),
+                    break_block: end_block,
+                    try_depth: self.try_stack.len(),
+                    continue_block: update_block,
+                });
+                let result = self.generate_statement(body);
+                self.loop_stack.pop();
+                result?;
                 if self.builder().get_insert_block().unwrap().get_terminator().is_none() {
                     self.builder().build_unconditional_branch(update_block)?;
                 }
@@ -913,6 +2106,469 @@ impl<'ctx> CodeGenerator<'ctx> {
         Ok(())
     }
 
+    // ============ 闭包 ============
+
+    /// 闭包值类型：{ 函数指针, 环境指针 }
+    fn closure_type(&self) -> inkwell::types::StructType<'ctx> {
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+        self.context.struct_type(&[ptr_type.into(), ptr_type.into()], false)
+    }
+
+    /// 闭包函数的 LLVM 类型：第一个参数为环境指针
+    fn closure_fn_type(
+        &self,
+        params: &[Type],
+        return_type: &Type,
+    ) -> inkwell::types::FunctionType<'ctx> {
+        let env_type = self.context.ptr_type(AddressSpace::default());
+        let param_types: Vec<inkwell::types::BasicMetadataTypeEnum> =
+            std::iter::once(env_type.into())
+                .chain(params.iter().map(|p| self.map_type(p).into()))
+                .collect();
+        self.map_type(return_type).fn_type(&param_types, false)
+    }
+
+    /// 组装闭包值
+    fn build_closure_value(
+        &mut self,
+        fn_value: FunctionValue<'ctx>,
+        env: PointerValue<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let fn_ptr = fn_value.as_global_value().as_pointer_value();
+        let closure = self.closure_type().get_undef();
+        let closure = self.builder().build_insert_value(closure, fn_ptr, 0, "closure_fn")?;
+        let closure = self.builder().build_insert_value(closure, env, 1, "closure")?;
+        Ok(closure.as_basic_value_enum())
+    }
+
+    /// 生成函数表达式
+    ///
+    /// 函数体被提升为独立的 LLVM 函数，捕获的变量存放在堆上的环境 struct 中：
+    /// 放在单元中的变量保存单元的地址 (与外层函数共享)，其余变量复制当时的值。
+    fn generate_closure(
+        &mut self,
+        parameters: &[Parameter],
+        return_type: &Type,
+        body: &Statement,
+        captures: &[Capture],
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+
+        // 只捕获外层作用域中的局部变量，全局变量和函数直接引用
+        let captured: Vec<CapturedVariable<'ctx>> = captures
+            .iter()
+            .filter_map(|c| {
+                let (ptr, ty) = *self.variables.get(&c.name)?;
+                let in_cell = self.cells.contains(&c.name);
+                Some(CapturedVariable { name: c.name.clone(), in_cell, ty, ptr })
+            })
+            .collect();
+
+        let env_fields: Vec<BasicTypeEnum<'ctx>> =
+            captured.iter().map(|c| if c.in_cell { ptr_type.into() } else { c.ty }).collect();
+        let env_type = self.context.struct_type(&env_fields, false);
+
+        // 在当前函数中构造环境
+        let env = if captured.is_empty() {
+            ptr_type.const_null()
+        } else {
+            let env = self.builder().build_malloc(env_type, "closure_env")?;
+            for (i, capture) in captured.iter().enumerate() {
+                let field =
+                    self.builder().build_struct_gep(env_type, env, i as u32, &capture.name)?;
+                if capture.in_cell {
+                    self.builder().build_store(field, capture.ptr)?;
+                } else {
+                    let value =
+                        self.builder().build_load(capture.ty, capture.ptr, &capture.name)?;
+                    self.builder().build_store(field, value)?;
+                }
+            }
+            env
+        };
+
+        // 生成提升后的函数
+        let name = format!("__closure_{}", self.closure_count);
+        self.closure_count += 1;
+        let param_types: Vec<Type> = parameters.iter().map(|p| p.type_annotation.clone()).collect();
+        let fn_type = self.closure_fn_type(&param_types, return_type);
+        let fn_value = self.module.add_function(&name, fn_type, Some(Linkage::Internal));
+
+        let saved_block = self.builder.get_insert_block();
+        let saved_function = self.current_function;
+        let saved_variables = std::mem::take(&mut self.variables);
+        let saved_loops = std::mem::take(&mut self.loop_stack);
+        let saved_tries = std::mem::take(&mut self.try_stack);
+        let saved_return = self.return_type.replace(return_type.clone());
+        // 闭包自己的单元变量，加上从外层共享来的单元
+        let mut cells = nexa_parser::cell_variables(parameters, &[body]);
+        cells.extend(captured.iter().filter(|c| c.in_cell).map(|c| c.name.clone()));
+        let saved_cells = std::mem::replace(&mut self.cells, cells);
+
+        let result = self.generate_closure_body(
+            fn_value,
+            env_type,
+            &captured,
+            parameters,
+            return_type,
+            body,
+        );
+
+        self.current_function = saved_function;
+        self.variables = saved_variables;
+        self.loop_stack = saved_loops;
+        self.try_stack = saved_tries;
+        self.return_type = saved_return;
+        self.cells = saved_cells;
+        if let Some(block) = saved_block {
+            self.builder().position_at_end(block);
+        }
+        result?;
+
+        self.build_closure_value(fn_value, env)
+    }
+
+    /// 生成闭包函数体：先从环境中恢复捕获的变量，再绑定参数
+    fn generate_closure_body(
+        &mut self,
+        fn_value: FunctionValue<'ctx>,
+        env_type: inkwell::types::StructType<'ctx>,
+        captured: &[CapturedVariable<'ctx>],
+        parameters: &[Parameter],
+        return_type: &Type,
+        body: &Statement,
+    ) -> Result<(), CodegenError> {
+        let entry = self.context.append_basic_block(fn_value, "entry");
+        self.builder().position_at_end(entry);
+        self.set_function(fn_value);
+
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+        let env = fn_value.get_nth_param(0).unwrap().into_pointer_value();
+        for (i, capture) in captured.iter().enumerate() {
+            let field = self.builder().build_struct_gep(env_type, env, i as u32, &capture.name)?;
+            let ptr = if capture.in_cell {
+                self.builder().build_load(ptr_type, field, &capture.name)?.into_pointer_value()
+            } else {
+                field
+            };
+            self.add_variable(capture.name.clone(), ptr, capture.ty);
+        }
+
+        for (i, param) in parameters.iter().enumerate() {
+            let param_value = fn_value.get_nth_param(i as u32 + 1).unwrap();
+            let alloca = self.allocate_variable(param_value.get_type(), &param.name)?;
+            self.builder().build_store(alloca, param_value)?;
+            self.add_variable(param.name.clone(), alloca, param_value.get_type());
+        }
+
+        self.generate_statement(body)?;
+
+        if !self.is_terminated() {
+            let default = self.map_type(return_type).const_zero();
+            self.builder().build_return(Some(&default))?;
+        }
+        Ok(())
+    }
+
+    /// 将具名函数包装为闭包值 (环境为空)
+    fn function_as_closure(&mut self, name: &str) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let target = self
+            .lookup_function(name)
+            .ok_or_else(|| CodegenError { message: format!("Function {} not found", name) })?;
+        let adapter_name = format!("__closure_adapter_{}", name);
+
+        let adapter = match self.module.get_function(&adapter_name) {
+            Some(adapter) => adapter,
+            None => {
+                // 适配函数忽略环境参数，直接转发给原函数
+                let mut param_types: Vec<inkwell::types::BasicMetadataTypeEnum> =
+                    vec![self.context.ptr_type(AddressSpace::default()).into()];
+                param_types.extend(target.get_type().get_param_types());
+                let fn_type = match target.get_type().get_return_type() {
+                    Some(ret) => ret.fn_type(&param_types, false),
+                    None => self.context.void_type().fn_type(&param_types, false),
+                };
+                let adapter =
+                    self.module.add_function(&adapter_name, fn_type, Some(Linkage::Internal));
+
+                let saved_block = self.builder.get_insert_block();
+                let entry = self.context.append_basic_block(adapter, "entry");
+                self.builder().position_at_end(entry);
+                let args: Vec<inkwell::values::BasicMetadataValueEnum> =
+                    adapter.get_param_iter().skip(1).map(|p| p.into()).collect();
+                let call = self.builder().build_call(target, &args, "call")?;
+                match call.try_as_basic_value() {
+                    inkwell::values::ValueKind::Basic(value) => {
+                        self.builder().build_return(Some(&value))?
+                    },
+                    _ => self.builder().build_return(None)?,
+                };
+                if let Some(block) = saved_block {
+                    self.builder().position_at_end(block);
+                }
+                adapter
+            },
+        };
+
+        let null_env = self.context.ptr_type(AddressSpace::default()).const_null();
+        self.build_closure_value(adapter, null_env)
+    }
+
+    /// 被调用的表达式是否为闭包值 (而不是具名函数或方法)
+    fn is_closure_value(&mut self, callee: &Expression) -> Result<bool, CodegenError> {
+        if !matches!(self.expression_type(callee)?, Some(Type::Function(_, _))) {
+            return Ok(false);
+        }
+        Ok(match callee {
+            Expression::Identifier(name, _) => self.get_variable(name).is_some(),
+            Expression::Member { object, member, .. } => self
+                .struct_name_of(object)?
+                .is_some_and(|name| self.struct_field(&name, member).is_some()),
+            _ => true,
+        })
+    }
+
+    /// `ns.member` 中 ns 是命名空间导入时返回成员的本地名称 `ns.member`
+    fn namespace_member(&self, object: &Expression, member: &str) -> Option<String> {
+        let Expression::Identifier(ns, _) = object else { return None };
+        if self.variables.contains_key(ns) {
+            return None;
+        }
+        let name = format!("{}.{}", ns, member);
+        (self.globals.contains_key(&name) || self.link_names.contains_key(&name)).then_some(name)
+    }
+
+    /// `Color.Red` 或 `ns.Color.Red` 形式的枚举成员的值
+    fn enum_member(&self, object: &Expression, member: &str) -> Option<EnumValue> {
+        let enum_name = match object {
+            Expression::Identifier(name, _) if !self.variables.contains_key(name) => name.clone(),
+            Expression::Member { object, member, .. } => match object.as_ref() {
+                Expression::Identifier(ns, _) if !self.variables.contains_key(ns) => {
+                    format!("{}.{}", ns, member)
+                },
+                _ => return None,
+            },
+            _ => return None,
+        };
+        self.enum_defs.get(&enum_name)?.member(member).map(|m| m.value.clone())
+    }
+
+    /// 生成枚举成员的常量值
+    fn generate_enum_value(
+        &mut self,
+        value: &EnumValue,
+        span: &Span,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        match value {
+            EnumValue::Number(n) => Ok(self.context.i32_type().const_int(*n as u64, true).into()),
+            EnumValue::String(s) => {
+                self.generate_expression(&Expression::String(s.clone(), span.clone()))
+            },
+        }
+    }
+
+    /// 调用闭包：取出函数指针与环境，环境作为第一个参数传入
+    fn generate_closure_call(
+        &mut self,
+        callee: &Expression,
+        arguments: &[Expression],
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let Some(Type::Function(params, return_type)) = self.expression_type(callee)? else {
+            return Err(CodegenError { message: "Callee is not a function value".to_string() });
+        };
+
+        let closure = self.generate_expression(callee)?.into_struct_value();
+        let fn_ptr =
+            self.builder().build_extract_value(closure, 0, "closure_fn")?.into_pointer_value();
+        let env = self.builder().build_extract_value(closure, 1, "closure_env")?;
+
+        let mut args: Vec<inkwell::values::BasicMetadataValueEnum> = vec![env.into()];
+        for arg in arguments {
+            args.push(self.generate_expression(arg)?.into());
+        }
+
+        let fn_type = self.closure_fn_type(&params, &return_type);
+        let call = self.builder().build_indirect_call(fn_type, fn_ptr, &args, "closure_call")?;
+        Ok(match call.try_as_basic_value() {
+            inkwell::values::ValueKind::Basic(value) => value,
+            _ => self.context.i32_type().const_int(0, false).into(),
+        })
+    }
+
+    /// 当前基本块是否已有终结指令
+    fn is_terminated(&self) -> bool {
+        self.builder.get_insert_block().unwrap().get_terminator().is_some()
+    }
+
+    /// 调用运行时函数
+    fn call_runtime(
+        &self,
+        name: &str,
+        args: &[inkwell::values::BasicMetadataValueEnum<'ctx>],
+    ) -> Result<Option<BasicValueEnum<'ctx>>, CodegenError> {
+        let function = self
+            .module
+            .get_function(name)
+            .ok_or_else(|| CodegenError { message: format!("{} function not found", name) })?;
+        let call = self.builder.build_call(function, args, name)?;
+        Ok(match call.try_as_basic_value() {
+            inkwell::values::ValueKind::Basic(value) => Some(value),
+            _ => None,
+        })
+    }
+
+    /// 进入 try 块：压入运行时异常帧并调用 setjmp
+    /// 返回值为 true 表示正常进入，false 表示由 throw 跳转回来
+    fn build_try_frame(&mut self) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let env = self.call_runtime("nexa_try_push", &[])?.unwrap();
+        let jumped = self.call_runtime("setjmp", &[env.into()])?.unwrap().into_int_value();
+        let zero = self.context.i32_type().const_int(0, false);
+        Ok(self.builder().build_int_compare(inkwell::IntPredicate::EQ, jumped, zero, "no_throw")?)
+    }
+
+    /// 生成 try-catch-finally
+    ///
+    /// 每个 try 块在运行时压入一个 setjmp 帧，throw 通过 longjmp 回到最近的帧。
+    /// finally 在正常结束、捕获异常、异常继续传播以及 return / break / continue
+    /// 跳出时都会执行。
+    fn generate_try_catch_finally(
+        &mut self,
+        try_body: &Statement,
+        catch_var: Option<&str>,
+        catch_body: Option<&Statement>,
+        finally_body: Option<&Statement>,
+    ) -> Result<(), CodegenError> {
+        let function = self.current_function.unwrap();
+        let try_block = self.context.append_basic_block(function, "try_body");
+        let catch_block = self.context.append_basic_block(function, "try_catch");
+        let finally_block = self.context.append_basic_block(function, "try_finally");
+
+        let no_throw = self.build_try_frame()?;
+        self.builder().build_conditional_branch(no_throw, try_block, catch_block)?;
+
+        // try 块
+        self.builder().position_at_end(try_block);
+        self.try_stack.push(TryScope { has_frame: true, finally_body: finally_body.cloned() });
+        let result = self.generate_statement(try_body);
+        self.try_stack.pop();
+        result?;
+        if !self.is_terminated() {
+            self.call_runtime("nexa_try_pop", &[])?;
+            self.builder().build_unconditional_branch(finally_block)?;
+        }
+
+        // 发生异常：帧仍在运行时栈上，先弹出
+        self.builder().position_at_end(catch_block);
+        self.call_runtime("nexa_try_pop", &[])?;
+
+        if let Some(catch_body) = catch_body {
+            if let Some(var) = catch_var {
+                let ptr_type = self.context.ptr_type(AddressSpace::default());
+                let exception = self.call_runtime("nexa_exception_value", &[])?.unwrap();
+                let alloca = self.allocate_variable(ptr_type.into(), var)?;
+                self.builder().build_store(alloca, exception)?;
+                self.add_variable(var.to_string(), alloca, ptr_type.into());
+            }
+
+            if finally_body.is_some() {
+                // catch 中再次抛出时也要先执行 finally，再继续传播
+                let run_block = self.context.append_basic_block(function, "catch_body");
+                let rethrow_block = self.context.append_basic_block(function, "catch_rethrow");
+                let no_throw = self.build_try_frame()?;
+                self.builder().build_conditional_branch(no_throw, run_block, rethrow_block)?;
+
+                self.builder().position_at_end(run_block);
+                self.try_stack
+                    .push(TryScope { has_frame: true, finally_body: finally_body.cloned() });
+                let result = self.generate_statement(catch_body);
+                self.try_stack.pop();
+                result?;
+                if !self.is_terminated() {
+                    self.call_runtime("nexa_try_pop", &[])?;
+                    self.builder().build_unconditional_branch(finally_block)?;
+                }
+
+                self.builder().position_at_end(rethrow_block);
+                self.call_runtime("nexa_try_pop", &[])?;
+                self.generate_rethrow(finally_body)?;
+            } else {
+                self.generate_statement(catch_body)?;
+                if !self.is_terminated() {
+                    self.builder().build_unconditional_branch(finally_block)?;
+                }
+            }
+        } else {
+            // 没有 catch：执行 finally 后继续传播异常
+            self.generate_rethrow(finally_body)?;
+        }
+
+        // 正常路径上的 finally
+        self.builder().position_at_end(finally_block);
+        if let Some(finally_body) = finally_body {
+            self.generate_statement(finally_body)?;
+        }
+
+        Ok(())
+    }
+
+    /// 执行 finally 后把当前异常继续抛给外层
+    fn generate_rethrow(&mut self, finally_body: Option<&Statement>) -> Result<(), CodegenError> {
+        if let Some(finally_body) = finally_body {
+            self.generate_statement(finally_body)?;
+        }
+        if !self.is_terminated() {
+            self.call_runtime("nexa_rethrow", &[])?;
+            self.builder().build_unreachable()?;
+        }
+        Ok(())
+    }
+
+    /// return / break / continue 跳出 try 作用域时，
+    /// 由内向外弹出异常帧并执行 finally，直到剩余 `depth` 层
+    fn unwind_try_scopes(&mut self, depth: usize) -> Result<(), CodegenError> {
+        let saved = self.try_stack.clone();
+        let mut result = Ok(());
+        while self.try_stack.len() > depth && !self.is_terminated() {
+            let scope = self.try_stack.pop().unwrap();
+            if scope.has_frame {
+                if let Err(e) = self.call_runtime("nexa_try_pop", &[]) {
+                    result = Err(e);
+                    break;
+                }
+            }
+            if let Some(finally_body) = &scope.finally_body {
+                // finally 内部的跳转只需考虑更外层的 try 作用域
+                if let Err(e) = self.generate_statement(finally_body) {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.try_stack = saved;
+        result
+    }
+
+    /// 查找 break / continue 的目标循环
+    /// 无标签时取最内层循环，有标签时沿循环栈向外查找
+    fn find_loop(
+        &self,
+        keyword: &str,
+        label: Option<&str>,
+    ) -> Result<&LoopContext<'ctx>, CodegenError> {
+        match label {
+            None => self.loop_stack.last().ok_or_else(|| CodegenError {
+                message: format!("'{}' outside of a loop", keyword),
+            }),
+            Some(label) => {
+                self.loop_stack.iter().rev().find(|l| l.label.as_deref() == Some(label)).ok_or_else(
+                    || CodegenError {
+                        message: format!("Unknown label '{}' in '{}'", label, keyword),
+                    },
+                )
+            },
+        }
+    }
+
     /// 生成表达式
     pub fn generate_expression(
         &mut self,
@@ -920,99 +2576,84 @@ impl<'ctx> CodeGenerator<'ctx> {
     ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
         match expr {
             Expression::Number(n, _span) => {
-                let i32_type = self.context.i32_type();
-                Ok(i32_type.const_int(*n as u64, false).into())
+                // 字面量按类型检查确定的数值类型生成，默认为 i32
+                let ty = self.expression_type(expr)?.unwrap_or(Type::Number);
+                match self.map_type(&self.numeric_type(&ty).unwrap_or(Type::Number)) {
+                    BasicTypeEnum::FloatType(t) => Ok(t.const_float(*n as f64).into()),
+                    BasicTypeEnum::IntType(t) => Ok(t.const_int(*n as u64, true).into()),
+                    _ => Ok(self.context.i32_type().const_int(*n as u64, true).into()),
+                }
             },
             Expression::Float(f, _span) => {
-                let f64_type = self.context.f64_type();
-                Ok(f64_type.const_float(*f).into())
-            },
-            Expression::String(s, _span) => {
-                let str_with_null = format!("{}\0", s);
-                let llvm_str = self.context.const_string(str_with_null.as_bytes(), true);
-                let global = self.module.add_global(llvm_str.get_type(), None, "str");
-                global.set_initializer(&llvm_str);
-
-                let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
-                let ptr = self.builder().build_bit_cast(global, i8_ptr_type, "str_ptr")?;
-                Ok(ptr)
+                let float_type = match self.expression_type(expr)? {
+                    Some(Type::F32) => self.context.f32_type(),
+                    _ => self.context.f64_type(),
+                };
+                Ok(float_type.const_float(*f).into())
             },
+            Expression::String(s, _span) => Ok(self.string_constant(s).into()),
             Expression::Boolean(b, _span) => {
                 let i32_type = self.context.i32_type();
                 Ok(i32_type.const_int(if *b { 1 } else { 0 }, false).into())
             },
+            Expression::Null(_) => {
+                Ok(self.context.ptr_type(AddressSpace::default()).const_null().into())
+            },
+            Expression::Undefined(_) => Ok(self.context.i32_type().const_zero().into()),
             Expression::Identifier(name, _span) => {
-                if let Some(ptr) = self.get_variable(name) {
-                    // 根据保存的类型信息加载变量
-                    if let Some(var_type) = self.get_variable_type(name) {
-                        let value = self.builder().build_load(var_type, ptr, name)?;
-                        Ok(value)
-                    } else {
-                        // 默认使用 i32 类型（兼容旧代码）
-                        let i32_type = self.context.i32_type();
-                        let value = self.builder().build_load(i32_type, ptr, name)?;
-                        Ok(value)
-                    }
-                } else if let Some(symbol) = self.get_imported_symbol(name) {
-                    // 处理导入的符号
-                    match symbol {
-                        nexa_parser::module::ExportedSymbol::Variable(_, _) => {
-                            // 对于导入的变量，返回常量值
-                            // 目前返回 0，后续可以从模块信息中获取实际值
-                            let i32_type = self.context.i32_type();
-                            Ok(i32_type.const_int(0, false).into())
-                        },
-                        nexa_parser::module::ExportedSymbol::Function(func) => {
-                            // 对于导入的函数，需要先声明函数
-                            self.declare_imported_function(&func)?;
-                            // 然后获取函数 - 返回函数指针
-                            if let Some(fn_value) = self.module.get_function(&func.name) {
-                                // 将函数转换为指针返回
-                                let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
-                                let fn_ptr = self.builder().build_bit_cast(
-                                    fn_value.as_global_value().as_pointer_value(),
-                                    i8_ptr_type,
-                                    "fn_ptr",
-                                )?;
-                                Ok(fn_ptr)
-                            } else {
-                                Err(CodegenError {
-                                    message: format!("Imported function {} not found", name),
-                                })
-                            }
-                        },
-                        _ => Err(CodegenError {
-                            message: format!("Unsupported imported symbol type for {}", name),
-                        }),
+                if let Some((ptr, storage)) = self.variable_slot(name) {
+                    let value = self.builder().build_load(storage, ptr, name)?;
+                    // 被条件收窄的变量按收窄后的类型取值
+                    if let Some(declared) = self.type_info.declared_type_of(expr).cloned() {
+                        let declared = self.concrete_type(&declared)?;
+                        let narrowed = self.expression_type(expr)?;
+                        return self.coerce_value(value, Some(&declared), narrowed.as_ref());
                     }
+                    Ok(value)
+                } else if self.lookup_function(name).is_some() {
+                    // 具名函数 (包括导入的函数) 作为值使用时包装为闭包
+                    self.function_as_closure(name)
                 } else {
                     Err(CodegenError { message: format!("Variable {} not found", name) })
                 }
             },
-            Expression::This(_span) => {
-                // this 关键字在方法中代表当前实例的指针
-                // 目前返回空指针，后续需要实现方法中的 this 绑定
-                let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
-                Ok(i8_ptr_type.const_null().into())
+            Expression::This(_span) | Expression::Super(_span) => {
+                // this 是方法的第一个参数，指向当前实例；super 以父类视角访问同一实例
+                let ptr_type = self.context.ptr_type(AddressSpace::default());
+                let this = self.get_variable("this").ok_or_else(|| CodegenError {
+                    message: "'this' used outside of a class method".to_string(),
+                })?;
+                Ok(self.builder().build_load(ptr_type, this, "this")?)
+            },
+            Expression::Cast { expression, target, span: _ } => {
+                let value = self.generate_expression(expression)?;
+                let source = self.expression_type(expression)?.unwrap_or(Type::Any);
+                let target = self.concrete_type(target)?;
+                // 布尔值按无符号整数转换
+                let from = match self.resolve_type(&source) {
+                    Type::Boolean | Type::Literal(LiteralType::Boolean(_)) => Some(Type::Boolean),
+                    _ => self.numeric_type(&source),
+                };
+                match (from, self.numeric_type(&target)) {
+                    (Some(from), Some(to)) => self.build_numeric_cast(value, &from, &to),
+                    _ => self.coerce_value(value, Some(&source), Some(&target)),
+                }
             },
             Expression::Typeof { operand, span: _ } => {
-                // typeof 操作符 - 目前返回字符串 "number"
-                let _ = operand;
-                let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
-                Ok(i8_ptr_type.const_null().into())
+                let value = self.generate_expression(operand)?;
+                self.build_typeof(value, operand)
             },
             Expression::Instanceof { left, right, span: _ } => {
-                // instanceof 操作符 - 目前返回 false
-                let _ = (left, right);
-                let i32_type = self.context.i32_type();
-                Ok(i32_type.const_int(0, false).into())
-            },
-            Expression::In { left, right, span: _ } => {
-                // in 操作符 - 目前返回 false
-                let _ = (left, right);
-                let i32_type = self.context.i32_type();
-                Ok(i32_type.const_int(0, false).into())
+                let Expression::Identifier(class, _) = right.as_ref() else {
+                    return Err(CodegenError {
+                        message: "Right-hand side of instanceof must be a class".to_string(),
+                    });
+                };
+                let ty = self.expression_type(left)?.unwrap_or(Type::Any);
+                let value = self.generate_expression(left)?;
+                Ok(self.build_instanceof(value, &ty, class)?.into())
             },
+            Expression::In { left, right, span: _ } => self.generate_in(left, right),
             Expression::Delete { operand, span: _ } => {
                 // delete 操作符 - 目前返回 true
                 let _ = operand;
@@ -1028,32 +2669,38 @@ impl<'ctx> CodeGenerator<'ctx> {
                 let _ = chains;
                 self.generate_expression(base)
             },
+            Expression::Binary {
+                op: op @ (BinaryOp::LogicalAnd | BinaryOp::LogicalOr),
+                left,
+                right,
+                ..
+            } => self.generate_logical(*op == BinaryOp::LogicalAnd, left, right),
+            Expression::Binary {
+                op:
+                    op @ (BinaryOp::Equals
+                    | BinaryOp::NotEquals
+                    | BinaryOp::StrictEquals
+                    | BinaryOp::StrictNotEquals),
+                left,
+                right,
+                ..
+            } => self.generate_equality(op, left, right),
             Expression::Binary { op, left, right, span: _ } => {
                 let lhs = self.generate_expression(left)?;
                 let rhs = self.generate_expression(right)?;
+                let left_type = self.expression_type(left)?.unwrap_or(Type::Number);
+                let signed = self
+                    .numeric_type(&left_type)
+                    .and_then(|ty| ty.int_width())
+                    .is_none_or(|(_, signed)| signed);
 
                 let result = match op {
                     BinaryOp::Add => {
-                        // 检查是否是字符串拼接（指针类型）
-                        if lhs.is_pointer_value() {
-                            // 字符串拼接 - 委托给 Concat 处理
-                            let _ = left;
-                            let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
-
-                            let lhs_ptr = lhs.into_pointer_value();
-                            let rhs_ptr = if rhs.is_pointer_value() {
-                                rhs.into_pointer_value()
-                            } else if rhs.is_int_value() {
-                                self.builder().build_int_to_ptr(
-                                    rhs.into_int_value(),
-                                    i8_ptr_type,
-                                    "rhs_ptr",
-                                )?
-                            } else {
-                                return Err(CodegenError {
-                                    message: "Cannot concat non-pointer value".to_string(),
-                                });
-                            };
+                        // 类型检查把有字符串操作数的加法推导为字符串，按拼接处理
+                        let result_type = self.expression_type(expr)?.unwrap_or(Type::Number);
+                        if self.is_string_type(&result_type) {
+                            let lhs_ptr = self.build_string_operand(lhs, left)?;
+                            let rhs_ptr = self.build_string_operand(rhs, right)?;
 
                             // 获取字符串长度
                             let strlen_fn =
@@ -1149,269 +2796,17 @@ impl<'ctx> CodeGenerator<'ctx> {
                             )?;
 
                             allocated_ptr.into()
-                        } else if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_add(lhs.into_int_value(), rhs.into_int_value(), "add")?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_add(
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "fadd",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::Subtract => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_sub(lhs.into_int_value(), rhs.into_int_value(), "sub")?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_sub(
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "fsub",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::Multiply => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_mul(lhs.into_int_value(), rhs.into_int_value(), "mul")?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_mul(
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "fmul",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::Divide => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_signed_div(
-                                    lhs.into_int_value(),
-                                    rhs.into_int_value(),
-                                    "div",
-                                )?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_div(
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "fdiv",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::Modulo => self
-                        .builder()
-                        .build_int_signed_rem(lhs.into_int_value(), rhs.into_int_value(), "rem")?
-                        .into(),
-                    BinaryOp::Equals => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_compare(
-                                    inkwell::IntPredicate::EQ,
-                                    lhs.into_int_value(),
-                                    rhs.into_int_value(),
-                                    "eq",
-                                )?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_compare(
-                                    inkwell::FloatPredicate::OEQ,
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "eq",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::NotEquals => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_compare(
-                                    inkwell::IntPredicate::NE,
-                                    lhs.into_int_value(),
-                                    rhs.into_int_value(),
-                                    "ne",
-                                )?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_compare(
-                                    inkwell::FloatPredicate::ONE,
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "ne",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::LessThan => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_compare(
-                                    inkwell::IntPredicate::SLT,
-                                    lhs.into_int_value(),
-                                    rhs.into_int_value(),
-                                    "lt",
-                                )?
-                                .into()
                         } else {
-                            self.builder()
-                                .build_float_compare(
-                                    inkwell::FloatPredicate::OLT,
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "lt",
-                                )?
-                                .into()
+                            self.build_numeric_binary(op, lhs, rhs, signed)?
                         }
                     },
-                    BinaryOp::LessThanOrEqual => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_compare(
-                                    inkwell::IntPredicate::SLE,
-                                    lhs.into_int_value(),
-                                    rhs.into_int_value(),
-                                    "le",
-                                )?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_compare(
-                                    inkwell::FloatPredicate::OLE,
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "le",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::GreaterThan => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_compare(
-                                    inkwell::IntPredicate::SGT,
-                                    lhs.into_int_value(),
-                                    rhs.into_int_value(),
-                                    "gt",
-                                )?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_compare(
-                                    inkwell::FloatPredicate::OGT,
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "gt",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::GreaterThanOrEqual => {
-                        if lhs.is_int_value() {
-                            self.builder()
-                                .build_int_compare(
-                                    inkwell::IntPredicate::SGE,
-                                    lhs.into_int_value(),
-                                    rhs.into_int_value(),
-                                    "ge",
-                                )?
-                                .into()
-                        } else {
-                            self.builder()
-                                .build_float_compare(
-                                    inkwell::FloatPredicate::OGE,
-                                    lhs.into_float_value(),
-                                    rhs.into_float_value(),
-                                    "ge",
-                                )?
-                                .into()
-                        }
-                    },
-                    BinaryOp::LogicalAnd => {
-                        let i32_type = self.context.i32_type();
-                        let zero = i32_type.const_int(0, false);
-                        let lhs_nonzero = self.builder().build_int_compare(
-                            inkwell::IntPredicate::NE,
-                            lhs.into_int_value(),
-                            zero,
-                            "lhs_nz",
-                        )?;
-                        let rhs_nonzero = self.builder().build_int_compare(
-                            inkwell::IntPredicate::NE,
-                            rhs.into_int_value(),
-                            zero,
-                            "rhs_nz",
-                        )?;
-                        self.builder()
-                            .build_and(lhs_nonzero, rhs_nonzero, "and")?
-                            .as_basic_value_enum()
-                    },
-                    BinaryOp::LogicalOr => {
-                        let i32_type = self.context.i32_type();
-                        let zero = i32_type.const_int(0, false);
-                        let lhs_nonzero = self.builder().build_int_compare(
-                            inkwell::IntPredicate::NE,
-                            lhs.into_int_value(),
-                            zero,
-                            "lhs_nz",
-                        )?;
-                        let rhs_nonzero = self.builder().build_int_compare(
-                            inkwell::IntPredicate::NE,
-                            rhs.into_int_value(),
-                            zero,
-                            "rhs_nz",
-                        )?;
-                        self.builder()
-                            .build_or(lhs_nonzero, rhs_nonzero, "or")?
-                            .as_basic_value_enum()
-                    },
                     BinaryOp::Concat => {
                         // 字符串拼接
                         let i32_type = self.context.i32_type();
-                        let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
 
-                        // 将操作数转换为指针
-                        let lhs_ptr = if lhs.is_pointer_value() {
-                            lhs.into_pointer_value()
-                        } else if lhs.is_int_value() {
-                            self.builder().build_int_to_ptr(
-                                lhs.into_int_value(),
-                                i8_ptr_type,
-                                "lhs_ptr",
-                            )?
-                        } else {
-                            return Err(CodegenError {
-                                message: "Cannot concat non-pointer value".to_string(),
-                            });
-                        };
-                        let rhs_ptr = if rhs.is_pointer_value() {
-                            rhs.into_pointer_value()
-                        } else if rhs.is_int_value() {
-                            self.builder().build_int_to_ptr(
-                                rhs.into_int_value(),
-                                i8_ptr_type,
-                                "rhs_ptr",
-                            )?
-                        } else {
-                            return Err(CodegenError {
-                                message: "Cannot concat non-pointer value".to_string(),
-                            });
-                        };
+                        // 将操作数转换为字符串
+                        let lhs_ptr = self.build_string_operand(lhs, left)?;
+                        let rhs_ptr = self.build_string_operand(rhs, right)?;
 
                         // 获取字符串长度
                         let strlen_fn = self.module.get_function("strlen").ok_or_else(|| {
@@ -1493,11 +2888,7 @@ impl<'ctx> CodeGenerator<'ctx> {
                         // 返回结果指针
                         allocated_ptr.as_basic_value_enum()
                     },
-                    _ => {
-                        return Err(CodegenError {
-                            message: format!("Unsupported binary operator: {:?}", op),
-                        });
-                    },
+                    _ => self.build_numeric_binary(op, lhs, rhs, signed)?,
                 };
 
                 Ok(result)
@@ -1507,29 +2898,17 @@ impl<'ctx> CodeGenerator<'ctx> {
                 match op {
                     UnaryOp::Negate => {
                         if value.is_int_value() {
-                            let zero = self.context.i32_type().const_int(0, false);
-                            Ok(self
-                                .builder()
-                                .build_int_sub(zero, value.into_int_value(), "neg")?
-                                .into())
+                            Ok(self.builder().build_int_neg(value.into_int_value(), "neg")?.into())
                         } else {
-                            let zero = self.context.f64_type().const_float(0.0);
                             Ok(self
                                 .builder()
-                                .build_float_sub(zero, value.into_float_value(), "fneg")?
+                                .build_float_neg(value.into_float_value(), "fneg")?
                                 .into())
                         }
                     },
                     UnaryOp::LogicalNot => {
-                        let i32_type = self.context.i32_type();
-                        let zero = i32_type.const_int(0, false);
-                        let nonzero = self.builder().build_int_compare(
-                            inkwell::IntPredicate::EQ,
-                            value.into_int_value(),
-                            zero,
-                            "is_zero",
-                        )?;
-                        Ok(nonzero.into())
+                        let truthy = self.build_truthy(value)?;
+                        Ok(self.builder().build_not(truthy, "not")?.into())
                     },
                     UnaryOp::BitNot => {
                         // 位取反
@@ -1542,13 +2921,7 @@ impl<'ctx> CodeGenerator<'ctx> {
                             })
                         }
                     },
-                    UnaryOp::Typeof => {
-                        // typeof 操作符 - 返回类型名字符串
-                        // 目前简化处理，返回 "number"
-                        let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
-                        let result_ptr = i8_ptr_type.const_null();
-                        Ok(result_ptr.into())
-                    },
+                    UnaryOp::Typeof => self.build_typeof(value, operand),
                     UnaryOp::Delete => {
                         // delete 操作符 - 目前不支持，返回 null
                         let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
@@ -1596,71 +2969,49 @@ impl<'ctx> CodeGenerator<'ctx> {
                     },
                 }
             },
-            Expression::Call { callee, arguments, span: _ } => {
+            Expression::Call { callee, arguments, .. } => {
+                // 通过闭包值调用 (变量、参数、字段或表达式结果)
+                if self.is_closure_value(callee)? {
+                    return self.generate_closure_call(callee, arguments);
+                }
+
+                if let Some(result) = self.generate_method_call(callee, arguments)? {
+                    return Ok(result);
+                }
+
                 // 处理成员调用 (io.println, obj.method 等)
-                let (callee_name, is_method_call, object_ptr) = match callee.as_ref() {
+                let (callee_name, this_arg) = match callee.as_ref() {
                     Expression::Identifier(name, _) => {
-                        // 直接函数调用
-                        (name.clone(), false, None)
+                        match self.type_info.instantiation_of(expr).cloned() {
+                            // 泛型函数调用其对应类型实参的实例
+                            Some(args) if self.generic_functions.contains_key(name) => {
+                                let args = self.concrete_types(&args)?;
+                                (self.instantiate_function(name, &args)?, None)
+                            },
+                            // 直接函数调用
+                            _ => (name.clone(), None),
+                        }
                     },
                     Expression::Member { object, member, .. } => {
                         // 成员调用 (io.println 或 obj.method)
-                        // 检查对象是否是标识符
-                        if let Expression::Identifier(ns, _) = object.as_ref() {
+                        if let Some(class_name) = self.struct_name_of(object)? {
+                            // 对象的成员方法调用 (p.getX())，类名取自类型检查结果，
+                            // 对象指针作为隐式的 this 参数
+                            let this = self.generate_expression(object)?;
+                            (format!("{}_{}", class_name, member), Some(this))
+                        } else if let Some(name) = self.namespace_member(object, member) {
+                            // 命名空间导入的函数 (ns.func)
+                            (name, None)
+                        } else if let Expression::Identifier(ns, _) = object.as_ref() {
                             // 命名空间调用 (io.println -> std_io_println)
-                            (format!("{}_{}", ns, member), false, None)
+                            (format!("{}_{}", ns, member), None)
                         } else {
-                            // 对象的成员方法调用 (p.getX())
-                            // 先获取对象的指针
-                            let obj_expr = object.as_ref();
-                            if let Expression::Identifier(var_name, _) = obj_expr {
-                                // 获取变量的指针
-                                if let Some(ptr) = self.get_variable(var_name) {
-                                    // 获取变量的类型，根据类型生成正确的方法名
-                                    let method_name = if let Some(struct_type) =
-                                        self.find_struct_type_from_variable(var_name)
-                                    {
-                                        // 从结构体类型获取名称
-                                        if let Some(type_name) = struct_type.get_name() {
-                                            format!(
-                                                "{}_{}",
-                                                type_name.to_str().unwrap_or(""),
-                                                member
-                                            )
-                                        } else {
-                                            // 如果没有类型名，尝试从变量名推断类名（首字母大写）
-                                            let class_name: String = var_name
-                                                .chars()
-                                                .next()
-                                                .map(|c| c.to_uppercase().collect())
-                                                .unwrap_or_default();
-                                            format!("{}_{}", class_name, member)
-                                        }
-                                    } else {
-                                        // 如果没有找到结构体类型，尝试从变量名推断类名（首字母大写）
-                                        let class_name: String = var_name
-                                            .chars()
-                                            .next()
-                                            .map(|c| c.to_uppercase().collect())
-                                            .unwrap_or_default();
-                                        format!("{}_{}", class_name, member)
-                                    };
-                                    // 返回方法名和对象指针
-                                    (method_name, true, Some((var_name.clone(), ptr)))
-                                } else {
-                                    return Err(CodegenError {
-                                        message: format!(
-                                            "Variable {} not found for method call",
-                                            var_name
-                                        ),
-                                    });
-                                }
-                            } else {
-                                return Err(CodegenError {
-                                    message: "Object method calls only supported on identifiers"
-                                        .to_string(),
-                                });
-                            }
+                            return Err(CodegenError {
+                                message: format!(
+                                    "Cannot call member {} on this expression",
+                                    member
+                                ),
+                            });
                         }
                     },
                     _ => {
@@ -1672,11 +3023,15 @@ impl<'ctx> CodeGenerator<'ctx> {
 
                 let callee_name = callee_name.clone();
 
+                // 从模块导入的同名函数 (如 std/io 的 println) 按其声明调用，不作为内置函数
+                let imported = self.link_names.contains_key(&callee_name);
+
                 // 处理内置函数 (包括命名空间形式的调用如 io.println)
-                if callee_name == "println"
-                    || callee_name == "console.log"
-                    || callee_name == "console_log"
-                    || callee_name.starts_with("io_")
+                if !imported
+                    && (callee_name == "println"
+                        || callee_name == "console.log"
+                        || callee_name == "console_log"
+                        || callee_name.starts_with("io_"))
                 {
                     let puts_fn = self.module.get_function("puts").ok_or_else(|| CodegenError {
                         message: "puts function not found".to_string(),
@@ -1699,7 +3054,32 @@ impl<'ctx> CodeGenerator<'ctx> {
                         let arg_value = self.generate_expression(arg)?;
 
                         // 根据参数类型选择合适的函数
-                        if arg_value.is_int_value() {
+                        let arg_type = self.expression_type(arg)?.unwrap_or(Type::Number);
+                        let arg_type = self.numeric_type(&arg_type).unwrap_or(Type::Number);
+                        let is_i32 = arg_value.is_int_value()
+                            && arg_value.into_int_value().get_type().get_bit_width() == 32
+                            && arg_type != Type::U32;
+                        if !is_i32 && (arg_value.is_int_value() || arg_value.is_float_value()) {
+                            // 其它宽度的整数扩展为 64 位、浮点数扩展为 f64 后由 printf 打印
+                            let (format, target) = match arg_type.int_width() {
+                                Some((_, false)) => ("%llu\n", Type::U64),
+                                Some(_) if arg_value.is_int_value() => ("%lld\n", Type::I64),
+                                _ if arg_value.is_float_value() => ("%g\n", Type::F64),
+                                // 布尔比较结果 (i1)
+                                _ => ("%lld\n", Type::I64),
+                            };
+                            let value = self.build_numeric_cast(arg_value, &arg_type, &target)?;
+                            let format = self.string_constant(format);
+                            let printf_fn =
+                                self.module.get_function("printf").ok_or_else(|| CodegenError {
+                                    message: "printf function not found".to_string(),
+                                })?;
+                            self.builder().build_call(
+                                printf_fn,
+                                &[format.into(), value.into()],
+                                "printf_call",
+                            )?;
+                        } else if arg_value.is_int_value() {
                             // 如果是整数，调用 println_i32
                             let println_i32_fn = self
                                 .module
@@ -1740,7 +3120,7 @@ impl<'ctx> CodeGenerator<'ctx> {
                 }
 
                 // 处理 readln 函数
-                if callee_name == "readln" {
+                if callee_name == "readln" && !imported {
                     // 调用 std_io_readln 运行时函数
                     let readln_fn = self.module.get_function("std_io_readln").ok_or_else(|| {
                         CodegenError { message: "std_io_readln function not found".to_string() }
@@ -1784,33 +3164,17 @@ impl<'ctx> CodeGenerator<'ctx> {
                     return Ok(result);
                 }
 
-                // 对于方法调用，需要获取变量的类型信息
-                let mut args_values: Vec<inkwell::values::BasicMetadataValueEnum> = Vec::new();
-
-                // 如果是方法调用，获取对象指针并作为第一个参数
-                if is_method_call {
-                    if let Some((var_name, ptr)) = object_ptr {
-                        // 获取变量的类型
-                        if let Some(var_type) = self.get_variable_type(&var_name) {
-                            // 加载对象指针
-                            let obj_value = self.builder().build_load(var_type, ptr, &var_name)?;
-                            args_values.push(obj_value.into());
-                        } else {
-                            // 如果没有类型信息，直接使用指针
-                            let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
-                            let null_ptr = i8_ptr_type.const_null();
-                            args_values.push(null_ptr.into());
-                        }
-                    }
-                }
+                let mut args_values: Vec<inkwell::values::BasicMetadataValueEnum> = Vec::new();
 
-                for arg in arguments {
-                    let arg_val = self.generate_expression(arg)?;
-                    args_values.push(arg_val.into());
+                // 方法调用时对象指针作为第一个参数
+                if let Some(this) = this_arg {
+                    args_values.push(this.into());
                 }
 
-                let fn_value = self.module.get_function(&callee_name).ok_or_else(|| {
-                    CodegenError { message: format!("Function {} not found", callee_name) }
+                args_values.extend(self.generate_arguments(callee, arguments)?);
+
+                let fn_value = self.lookup_function(&callee_name).ok_or_else(|| CodegenError {
+                    message: format!("Function {} not found", callee_name),
                 })?;
 
                 let call_result = self.builder().build_call(fn_value, &args_values, "call")?;
@@ -1828,20 +3192,39 @@ impl<'ctx> CodeGenerator<'ctx> {
                 // 默认返回 0
                 Ok(i32_type.const_int(0, false).into())
             },
-            Expression::New { type_name, args, span: _ } => {
-                // new TypeName(args) - 分配并初始化对象
-                // 目前简化处理：分配 struct 空间并调用构造函数
+            Expression::New { type_name, args, .. } => {
+                // new TypeName(args) - 在堆上分配对象，字段清零后调用构造函数
+                // 泛型类使用类型检查推导出的实例
+                let type_name = &self.struct_name_of(expr)?.unwrap_or_else(|| type_name.clone());
 
                 // 查找 struct 类型
-                let struct_type = self.find_struct_type_from_variable(type_name);
+                let struct_type = self.struct_types.get(type_name).copied();
 
                 if let Some(struct_type) = struct_type {
                     // 分配 struct 空间
                     let ptr = self.builder().build_malloc(struct_type, type_name)?;
+                    self.builder().build_store(ptr, struct_type.const_zero())?;
+
+                    // class 对象头部指向本类的虚表
+                    if let Some(vtable) = self.lookup_global(&format!("__vtable_{}", type_name)) {
+                        let (_, index) = self.struct_field(type_name, VTABLE_FIELD).unwrap();
+                        let vtable_field = self.builder().build_struct_gep(
+                            struct_type,
+                            ptr,
+                            index,
+                            "vtable_ptr",
+                        )?;
+                        self.builder().build_store(vtable_field, vtable.as_pointer_value())?;
+                    }
 
-                    // 如果有参数，尝试调用构造函数 (目前忽略参数)
-                    // TODO: 实现构造函数调用
-                    let _ = args;
+                    if let Some((function, params)) = self.find_constructor(type_name) {
+                        let mut call_args: Vec<inkwell::values::BasicMetadataValueEnum> =
+                            vec![ptr.into()];
+                        for (arg, param) in args.iter().zip(&params) {
+                            call_args.push(self.generate_coerced(arg, Some(param))?.into());
+                        }
+                        self.builder().build_call(function, &call_args, "")?;
+                    }
 
                     Ok(ptr.as_basic_value_enum())
                 } else {
@@ -1878,7 +3261,8 @@ impl<'ctx> CodeGenerator<'ctx> {
                 let element = self.builder().build_load(i32_type, element_ptr, "array_element")?;
                 Ok(element)
             },
-            Expression::StructLiteral { name, fields, span: _ } => {
+            Expression::StructLiteral { name, fields, .. } => {
+                let name = &self.struct_name_of(expr)?.unwrap_or_else(|| name.clone());
                 // 获取或创建 struct 类型
                 let struct_type = self.get_or_create_struct_type(name, fields)?;
                 // 分配内存
@@ -1904,7 +3288,24 @@ impl<'ctx> CodeGenerator<'ctx> {
                 let loaded = self.builder().build_load(struct_type, alloca, "struct_load")?;
                 Ok(loaded)
             },
-            Expression::Member { object, member, span: _ } => {
+            Expression::Member { object, member, span } => {
+                // 枚举成员直接生成常量
+                if let Some(value) = self.enum_member(object, member) {
+                    return self.generate_enum_value(&value, span);
+                }
+
+                // 命名空间导入的成员按限定名称解析为导入的全局变量或函数
+                if let Some(name) = self.namespace_member(object, member) {
+                    return self.generate_expression(&Expression::Identifier(name, span.clone()));
+                }
+
+                // 带标签联合的成员按运行时标签分派
+                let members = self.expression_type(object)?.and_then(|t| self.tagged_members(&t));
+                if let Some(members) = members {
+                    let result = self.expression_type(expr)?.unwrap_or(Type::Any);
+                    return self.generate_union_member(object, &members, member, &result);
+                }
+
                 // 检查是否是 .length 属性访问（数组长度）
                 if member == "length" {
                     // 数组长度访问 - 需要返回数组的长度
@@ -1927,123 +3328,14 @@ impl<'ctx> CodeGenerator<'ctx> {
                     return Ok(i32_type.const_int(0, false).into());
                 }
 
-                // 检查对象是否是标识符
-                if let Expression::Identifier(var_name, _) = object.as_ref() {
-                    // 如果是标识符，获取变量的指针
-                    if let Some(ptr) = self.get_variable(var_name) {
-                        // 动态获取字段索引
-                        let field_index = self.get_field_index(var_name, member);
-                        let idx = field_index.unwrap_or(0) as u64;
-
-                        // 预先创建索引常量
-                        let i32_type = self.context.i32_type();
-                        let zero = i32_type.const_int(0, false);
-                        let field_idx = i32_type.const_int(idx, false);
-
-                        // 查找 struct 类型
-                        let struct_type = self.find_struct_type_from_variable(var_name);
-
-                        if let Some(struct_type) = struct_type {
-                            // 使用 struct 类型进行 GEP 获取字段地址
-                            let field_ptr = unsafe {
-                                self.builder().build_in_bounds_gep(
-                                    struct_type,
-                                    ptr,
-                                    &[zero, field_idx],
-                                    member,
-                                )?
-                            };
-
-                            // 获取字段的实际类型并加载
-                            let field_types = struct_type.get_field_types();
-                            let field_llvm_type = field_types[field_index.unwrap_or(0) as usize];
-                            let field_value =
-                                self.builder().build_load(field_llvm_type, field_ptr, member)?;
-                            return Ok(field_value);
-                        }
-
-                        // 如果没有找到 struct 类型，尝试使用 i32（兼容旧代码）
-                        let field_ptr = unsafe {
-                            self.builder().build_in_bounds_gep(
-                                i32_type,
-                                ptr,
-                                &[zero, field_idx],
-                                member,
-                            )?
-                        };
-
-                        // 加载字段值
-                        let field_value = self.builder().build_load(i32_type, field_ptr, member)?;
-                        return Ok(field_value);
-                    }
-                }
-
-                // 对于非标识符的对象，生成表达式
-                let obj_value = self.generate_expression(object)?;
-
-                // 检查对象的类型
-                if obj_value.is_pointer_value() {
-                    // 如果是指针，假设是 struct 指针
-                    // 简化处理：假设字段在 struct 中的偏移量为 i32
-                    let field_index = if member == "x" {
-                        0
-                    } else if member == "y" {
-                        1
-                    } else if member == "z" {
-                        2
-                    } else {
-                        0
-                    };
-
-                    // 预先创建索引常量
-                    let i32_type = self.context.i32_type();
-                    let zero = i32_type.const_int(0, false);
-                    let idx = i32_type.const_int(field_index as u64, false);
-
-                    // 使用 struct 作为基本类型进行 GEP
-                    let field_ptr = unsafe {
-                        self.builder().build_in_bounds_gep(
-                            i32_type,
-                            obj_value.into_pointer_value(),
-                            &[zero, idx],
-                            member,
-                        )?
-                    };
-
-                    // 加载字段值
-                    let field_value = self.builder().build_load(i32_type, field_ptr, member)?;
-                    Ok(field_value)
-                } else if obj_value.is_struct_value() {
-                    // 如果是 struct 值，使用 extractvalue
-                    let field_index = if member == "x" {
-                        0
-                    } else if member == "y" {
-                        1
-                    } else if member == "z" {
-                        2
-                    } else {
-                        0
-                    };
-
-                    // 使用 extractvalue 提取字段
-                    let field_value = self.builder().build_extract_value(
-                        obj_value.into_struct_value(),
-                        field_index,
-                        member,
-                    )?;
-                    Ok(field_value)
-                } else {
-                    Err(CodegenError {
-                        message: format!(
-                            "Member access on unsupported type: {:?}",
-                            obj_value.get_type()
-                        ),
-                    })
-                }
+                let (field_ptr, field_type) = self.member_pointer(object, member)?;
+                let field_value = self.builder().build_load(field_type, field_ptr, member)?;
+                Ok(field_value)
             },
             Expression::Assignment { target, value, span: _ } => {
                 // 支持 identifier = expr 和 obj.field = expr 形式的赋值
-                let value = self.generate_expression(value)?;
+                let target_type = self.expression_type(target)?;
+                let value = self.generate_coerced(value, target_type.as_ref())?;
 
                 match target.as_ref() {
                     Expression::Identifier(name, _) => {
@@ -2053,34 +3345,15 @@ impl<'ctx> CodeGenerator<'ctx> {
                         }
                     },
                     Expression::Member { object, member, span: _ } => {
-                        // 成员赋值: obj.field = value
-                        if let Expression::Identifier(var_name, _) = object.as_ref() {
-                            if let Some(ptr) = self.get_variable(var_name) {
-                                // 动态获取字段索引
-                                let field_index = self.get_field_index(var_name, member);
-                                let idx = field_index.unwrap_or(0) as u64;
-
-                                let i32_type = self.context.i32_type();
-                                let zero = i32_type.const_int(0, false);
-                                let field_idx = i32_type.const_int(idx, false);
-
-                                // 查找 struct 类型
-                                let struct_type = self.find_struct_type_from_variable(var_name);
-
-                                if let Some(struct_type) = struct_type {
-                                    // 使用 struct 类型进行 GEP
-                                    let field_ptr = unsafe {
-                                        self.builder().build_in_bounds_gep(
-                                            struct_type,
-                                            ptr,
-                                            &[zero, field_idx],
-                                            member.as_str(),
-                                        )?
-                                    };
-
-                                    self.builder().build_store(field_ptr, value)?;
-                                }
-                            }
+                        let namespace_variable = self
+                            .namespace_member(object, member)
+                            .and_then(|name| self.get_variable(&name));
+                        if let Some(ptr) = namespace_variable {
+                            // 命名空间导入的变量: ns.name = value
+                            self.builder().build_store(ptr, value)?;
+                        } else {
+                            // 成员赋值: obj.field = value
+                            self.store_member(object, member, value)?;
                         }
                     },
                     _ => {},
@@ -2088,11 +3361,20 @@ impl<'ctx> CodeGenerator<'ctx> {
 
                 Ok(value)
             },
-            Expression::FunctionExpression { .. } => {
-                // 函数表达式暂不支持作为表达式值
-                Err(CodegenError {
-                    message: "Function expressions are not supported as values".to_string(),
-                })
+            Expression::FunctionExpression { parameters, return_type, body, captures, span: _ } => {
+                // 省略的返回类型以类型检查推导的结果为准，参数类型取实例化后的类型
+                let (parameters, return_type) = match self.expression_type(expr)? {
+                    Some(Type::Function(types, inferred)) => {
+                        let parameters = parameters
+                            .iter()
+                            .zip(types)
+                            .map(|(p, ty)| Parameter { name: p.name.clone(), type_annotation: ty })
+                            .collect();
+                        (parameters, *inferred)
+                    },
+                    _ => (parameters.clone(), self.concrete_type(return_type)?),
+                };
+                self.generate_closure(&parameters, &return_type, body, captures)
             },
             Expression::ArrayLiteral { elements, span: _ } => {
                 // 数组字面量: 为每个元素分配内存
@@ -2122,34 +3404,10 @@ impl<'ctx> CodeGenerator<'ctx> {
             },
             Expression::Ternary { condition, then_expr, else_expr, span: _ } => {
                 // 三元表达式: condition ? then_expr : else_expr
-                let i32_type = self.context.i32_type();
-
                 // 生成条件值
                 let cond_value = self.generate_expression(condition)?;
 
-                // 将条件转换为 i1 进行分支判断
-                let cond_i1 = match cond_value {
-                    inkwell::values::BasicValueEnum::IntValue(v) => {
-                        // 如果是 i1 类型，直接使用
-                        if v.get_type().get_bit_width() == 1 {
-                            v
-                        } else {
-                            // 如果是 i32，转换为 i1 (非零为 true)
-                            let zero = i32_type.const_int(0, false);
-                            self.builder().build_int_compare(
-                                inkwell::IntPredicate::NE,
-                                v,
-                                zero,
-                                "cond",
-                            )?
-                        }
-                    },
-                    _ => {
-                        return Err(CodegenError {
-                            message: "Ternary condition must be integer".to_string(),
-                        });
-                    },
-                };
+                let cond_i1 = self.build_truthy(cond_value)?;
 
                 // 创建基本块
                 let current_function =
@@ -2170,162 +3428,1163 @@ impl<'ctx> CodeGenerator<'ctx> {
                 let then_value = self.generate_expression(then_expr)?;
                 self.builder().build_unconditional_branch(merge_block)?;
 
-                // 生成 else 分支
-                self.builder().position_at_end(else_block);
-                let else_value = self.generate_expression(else_expr)?;
-                self.builder().build_unconditional_branch(merge_block)?;
+                // 生成 else 分支
+                self.builder().position_at_end(else_block);
+                let else_value = self.generate_expression(else_expr)?;
+                self.builder().build_unconditional_branch(merge_block)?;
+
+                // 在 merge 块中创建 phi 节点
+                self.builder().position_at_end(merge_block);
+                let phi = self.builder().build_phi(then_value.get_type(), "ternary_result")?;
+                phi.add_incoming(&[(&then_value, then_block), (&else_value, else_block)]);
+
+                Ok(phi.as_basic_value())
+            },
+        }
+    }
+
+    /// 获取或创建 struct 类型
+    fn get_or_create_struct_type(
+        &mut self,
+        name: &str,
+        fields: &[(String, Expression)],
+    ) -> Result<inkwell::types::StructType<'ctx>, CodegenError> {
+        // 检查是否已存在
+        if let Some(struct_type) = self.struct_types.get(name) {
+            return Ok(*struct_type);
+        }
+
+        // 创建 struct 类型
+        let field_types: Vec<inkwell::types::BasicTypeEnum> = fields
+            .iter()
+            .map(|(_, _expr)| {
+                // 简化处理：假设所有字段都是 i32
+                self.context.i32_type().into()
+            })
+            .collect();
+
+        let struct_type = self.context.opaque_struct_type(name);
+        struct_type.set_body(&field_types, false);
+
+        self.struct_types.insert(name.to_string(), struct_type);
+        Ok(struct_type)
+    }
+
+    /// 根据 struct 名和字段名查找 struct 类型与字段索引
+    fn struct_field(
+        &self,
+        struct_name: &str,
+        field_name: &str,
+    ) -> Option<(inkwell::types::StructType<'ctx>, u32)> {
+        let struct_type = *self.struct_types.get(struct_name)?;
+        let index =
+            self.struct_field_lists.get(struct_name)?.iter().position(|(f, _)| f == field_name)?;
+        Some((struct_type, index as u32))
+    }
+
+    /// 成员赋值: obj.field = value
+    fn store_member(
+        &mut self,
+        object: &Expression,
+        member: &str,
+        value: BasicValueEnum<'ctx>,
+    ) -> Result<(), CodegenError> {
+        let (field_ptr, _) = self.member_pointer(object, member)?;
+        self.builder().build_store(field_ptr, value)?;
+        Ok(())
+    }
+
+    /// 计算成员字段的地址和 LLVM 类型
+    ///
+    /// class 对象本身就是指针，直接对其做 GEP；
+    /// struct 值优先使用变量地址，临时值先存入栈上再取地址。
+    fn member_pointer(
+        &mut self,
+        object: &Expression,
+        member: &str,
+    ) -> Result<(PointerValue<'ctx>, BasicTypeEnum<'ctx>), CodegenError> {
+        // 字段索引由类型检查得到的 struct 类型确定
+        let struct_name = self.struct_name_of(object)?.ok_or_else(|| CodegenError {
+            message: format!("Member access .{} on non-struct value", member),
+        })?;
+        if self.interface_defs.contains_key(&struct_name) {
+            return self.interface_field_pointer(object, &struct_name, member);
+        }
+        let (struct_type, index) = self.struct_field(&struct_name, member).ok_or_else(|| {
+            CodegenError { message: format!("Struct {} has no field {}", struct_name, member) }
+        })?;
+
+        // 被收窄的变量以声明类型存储，不能直接使用其地址
+        let narrowed = self.type_info.declared_type_of(object).is_some();
+        let variable = match object {
+            Expression::Identifier(name, _) if !self.is_class(&struct_name) && !narrowed => {
+                self.get_variable(name)
+            },
+            _ => None,
+        };
+        let base = match variable {
+            Some(ptr) => ptr,
+            None => match self.generate_expression(object)? {
+                BasicValueEnum::PointerValue(ptr) => ptr,
+                BasicValueEnum::StructValue(value) => {
+                    let tmp = self.builder().build_alloca(struct_type, "struct_tmp")?;
+                    self.builder().build_store(tmp, value)?;
+                    tmp
+                },
+                other => {
+                    return Err(CodegenError {
+                        message: format!(
+                            "Member access on unsupported type: {:?}",
+                            other.get_type()
+                        ),
+                    })
+                },
+            },
+        };
+
+        let field_ptr = self.builder().build_struct_gep(struct_type, base, index, member)?;
+        Ok((field_ptr, struct_type.get_field_types()[index as usize]))
+    }
+
+    /// 是否为 class (以对象指针表示)
+    fn is_class(&self, name: &str) -> bool {
+        self.class_defs.contains_key(name)
+    }
+
+    /// 将 Nexa 类型映射到 LLVM 类型 (TypeScript 风格)
+    fn map_type(&self, ty: &Type) -> BasicTypeEnum<'ctx> {
+        match ty {
+            Type::Struct(name) if self.type_aliases.contains_key(name) => {
+                self.map_type(&self.resolve_type(ty))
+            },
+            Type::Literal(literal) => self.map_type(&literal.base_type()),
+            Type::Intersection(_) => self.map_type(&self.resolve_type(ty)),
+            Type::Union(_) => match self.tagged_members(ty) {
+                Some(members) => self.union_type(&members).into(),
+                None => match self.plain_union_member(ty) {
+                    Some(member) => self.map_type(&member),
+                    None => self.context.ptr_type(AddressSpace::default()).into(),
+                },
+            },
+            Type::Number | Type::I32 | Type::U32 => self.context.i32_type().into(),
+            Type::I8 | Type::U8 => self.context.i8_type().into(),
+            Type::I16 | Type::U16 => self.context.i16_type().into(),
+            Type::I64 | Type::U64 => self.context.i64_type().into(),
+            Type::F32 => self.context.f32_type().into(),
+            Type::F64 => self.context.f64_type().into(),
+            Type::Boolean => self.context.i32_type().into(),
+            Type::String => self.context.ptr_type(AddressSpace::default()).into(),
+            Type::Void => {
+                // Void 类型作为函数返回类型时不映射到 BasicTypeEnum
+                // 这里返回 i32 作为占位符
+                self.context.i32_type().into()
+            },
+            Type::Undefined => self.context.i32_type().into(),
+            Type::Null => self.context.ptr_type(AddressSpace::default()).into(),
+            Type::Any => self.context.ptr_type(AddressSpace::default()).into(),
+            Type::Never => self.context.i32_type().into(),
+            Type::Array(_) => self.context.ptr_type(AddressSpace::default()).into(),
+            Type::Pointer(_) => self.context.ptr_type(AddressSpace::default()).into(),
+            Type::Function(_, _) => self.closure_type().into(),
+            Type::Struct(name) => {
+                // class 值是指向堆上对象的指针
+                if self.is_class(name) {
+                    return self.context.ptr_type(AddressSpace::default()).into();
+                }
+                if self.interface_defs.contains_key(name) {
+                    return self.interface_type().into();
+                }
+                // 数值枚举是 i32，字符串枚举是字符串指针
+                if let Some(enum_def) = self.enum_defs.get(name) {
+                    if enum_def.is_string() {
+                        return self.context.ptr_type(AddressSpace::default()).into();
+                    }
+                    return self.context.i32_type().into();
+                }
+                // 尝试查找已注册的 struct 类型
+                if let Some(struct_type) = self.struct_types.get(name) {
+                    (*struct_type).into()
+                } else {
+                    // 如果未找到，返回指针类型作为后备
+                    self.context.ptr_type(AddressSpace::default()).into()
+                }
+            },
+            Type::Object(_) => self.context.ptr_type(AddressSpace::default()).into(),
+            Type::Generic(name, args) => {
+                self.map_type(&Type::Struct(Self::instance_name(name, args)))
+            },
+        }
+    }
+
+    /// 展开顶层的类型别名
+    ///
+    /// 交叉类型在运行时以其中第一个 class 成员 (没有时为第一个成员) 表示。
+    fn resolve_type(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Struct(name) => match self.type_aliases.get(name) {
+                Some(target) => self.resolve_type(target),
+                None => ty.clone(),
+            },
+            Type::Intersection(types) => {
+                let class = types.iter().find(
+                    |t| matches!(self.resolve_type(t), Type::Struct(name) if self.is_class(&name)),
+                );
+                self.resolve_type(class.or(types.first()).unwrap_or(&Type::Any))
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    /// 类型对应的数值类型：数值字面量和数值枚举为 number，非数值类型为 None
+    fn numeric_type(&self, ty: &Type) -> Option<Type> {
+        match self.resolve_type(ty) {
+            Type::Literal(LiteralType::Number(_)) => Some(Type::Number),
+            Type::Struct(name) => match self.enum_defs.get(&name) {
+                Some(def) if !def.is_string() => Some(Type::Number),
+                _ => None,
+            },
+            ty if ty.is_numeric() => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// 数值类型转换：整数按源类型的有无符号扩展或截断，整数与浮点数按各自的有无符号互转
+    fn build_numeric_cast(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        source: &Type,
+        target: &Type,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let signed = source.int_width().is_some_and(|(_, signed)| signed);
+        let target_signed = target.int_width().is_some_and(|(_, signed)| signed);
+        let target_type = self.map_type(target);
+        let builder = self.builder();
+        let result = match (value, target_type) {
+            (BasicValueEnum::IntValue(v), BasicTypeEnum::IntType(t)) => {
+                let (from, to) = (v.get_type().get_bit_width(), t.get_bit_width());
+                if from > to {
+                    builder.build_int_truncate(v, t, "trunc")?.into()
+                } else if from == to {
+                    v.into()
+                } else if signed && from > 1 {
+                    builder.build_int_s_extend(v, t, "sext")?.into()
+                } else {
+                    builder.build_int_z_extend(v, t, "zext")?.into()
+                }
+            },
+            (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(t)) => {
+                if signed && v.get_type().get_bit_width() > 1 {
+                    builder.build_signed_int_to_float(v, t, "sitofp")?.into()
+                } else {
+                    builder.build_unsigned_int_to_float(v, t, "uitofp")?.into()
+                }
+            },
+            (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(t)) => {
+                if target_signed {
+                    builder.build_float_to_signed_int(v, t, "fptosi")?.into()
+                } else {
+                    builder.build_float_to_unsigned_int(v, t, "fptoui")?.into()
+                }
+            },
+            (BasicValueEnum::FloatValue(v), BasicTypeEnum::FloatType(t)) => {
+                builder.build_float_cast(v, t, "fpcast")?.into()
+            },
+            (value, _) => value,
+        };
+        Ok(result)
+    }
+
+    /// 联合类型在运行时区分的成员
+    ///
+    /// 展开别名和嵌套联合，字面量类型按其基础类型合并。
+    fn union_members(&self, ty: &Type) -> Option<Vec<Type>> {
+        let Type::Union(types) = self.resolve_type(ty) else { return None };
+        let mut members: Vec<Type> = Vec::new();
+        for ty in types {
+            let nested = match self.resolve_type(&ty) {
+                Type::Union(_) => self.union_members(&ty).unwrap_or_default(),
+                Type::Literal(literal) => vec![literal.base_type()],
+                other => vec![other],
+            };
+            for member in nested {
+                if !members.contains(&member) {
+                    members.push(member);
+                }
+            }
+        }
+        Some(members)
+    }
+
+    /// 需要标签区分的联合类型成员，标签值即成员的下标
+    ///
+    /// 只有一种成员 (加上 null / undefined 时该成员以指针表示) 的联合直接使用该成员的表示。
+    fn tagged_members(&self, ty: &Type) -> Option<Vec<Type>> {
+        let members = self.union_members(ty)?;
+        let present: Vec<&Type> =
+            members.iter().filter(|t| !matches!(t, Type::Null | Type::Undefined)).collect();
+        match present.as_slice() {
+            [] => None,
+            [single]
+                if present.len() == members.len() || self.map_type(single).is_pointer_type() =>
+            {
+                None
+            },
+            _ => Some(members),
+        }
+    }
+
+    /// 不需要标签的联合类型中唯一的非 null 成员
+    fn plain_union_member(&self, ty: &Type) -> Option<Type> {
+        if self.tagged_members(ty).is_some() {
+            return None;
+        }
+        self.union_members(ty)?.into_iter().find(|t| !matches!(t, Type::Null | Type::Undefined))
+    }
+
+    /// 带标签联合的 LLVM 类型: {i32 标签, [N x i64] 负载}，N 足以容纳最大的成员
+    fn union_type(&self, members: &[Type]) -> inkwell::types::StructType<'ctx> {
+        let words = members
+            .iter()
+            .filter(|t| !matches!(t, Type::Null | Type::Undefined))
+            .map(|t| self.word_count(self.map_type(t)))
+            .max()
+            .unwrap_or(1);
+        let payload = self.context.i64_type().array_type(words);
+        self.context.struct_type(&[self.context.i32_type().into(), payload.into()], false)
+    }
+
+    /// 值占用的 8 字节字数 (按每个标量字段各占一字保守估计)
+    fn word_count(&self, ty: BasicTypeEnum<'ctx>) -> u32 {
+        match ty {
+            BasicTypeEnum::StructType(struct_type) => struct_type
+                .get_field_types()
+                .into_iter()
+                .map(|t| self.word_count(t))
+                .sum::<u32>()
+                .max(1),
+            BasicTypeEnum::ArrayType(array_type) => {
+                array_type.len() * self.word_count(array_type.get_element_type())
+            },
+            _ => 1,
+        }
+    }
+
+    /// 值在联合类型中对应的成员下标
+    ///
+    /// 没有完全相同的成员时，null 放入第一个指针表示的成员，
+    /// class 对象放入它实现的接口或它的父类成员。
+    fn union_tag(&self, members: &[Type], source: &Type) -> Option<usize> {
+        let source = match self.resolve_type(source) {
+            Type::Literal(literal) => literal.base_type(),
+            other => other,
+        };
+        if let Some(index) = members.iter().position(|m| *m == source) {
+            return Some(index);
+        }
+        members.iter().position(|member| match (member, &source) {
+            (_, Type::Null | Type::Undefined) => self.map_type(member).is_pointer_type(),
+            // 数值放入 (可隐式拓宽到的) 数值成员
+            (member, source) if member.is_numeric() && source.is_numeric() => true,
+            (Type::Struct(target), Type::Struct(class)) if self.is_class(class) => {
+                self.interface_defs.contains_key(target)
+                    || self.class_chain(class).iter().any(|def| def.name == *target)
+            },
+            _ => false,
+        })
+    }
+
+    /// 把联合类型的值存入栈上临时变量，返回 (临时变量地址, 联合的 LLVM 类型)
+    fn spill_union(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+    ) -> Result<(PointerValue<'ctx>, inkwell::types::StructType<'ctx>), CodegenError> {
+        let union_type = value.into_struct_value().get_type();
+        let tmp = self.builder().build_alloca(union_type, "union_tmp")?;
+        self.builder().build_store(tmp, value)?;
+        Ok((tmp, union_type))
+    }
+
+    /// 组装带标签联合的值：负载先清零，null / undefined 成员不写入负载
+    fn build_union_value(
+        &mut self,
+        union_type: inkwell::types::StructType<'ctx>,
+        tag: inkwell::values::IntValue<'ctx>,
+        payload: Option<BasicValueEnum<'ctx>>,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let tmp = self.builder().build_alloca(union_type, "union_tmp")?;
+        let tag_ptr = self.builder().build_struct_gep(union_type, tmp, 0, "union_tag")?;
+        self.builder().build_store(tag_ptr, tag)?;
+        let payload_ptr = self.builder().build_struct_gep(union_type, tmp, 1, "union_payload")?;
+        let payload_type = union_type.get_field_types()[1];
+        self.builder().build_store(payload_ptr, payload_type.const_zero())?;
+        if let Some(payload) = payload {
+            self.builder().build_store(payload_ptr, payload)?;
+        }
+        Ok(self.builder().build_load(union_type, tmp, "union")?)
+    }
+
+    /// 值转换为联合类型
+    fn coerce_to_union(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        source: &Type,
+        target: &Type,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let error = || CodegenError { message: format!("Cannot convert {} to {}", source, target) };
+        let Some(members) = self.tagged_members(target) else {
+            // 直接以成员表示的联合：undefined 用空指针表示
+            return match self.plain_union_member(target) {
+                Some(member) if matches!(source, Type::Null | Type::Undefined) => {
+                    Ok(self.map_type(&member).const_zero())
+                },
+                Some(member) => self.coerce_value(value, Some(source), Some(&member)),
+                None => Ok(value),
+            };
+        };
+        let union_type = self.union_type(&members);
+        let i32_type = self.context.i32_type();
+
+        if let Some(source_members) = self.tagged_members(source) {
+            if source_members == members {
+                return Ok(value);
+            }
+            // 成员不同的联合之间转换：逐个映射标签，负载按字复制
+            let (src, src_type) = self.spill_union(value)?;
+            let tag_ptr = self.builder().build_struct_gep(src_type, src, 0, "src_tag")?;
+            let tag = self.builder().build_load(i32_type, tag_ptr, "tag")?.into_int_value();
+            let mut new_tag = i32_type.const_zero();
+            for (i, member) in source_members.iter().enumerate() {
+                let Some(j) = self.union_tag(&members, member) else { continue };
+                let is_member = self.builder().build_int_compare(
+                    inkwell::IntPredicate::EQ,
+                    tag,
+                    i32_type.const_int(i as u64, false),
+                    "is_member",
+                )?;
+                new_tag = self
+                    .builder()
+                    .build_select(is_member, i32_type.const_int(j as u64, false), new_tag, "tag")?
+                    .into_int_value();
+            }
+            let words =
+                self.word_count(src_type.into()).min(self.word_count(union_type.into())) - 1;
+            let payload_type = self.context.i64_type().array_type(words);
+            let src_payload = self.builder().build_struct_gep(src_type, src, 1, "src_payload")?;
+            let payload = self.builder().build_load(payload_type, src_payload, "payload")?;
+            return self.build_union_value(union_type, new_tag, Some(payload));
+        }
+
+        if let Some(member) = self.plain_union_member(source) {
+            // 可空指针：空指针对应 null 成员
+            let tag = self.union_tag(&members, &member).ok_or_else(error)?;
+            let null_tag = self.union_tag(&members, &Type::Null).unwrap_or(tag);
+            let is_null = self.builder().build_is_null(value.into_pointer_value(), "is_null")?;
+            let tag = self
+                .builder()
+                .build_select(
+                    is_null,
+                    i32_type.const_int(null_tag as u64, false),
+                    i32_type.const_int(tag as u64, false),
+                    "tag",
+                )?
+                .into_int_value();
+            return self.build_union_value(union_type, tag, Some(value));
+        }
+
+        let index = self.union_tag(&members, source).ok_or_else(error)?;
+        let member = members[index].clone();
+        let tag = i32_type.const_int(index as u64, false);
+        if matches!(source, Type::Null | Type::Undefined) {
+            return self.build_union_value(union_type, tag, None);
+        }
+        let payload = self.coerce_value(value, Some(source), Some(&member))?;
+        self.build_union_value(union_type, tag, Some(payload))
+    }
+
+    /// 从带标签联合中取出负载 (类型检查已收窄到具体成员)
+    fn union_payload(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        target: &Type,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let (tmp, union_type) = self.spill_union(value)?;
+        let payload_ptr = self.builder().build_struct_gep(union_type, tmp, 1, "union_payload")?;
+        let target_type = self.map_type(target);
+        Ok(self.builder().build_load(target_type, payload_ptr, "payload")?)
+    }
+
+    /// 联合类型对象的成员访问：按标签分派到各成员，结果合并为访问表达式的类型
+    fn generate_union_member(
+        &mut self,
+        object: &Expression,
+        members: &[Type],
+        member: &str,
+        result: &Type,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let value = self.generate_expression(object)?;
+        let (tmp, union_type) = self.spill_union(value)?;
+        let i32_type = self.context.i32_type();
+        let tag_ptr = self.builder().build_struct_gep(union_type, tmp, 0, "union_tag")?;
+        let tag = self.builder().build_load(i32_type, tag_ptr, "tag")?.into_int_value();
+        let payload_ptr = self.builder().build_struct_gep(union_type, tmp, 1, "union_payload")?;
+        let entry = self.builder().get_insert_block().unwrap();
+
+        let function = self.current_function.ok_or_else(|| CodegenError {
+            message: "Member access outside of a function".to_string(),
+        })?;
+        let merge_block = self.context.append_basic_block(function, "member_merge");
+        let default_block = self.context.append_basic_block(function, "member_none");
+        let mut cases = Vec::new();
+        let mut incoming = Vec::new();
+        for (i, ty) in members.iter().enumerate() {
+            let Type::Struct(name) = ty else { continue };
+            let (struct_type, index) = self.struct_field(name, member).ok_or_else(|| {
+                CodegenError { message: format!("Struct {} has no field {}", name, member) }
+            })?;
+            let field_type = self.struct_field_lists[name][index as usize].1.clone();
+            let block = self.context.append_basic_block(function, &format!("member_{}", name));
+            cases.push((i32_type.const_int(i as u64, false), block));
+            self.builder().position_at_end(block);
+            // class 成员的负载是对象指针，struct 成员的负载就是 struct 值本身
+            let base = if self.is_class(name) {
+                let ptr_type = self.context.ptr_type(AddressSpace::default());
+                self.builder().build_load(ptr_type, payload_ptr, "object")?.into_pointer_value()
+            } else {
+                payload_ptr
+            };
+            let field_ptr = self.builder().build_struct_gep(struct_type, base, index, member)?;
+            let llvm_type = struct_type.get_field_types()[index as usize];
+            let field = self.builder().build_load(llvm_type, field_ptr, member)?;
+            let field = self.coerce_value(field, Some(&field_type), Some(result))?;
+            let end = self.builder().get_insert_block().unwrap();
+            self.builder().build_unconditional_branch(merge_block)?;
+            incoming.push((field, end));
+        }
+
+        self.builder().position_at_end(default_block);
+        self.builder().build_unreachable()?;
+        // 各成员块生成后再回到原块末尾构建 switch
+        self.builder().position_at_end(entry);
+        self.builder().build_switch(tag, default_block, &cases)?;
+
+        self.builder().position_at_end(merge_block);
+        let result_type = self.map_type(result);
+        let phi = self.builder().build_phi(result_type, "member")?;
+        for (value, block) in &incoming {
+            phi.add_incoming(&[(value, *block)]);
+        }
+        Ok(phi.as_basic_value())
+    }
 
-                // 在 merge 块中创建 phi 节点
-                self.builder().position_at_end(merge_block);
-                let phi = self.builder().build_phi(i32_type, "ternary_result")?;
-                phi.add_incoming(&[(&then_value, then_block), (&else_value, else_block)]);
+    /// 字符串常量
+    fn string_constant(&mut self, value: &str) -> PointerValue<'ctx> {
+        let text = self.context.const_string(value.as_bytes(), true);
+        let global = self.module.add_global(text.get_type(), None, "str");
+        global.set_initializer(&text);
+        global.as_pointer_value()
+    }
 
-                Ok(phi.as_basic_value())
-            },
+    /// 带标签联合的标签值
+    fn union_tag_value(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let value = value.into_struct_value();
+        Ok(self.builder().build_extract_value(value, 0, "tag")?.into_int_value())
+    }
+
+    /// 是否以字符串表示 (字符串、字符串字面量和字符串枚举)
+    fn is_string_type(&self, ty: &Type) -> bool {
+        match self.resolve_type(ty) {
+            Type::String | Type::Literal(LiteralType::String(_)) => true,
+            Type::Struct(name) => self.enum_defs.get(&name).is_some_and(|def| def.is_string()),
+            _ => false,
         }
     }
 
-    /// 获取或创建 struct 类型
-    fn get_or_create_struct_type(
+    /// 字符串拼接的操作数：字符串直接使用，布尔值和数值按类型检查得到的类型格式化为字符串
+    fn build_string_operand(
         &mut self,
-        name: &str,
-        fields: &[(String, Expression)],
-    ) -> Result<inkwell::types::StructType<'ctx>, CodegenError> {
-        // 检查是否已存在
-        if let Some(struct_type) = self.struct_types.get(name) {
-            return Ok(*struct_type);
+        value: BasicValueEnum<'ctx>,
+        operand: &Expression,
+    ) -> Result<PointerValue<'ctx>, CodegenError> {
+        let ty = self.expression_type(operand)?.unwrap_or(Type::String);
+        if let BasicValueEnum::PointerValue(ptr) = value {
+            return Ok(ptr);
+        }
+        if matches!(self.resolve_type(&ty), Type::Boolean | Type::Literal(LiteralType::Boolean(_)))
+        {
+            let truthy = self.build_truthy(value)?;
+            let yes = self.string_constant("true");
+            let no = self.string_constant("false");
+            let text = self.builder().build_select(truthy, yes, no, "bool_str")?;
+            return Ok(text.into_pointer_value());
         }
+        let Some(numeric) = self.numeric_type(&ty) else {
+            return Err(CodegenError { message: format!("Cannot concat value of type {}", ty) });
+        };
+        let (format, target) = match numeric.int_width() {
+            Some((_, false)) => ("%llu", Type::U64),
+            Some(_) => ("%lld", Type::I64),
+            None => ("%g", Type::F64),
+        };
+        let value = self.build_numeric_cast(value, &numeric, &target)?;
+        let format = self.string_constant(format);
+        // 64 位整数和 %g 格式的浮点数都不超过 32 个字符
+        let size = self.context.i64_type().const_int(32, false);
+        let i8_type = self.context.i8_type();
+        let buffer = self.builder().build_array_malloc(i8_type, size, "num_str")?;
+        let snprintf = self
+            .module
+            .get_function("snprintf")
+            .ok_or_else(|| CodegenError { message: "snprintf function not found".to_string() })?;
+        self.builder().build_call(
+            snprintf,
+            &[buffer.into(), size.into(), format.into(), value.into()],
+            "snprintf_call",
+        )?;
+        Ok(buffer)
+    }
 
-        // 创建 struct 类型
-        let field_types: Vec<inkwell::types::BasicTypeEnum> = fields
-            .iter()
-            .map(|(_, _expr)| {
-                // 简化处理：假设所有字段都是 i32
-                self.context.i32_type().into()
-            })
-            .collect();
+    /// 短路求值的 && 和 ||：右操作数只在需要时求值
+    fn generate_logical(
+        &mut self,
+        is_and: bool,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let function = self.current_function.ok_or_else(|| CodegenError {
+            message: "Logical operator outside of a function".to_string(),
+        })?;
+        let lhs = self.generate_expression(left)?;
+        let lhs = self.build_truthy(lhs)?;
+        let lhs_block = self.builder().get_insert_block().unwrap();
+        let rhs_block = self.context.append_basic_block(function, "logic_rhs");
+        let merge_block = self.context.append_basic_block(function, "logic_merge");
+        if is_and {
+            self.builder().build_conditional_branch(lhs, rhs_block, merge_block)?;
+        } else {
+            self.builder().build_conditional_branch(lhs, merge_block, rhs_block)?;
+        }
 
-        let struct_type = self.context.opaque_struct_type(name);
-        struct_type.set_body(&field_types, false);
+        self.builder().position_at_end(rhs_block);
+        let rhs = self.generate_expression(right)?;
+        let rhs = self.build_truthy(rhs)?;
+        let rhs_block = self.builder().get_insert_block().unwrap();
+        self.builder().build_unconditional_branch(merge_block)?;
 
-        self.struct_types.insert(name.to_string(), struct_type);
-        Ok(struct_type)
+        self.builder().position_at_end(merge_block);
+        let bool_type = self.context.bool_type();
+        let phi = self.builder().build_phi(bool_type, "logic")?;
+        phi.add_incoming(&[(&lhs, lhs_block), (&rhs, rhs_block)]);
+        Ok(phi.as_basic_value())
     }
 
-    /// 从变量查找 struct 类型
-    /// 遍历所有 struct 类型，找到变量对应的 struct 类型
-    fn find_struct_type_from_variable(
-        &self,
-        var_name: &str,
-    ) -> Option<inkwell::types::StructType<'ctx>> {
-        // 检查变量类型映射
-        if let Some(var_type) = self.variable_types.get(var_name) {
-            // 处理指针类型（通过指针查找指向的结构体类型）
-            if var_type.is_pointer_type() {
-                // 尝试从指针获取结构体类型
-                // 由于指针可能被转换为 i8*，我们需要另一种方法
-            }
-            if var_type.is_struct_type() {
-                let struct_type = var_type.into_struct_type();
-                // 尝试在 struct_types 中查找匹配的类型
-                for st in self.struct_types.values() {
-                    if *st == struct_type {
-                        return Some(*st);
+    /// 生成数值的算术、位运算、移位和大小比较
+    ///
+    /// 除法、取余、大小比较和 `>>` 按 `signed` 选择有符号或无符号的指令，`>>>` 总是逻辑右移。
+    fn build_numeric_binary(
+        &mut self,
+        op: &BinaryOp,
+        lhs: BasicValueEnum<'ctx>,
+        rhs: BasicValueEnum<'ctx>,
+        signed: bool,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        use inkwell::{FloatPredicate, IntPredicate};
+        let unsupported =
+            || CodegenError { message: format!("Unsupported binary operator: {:?}", op) };
+        let builder = self.builder();
+        let result: BasicValueEnum<'ctx> = match (lhs, rhs) {
+            (BasicValueEnum::IntValue(a), BasicValueEnum::IntValue(b)) => {
+                let predicate = |signed_predicate, unsigned_predicate| {
+                    if signed {
+                        signed_predicate
+                    } else {
+                        unsigned_predicate
                     }
+                };
+                match op {
+                    BinaryOp::Add => builder.build_int_add(a, b, "add")?.into(),
+                    BinaryOp::Subtract => builder.build_int_sub(a, b, "sub")?.into(),
+                    BinaryOp::Multiply => builder.build_int_mul(a, b, "mul")?.into(),
+                    BinaryOp::Divide if signed => builder.build_int_signed_div(a, b, "div")?.into(),
+                    BinaryOp::Divide => builder.build_int_unsigned_div(a, b, "div")?.into(),
+                    BinaryOp::Modulo if signed => builder.build_int_signed_rem(a, b, "rem")?.into(),
+                    BinaryOp::Modulo => builder.build_int_unsigned_rem(a, b, "rem")?.into(),
+                    BinaryOp::BitAnd => builder.build_and(a, b, "and")?.into(),
+                    BinaryOp::BitOr => builder.build_or(a, b, "or")?.into(),
+                    BinaryOp::BitXor => builder.build_xor(a, b, "xor")?.into(),
+                    BinaryOp::LeftShift => builder.build_left_shift(a, b, "shl")?.into(),
+                    BinaryOp::RightShift => builder.build_right_shift(a, b, signed, "shr")?.into(),
+                    BinaryOp::UnsignedRightShift => {
+                        builder.build_right_shift(a, b, false, "lshr")?.into()
+                    },
+                    BinaryOp::LessThan => builder
+                        .build_int_compare(
+                            predicate(IntPredicate::SLT, IntPredicate::ULT),
+                            a,
+                            b,
+                            "lt",
+                        )?
+                        .into(),
+                    BinaryOp::LessThanOrEqual => builder
+                        .build_int_compare(
+                            predicate(IntPredicate::SLE, IntPredicate::ULE),
+                            a,
+                            b,
+                            "le",
+                        )?
+                        .into(),
+                    BinaryOp::GreaterThan => builder
+                        .build_int_compare(
+                            predicate(IntPredicate::SGT, IntPredicate::UGT),
+                            a,
+                            b,
+                            "gt",
+                        )?
+                        .into(),
+                    BinaryOp::GreaterThanOrEqual => builder
+                        .build_int_compare(
+                            predicate(IntPredicate::SGE, IntPredicate::UGE),
+                            a,
+                            b,
+                            "ge",
+                        )?
+                        .into(),
+                    _ => return Err(unsupported()),
                 }
-            }
-        }
+            },
+            (BasicValueEnum::FloatValue(a), BasicValueEnum::FloatValue(b)) => match op {
+                BinaryOp::Add => builder.build_float_add(a, b, "fadd")?.into(),
+                BinaryOp::Subtract => builder.build_float_sub(a, b, "fsub")?.into(),
+                BinaryOp::Multiply => builder.build_float_mul(a, b, "fmul")?.into(),
+                BinaryOp::Divide => builder.build_float_div(a, b, "fdiv")?.into(),
+                BinaryOp::Modulo => builder.build_float_rem(a, b, "frem")?.into(),
+                BinaryOp::LessThan => {
+                    builder.build_float_compare(FloatPredicate::OLT, a, b, "lt")?.into()
+                },
+                BinaryOp::LessThanOrEqual => {
+                    builder.build_float_compare(FloatPredicate::OLE, a, b, "le")?.into()
+                },
+                BinaryOp::GreaterThan => {
+                    builder.build_float_compare(FloatPredicate::OGT, a, b, "gt")?.into()
+                },
+                BinaryOp::GreaterThanOrEqual => {
+                    builder.build_float_compare(FloatPredicate::OGE, a, b, "ge")?.into()
+                },
+                _ => return Err(unsupported()),
+            },
+            _ => return Err(unsupported()),
+        };
+        Ok(result)
+    }
 
-        // 尝试从 struct_fields 推断类型名
-        if let Some(_fields) = self.struct_field_lists.get(var_name) {
-            // 使用变量名作为类型名的基础（首字母大写）
-            let type_name: String =
-                var_name.chars().next().map(|c| c.to_uppercase().collect()).unwrap_or_default();
-            if let Some(struct_type) = self.struct_types.get(&type_name) {
-                return Some(*struct_type);
-            }
+    /// 生成 ==、!=、===、!== 比较
+    ///
+    /// 与 null / undefined 比较时检查联合的标签或空指针，`==` 同时匹配 null 和 undefined。
+    fn generate_equality(
+        &mut self,
+        op: &BinaryOp,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let loose = matches!(op, BinaryOp::Equals | BinaryOp::NotEquals);
+        let equal = match (left, right) {
+            (value, nullish @ (Expression::Null(_) | Expression::Undefined(_)))
+            | (nullish @ (Expression::Null(_) | Expression::Undefined(_)), value) => {
+                self.build_is_nullish(value, nullish, loose)?
+            },
+            _ => {
+                let lhs = self.generate_expression(left)?;
+                let rhs = self.generate_expression(right)?;
+                let lhs_type = self.expression_type(left)?.unwrap_or(Type::Any);
+                let rhs_type = self.expression_type(right)?.unwrap_or(Type::Any);
+                self.build_values_equal(lhs, &lhs_type, rhs, &rhs_type)?
+            },
+        };
+        if matches!(op, BinaryOp::NotEquals | BinaryOp::StrictNotEquals) {
+            Ok(self.builder().build_not(equal, "ne")?.into())
+        } else {
+            Ok(equal.into())
         }
+    }
 
-        None
-    }
-
-    /// 根据变量名和字段名获取字段索引
-    fn get_field_index(&self, var_name: &str, field_name: &str) -> Option<u32> {
-        // 首先根据变量类型查找 struct 名称
-        if let Some(var_type) = self.variable_types.get(var_name) {
-            if var_type.is_struct_type() {
-                let struct_type = var_type.into_struct_type();
-                // 在 struct_types 中查找匹配的 struct
-                for (name, st) in &self.struct_types {
-                    if *st == struct_type {
-                        // 从字段列表中查找字段索引
-                        if let Some(fields) = self.struct_field_lists.get(name) {
-                            for (i, fname) in fields.iter().enumerate() {
-                                if fname == field_name {
-                                    return Some(i as u32);
-                                }
-                            }
-                        }
-                    }
+    /// 值是否为 null / undefined
+    fn build_is_nullish(
+        &mut self,
+        expr: &Expression,
+        nullish: &Expression,
+        loose: bool,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let value = self.generate_expression(expr)?;
+        let ty = self.expression_type(expr)?.unwrap_or(Type::Any);
+        let matches = |t: &Type| match (t, nullish) {
+            (Type::Null, Expression::Null(_)) | (Type::Undefined, Expression::Undefined(_)) => true,
+            (Type::Null | Type::Undefined, _) => loose,
+            _ => false,
+        };
+        let bool_type = self.context.bool_type();
+        if let Some(members) = self.tagged_members(&ty) {
+            let tag = self.union_tag_value(value)?;
+            let mut result = bool_type.const_zero();
+            for (i, member) in members.iter().enumerate() {
+                if !matches(member) {
+                    continue;
                 }
+                let is_member = self.builder().build_int_compare(
+                    inkwell::IntPredicate::EQ,
+                    tag,
+                    tag.get_type().const_int(i as u64, false),
+                    "is_nullish",
+                )?;
+                result = self.builder().build_or(result, is_member, "nullish")?;
             }
+            return Ok(result);
+        }
+        match value {
+            // 可空的指针以空指针表示 null / undefined
+            BasicValueEnum::PointerValue(ptr) => {
+                Ok(self.builder().build_is_null(ptr, "is_null")?)
+            },
+            _ => Ok(bool_type.const_int(matches(&self.resolve_type(&ty)) as u64, false)),
         }
-        None
     }
 
-    /// 将 Nexa 类型映射到 LLVM 类型 (TypeScript 风格)
-    fn map_type(&self, ty: &Type) -> BasicTypeEnum<'ctx> {
-        match ty {
-            Type::Number => self.context.i32_type().into(),
-            Type::Boolean => self.context.i32_type().into(),
-            Type::String => self.context.ptr_type(AddressSpace::default()).into(),
-            Type::Void => {
-                // Void 类型作为函数返回类型时不映射到 BasicTypeEnum
-                // 这里返回 i32 作为占位符
-                self.context.i32_type().into()
+    /// 按类型比较两个值是否相等：字符串比较内容，联合类型先比较标签再比较对应成员
+    fn build_values_equal(
+        &mut self,
+        lhs: BasicValueEnum<'ctx>,
+        lhs_type: &Type,
+        rhs: BasicValueEnum<'ctx>,
+        rhs_type: &Type,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let bool_type = self.context.bool_type();
+        let lhs_type = self.resolve_type(lhs_type);
+        let rhs_type = self.resolve_type(rhs_type);
+        if self.tagged_members(&lhs_type).is_none() && self.tagged_members(&rhs_type).is_some() {
+            return self.build_values_equal(rhs, &rhs_type, lhs, &lhs_type);
+        }
+        if let Some(members) = self.tagged_members(&lhs_type) {
+            // 另一侧先放入同一联合类型，无法放入时两者不可能相等
+            let Ok(rhs) = self.coerce_value(rhs, Some(&rhs_type), Some(&lhs_type)) else {
+                return Ok(bool_type.const_zero());
+            };
+            return self.build_union_equal(lhs, rhs, &members);
+        }
+
+        let i64_type = self.context.i64_type();
+        let builder = &self.builder;
+        let equal = match (lhs, rhs) {
+            (BasicValueEnum::PointerValue(a), BasicValueEnum::PointerValue(b))
+                if self.is_string_type(&lhs_type) || self.is_string_type(&rhs_type) =>
+            {
+                return self.build_string_equals(a, b);
             },
-            Type::Undefined => self.context.i32_type().into(),
-            Type::Null => self.context.ptr_type(AddressSpace::default()).into(),
-            Type::Any => self.context.ptr_type(AddressSpace::default()).into(),
-            Type::Never => self.context.i32_type().into(),
-            Type::Array(_) => self.context.ptr_type(AddressSpace::default()).into(),
-            Type::Pointer(_) => self.context.ptr_type(AddressSpace::default()).into(),
-            Type::Function(_, return_type) => self.map_type(return_type),
-            Type::Struct(name) => {
-                // 尝试查找已注册的 struct 类型
-                if let Some(struct_type) = self.struct_types.get(name) {
-                    (*struct_type).into()
-                } else {
-                    // 如果未找到，返回指针类型作为后备
-                    self.context.ptr_type(AddressSpace::default()).into()
-                }
+            (BasicValueEnum::PointerValue(a), BasicValueEnum::PointerValue(b)) => {
+                let a = builder.build_ptr_to_int(a, i64_type, "lhs_addr")?;
+                let b = builder.build_ptr_to_int(b, i64_type, "rhs_addr")?;
+                builder.build_int_compare(inkwell::IntPredicate::EQ, a, b, "eq")?
             },
-            Type::Object(_) => self.context.ptr_type(AddressSpace::default()).into(),
+            (BasicValueEnum::IntValue(a), BasicValueEnum::IntValue(b)) => {
+                // 布尔比较结果 (i1) 与 i32 比较时按无符号扩展
+                let width = a.get_type().get_bit_width().max(b.get_type().get_bit_width());
+                let int_type = self.context.custom_width_int_type(width);
+                let a = builder.build_int_z_extend_or_bit_cast(a, int_type, "lhs")?;
+                let b = builder.build_int_z_extend_or_bit_cast(b, int_type, "rhs")?;
+                builder.build_int_compare(inkwell::IntPredicate::EQ, a, b, "eq")?
+            },
+            (BasicValueEnum::FloatValue(a), BasicValueEnum::FloatValue(b)) => {
+                builder.build_float_compare(inkwell::FloatPredicate::OEQ, a, b, "eq")?
+            },
+            (BasicValueEnum::IntValue(a), BasicValueEnum::FloatValue(b)) => {
+                let a = builder.build_signed_int_to_float(a, b.get_type(), "lhs")?;
+                builder.build_float_compare(inkwell::FloatPredicate::OEQ, a, b, "eq")?
+            },
+            (BasicValueEnum::FloatValue(a), BasicValueEnum::IntValue(b)) => {
+                let b = builder.build_signed_int_to_float(b, a.get_type(), "rhs")?;
+                builder.build_float_compare(inkwell::FloatPredicate::OEQ, a, b, "eq")?
+            },
+            // 接口值比较其中的对象指针
+            (BasicValueEnum::StructValue(a), BasicValueEnum::StructValue(b))
+                if self.interface_type() == a.get_type() && a.get_type() == b.get_type() =>
+            {
+                let a = builder.build_extract_value(a, 0, "lhs_data")?.into_pointer_value();
+                let b = builder.build_extract_value(b, 0, "rhs_data")?.into_pointer_value();
+                let a = builder.build_ptr_to_int(a, i64_type, "lhs_addr")?;
+                let b = builder.build_ptr_to_int(b, i64_type, "rhs_addr")?;
+                builder.build_int_compare(inkwell::IntPredicate::EQ, a, b, "eq")?
+            },
+            _ => bool_type.const_zero(),
+        };
+        Ok(equal)
+    }
+
+    /// 比较同一联合类型的两个值：标签相同时按该成员的类型比较负载
+    fn build_union_equal(
+        &mut self,
+        lhs: BasicValueEnum<'ctx>,
+        rhs: BasicValueEnum<'ctx>,
+        members: &[Type],
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let function = self.current_function.ok_or_else(|| CodegenError {
+            message: "Comparison outside of a function".to_string(),
+        })?;
+        let bool_type = self.context.bool_type();
+        let lhs_tag = self.union_tag_value(lhs)?;
+        let rhs_tag = self.union_tag_value(rhs)?;
+        let same_tag = self.builder().build_int_compare(
+            inkwell::IntPredicate::EQ,
+            lhs_tag,
+            rhs_tag,
+            "same",
+        )?;
+        let entry = self.builder().get_insert_block().unwrap();
+        let dispatch_block = self.context.append_basic_block(function, "eq_dispatch");
+        let merge_block = self.context.append_basic_block(function, "eq_merge");
+        self.builder().build_conditional_branch(same_tag, dispatch_block, merge_block)?;
+
+        let false_value = bool_type.const_zero();
+        let mut incoming = vec![(false_value, entry), (false_value, dispatch_block)];
+        let mut cases = Vec::new();
+        for (i, member) in members.iter().enumerate() {
+            let block = self.context.append_basic_block(function, "eq_member");
+            cases.push((lhs_tag.get_type().const_int(i as u64, false), block));
+            self.builder().position_at_end(block);
+            // null / undefined 成员没有负载，标签相同即相等
+            let equal = if matches!(member, Type::Null | Type::Undefined) {
+                bool_type.const_all_ones()
+            } else {
+                let a = self.union_payload(lhs, member)?;
+                let b = self.union_payload(rhs, member)?;
+                self.build_values_equal(a, member, b, member)?
+            };
+            incoming.push((equal, self.builder().get_insert_block().unwrap()));
+            self.builder().build_unconditional_branch(merge_block)?;
+        }
+        self.builder().position_at_end(dispatch_block);
+        self.builder().build_switch(lhs_tag, merge_block, &cases)?;
+
+        self.builder().position_at_end(merge_block);
+        let phi = self.builder().build_phi(bool_type, "eq")?;
+        for (value, block) in &incoming {
+            phi.add_incoming(&[(value, *block)]);
+        }
+        Ok(phi.as_basic_value().into_int_value())
+    }
+
+    /// 类型在运行时的 typeof 结果
+    fn typeof_name(&self, ty: &Type) -> &'static str {
+        match self.resolve_type(ty) {
+            Type::Literal(LiteralType::Number(_)) => "number",
+            ty if ty.is_numeric() => "number",
+            Type::Boolean | Type::Literal(LiteralType::Boolean(_)) => "boolean",
+            Type::String | Type::Literal(LiteralType::String(_)) => "string",
+            Type::Undefined | Type::Void | Type::Never => "undefined",
+            Type::Function(..) => "function",
+            Type::Struct(name) => match self.enum_defs.get(&name) {
+                Some(def) if def.is_string() => "string",
+                Some(_) => "number",
+                None => "object",
+            },
+            _ => "object",
         }
     }
 
-    /// 声明导入的函数
-    fn declare_imported_function(
+    /// 运行时 typeof：联合类型按标签选择成员的类型名，可空指针为空时是 null 或 undefined
+    fn build_typeof(
         &mut self,
-        func: &nexa_parser::ast::Function,
-    ) -> Result<(), CodegenError> {
-        // 检查函数是否已存在
-        if self.module.get_function(&func.name).is_some() {
-            return Ok(());
+        value: BasicValueEnum<'ctx>,
+        operand: &Expression,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let ty = self.expression_type(operand)?.unwrap_or(Type::Any);
+        if let Some(members) = self.tagged_members(&ty) {
+            let tag = self.union_tag_value(value)?;
+            let mut result = self.string_constant(self.typeof_name(&members[0]));
+            for (i, member) in members.iter().enumerate().skip(1) {
+                let name = self.string_constant(self.typeof_name(member));
+                let is_member = self.builder().build_int_compare(
+                    inkwell::IntPredicate::EQ,
+                    tag,
+                    tag.get_type().const_int(i as u64, false),
+                    "is_member",
+                )?;
+                result = self
+                    .builder()
+                    .build_select(is_member, name, result, "typeof")?
+                    .into_pointer_value();
+            }
+            return Ok(result.into());
+        }
+        if let (Some(member), Some(members)) =
+            (self.plain_union_member(&ty), self.union_members(&ty))
+        {
+            let name = self.string_constant(self.typeof_name(&member));
+            let null_type =
+                if members.contains(&Type::Null) { Type::Null } else { Type::Undefined };
+            let null_name = self.string_constant(self.typeof_name(&null_type));
+            let is_null = self.builder().build_is_null(value.into_pointer_value(), "is_null")?;
+            return Ok(self.builder().build_select(is_null, null_name, name, "typeof")?);
         }
+        Ok(self.string_constant(self.typeof_name(&ty)).into())
+    }
 
-        // 获取参数类型
-        let param_types: Vec<inkwell::types::BasicMetadataTypeEnum> =
-            func.parameters.iter().map(|p| self.map_type(&p.type_annotation).into()).collect();
+    /// 运行时 instanceof：比较对象的虚表与该 class 及其所有子类的虚表
+    fn build_instanceof(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        ty: &Type,
+        class: &str,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let bool_type = self.context.bool_type();
+        if let Some(members) = self.tagged_members(ty) {
+            return self.build_union_instanceof(value, &members, class);
+        }
+        let ty = self.plain_union_member(ty).unwrap_or_else(|| self.resolve_type(ty));
+        let object = match (&ty, value) {
+            (Type::Struct(name), BasicValueEnum::StructValue(value))
+                if self.interface_defs.contains_key(name) =>
+            {
+                self.builder().build_extract_value(value, 0, "iface_data")?.into_pointer_value()
+            },
+            (Type::Struct(name), BasicValueEnum::PointerValue(ptr)) if self.is_class(name) => ptr,
+            _ => return Ok(bool_type.const_zero()),
+        };
+        let Some((struct_type, index)) = self.struct_field(class, VTABLE_FIELD) else {
+            return Ok(bool_type.const_zero());
+        };
 
-        // 获取返回类型
-        let return_type = self.map_type(&func.return_type);
-        let fn_type = return_type.fn_type(&param_types, false);
+        // 空对象不是任何 class 的实例
+        let function = self.current_function.ok_or_else(|| CodegenError {
+            message: "instanceof outside of a function".to_string(),
+        })?;
+        let entry = self.builder().get_insert_block().unwrap();
+        let check_block = self.context.append_basic_block(function, "instanceof_check");
+        let merge_block = self.context.append_basic_block(function, "instanceof_merge");
+        let is_null = self.builder().build_is_null(object, "is_null")?;
+        self.builder().build_conditional_branch(is_null, merge_block, check_block)?;
+
+        self.builder().position_at_end(check_block);
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+        let vtable_field =
+            self.builder().build_struct_gep(struct_type, object, index, "vtable_ptr")?;
+        let vtable = self.builder().build_load(ptr_type, vtable_field, "vtable")?;
+        let i64_type = self.context.i64_type();
+        let vtable =
+            self.builder().build_ptr_to_int(vtable.into_pointer_value(), i64_type, "vt")?;
+        let mut subclasses: Vec<String> = self
+            .class_defs
+            .keys()
+            .filter(|name| self.class_chain(name).iter().any(|def| def.name == class))
+            .cloned()
+            .collect();
+        subclasses.sort();
+        let mut result = bool_type.const_zero();
+        for subclass in subclasses {
+            let Some(global) = self.lookup_global(&format!("__vtable_{}", subclass)) else {
+                continue;
+            };
+            let expected = global.as_pointer_value().const_to_int(i64_type);
+            let is_class = self.builder().build_int_compare(
+                inkwell::IntPredicate::EQ,
+                vtable,
+                expected,
+                "is",
+            )?;
+            result = self.builder().build_or(result, is_class, "instanceof")?;
+        }
+        let check_end = self.builder().get_insert_block().unwrap();
+        self.builder().build_unconditional_branch(merge_block)?;
 
-        // 添加函数声明
-        self.module.add_function(&func.name, fn_type, None);
+        self.builder().position_at_end(merge_block);
+        let phi = self.builder().build_phi(bool_type, "instanceof")?;
+        phi.add_incoming(&[(&bool_type.const_zero(), entry), (&result, check_end)]);
+        Ok(phi.as_basic_value().into_int_value())
+    }
 
-        Ok(())
+    /// 联合类型值的 instanceof：按标签分派到各成员
+    fn build_union_instanceof(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        members: &[Type],
+        class: &str,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let function = self.current_function.ok_or_else(|| CodegenError {
+            message: "instanceof outside of a function".to_string(),
+        })?;
+        let bool_type = self.context.bool_type();
+        let tag = self.union_tag_value(value)?;
+        let entry = self.builder().get_insert_block().unwrap();
+        let merge_block = self.context.append_basic_block(function, "instanceof_merge");
+        let mut incoming = vec![(bool_type.const_zero(), entry)];
+        let mut cases = Vec::new();
+        for (i, member) in members.iter().enumerate() {
+            let block = self.context.append_basic_block(function, "instanceof_member");
+            cases.push((tag.get_type().const_int(i as u64, false), block));
+            self.builder().position_at_end(block);
+            let result = if matches!(member, Type::Null | Type::Undefined) {
+                bool_type.const_zero()
+            } else {
+                let payload = self.union_payload(value, member)?;
+                self.build_instanceof(payload, member, class)?
+            };
+            incoming.push((result, self.builder().get_insert_block().unwrap()));
+            self.builder().build_unconditional_branch(merge_block)?;
+        }
+        self.builder().position_at_end(entry);
+        self.builder().build_switch(tag, merge_block, &cases)?;
+
+        self.builder().position_at_end(merge_block);
+        let phi = self.builder().build_phi(bool_type, "instanceof")?;
+        for (value, block) in &incoming {
+            phi.add_incoming(&[(value, *block)]);
+        }
+        Ok(phi.as_basic_value().into_int_value())
+    }
+
+    /// 类型是否具有该属性 (字段或方法)
+    fn has_property(&self, ty: &Type, property: &str) -> bool {
+        let Type::Struct(name) = self.resolve_type(ty) else { return false };
+        if let Some(iface) = self.interface_defs.get(&name) {
+            return iface.fields.iter().any(|f| f.name == property)
+                || iface.methods.iter().any(|m| m.name == property);
+        }
+        self.struct_field(&name, property).is_some()
+            || self.vtable_slots(&name).iter().any(|(member, _)| member == property)
+    }
+
+    /// `"prop" in obj`：联合类型按标签判断所属成员是否具有该属性
+    fn generate_in(
+        &mut self,
+        property: &Expression,
+        object: &Expression,
+    ) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+        let Expression::String(property, _) = property else {
+            return Err(CodegenError {
+                message: "Left-hand side of 'in' must be a string literal".to_string(),
+            });
+        };
+        let ty = self.expression_type(object)?.unwrap_or(Type::Any);
+        let value = self.generate_expression(object)?;
+        let bool_type = self.context.bool_type();
+        let Some(members) = self.tagged_members(&ty) else {
+            let ty = self.plain_union_member(&ty).unwrap_or(ty);
+            return Ok(bool_type.const_int(self.has_property(&ty, property) as u64, false).into());
+        };
+        let tag = self.union_tag_value(value)?;
+        let mut result = bool_type.const_zero();
+        for (i, member) in members.iter().enumerate() {
+            if !self.has_property(member, property) {
+                continue;
+            }
+            let is_member = self.builder().build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag,
+                tag.get_type().const_int(i as u64, false),
+                "is_member",
+            )?;
+            result = self.builder().build_or(result, is_member, "in")?;
+        }
+        Ok(result.into())
     }
 
     /// 生成 switch 表达式
@@ -2340,22 +4599,30 @@ impl<'ctx> CodeGenerator<'ctx> {
 
         // 生成要匹配的值
         let switch_value = self.generate_expression(value)?;
-
-        // 获取匹配的值并统一转换为 i64 进行比较
-        let switch_int = if switch_value.is_int_value() {
-            // i32 扩展为 i64
-            self.builder().build_int_s_extend(
-                switch_value.into_int_value(),
-                i64_type,
-                "switch_i64",
-            )?
+        let value_type = self.expression_type(value)?.unwrap_or(Type::Number);
+
+        // 联合类型的值保留标签和负载，其余统一转换为 i64 进行比较
+        let subject = if self.tagged_members(&value_type).is_some() {
+            SwitchSubject::Union(switch_value, value_type)
+        } else if switch_value.is_int_value() {
+            // 整数按有无符号扩展为 i64
+            let unsigned = self
+                .numeric_type(&value_type)
+                .and_then(|ty| ty.int_width())
+                .is_some_and(|(_, signed)| !signed);
+            let int_value = switch_value.into_int_value();
+            SwitchSubject::Int(if unsigned {
+                self.builder().build_int_z_extend_or_bit_cast(int_value, i64_type, "switch_i64")?
+            } else {
+                self.builder().build_int_s_extend_or_bit_cast(int_value, i64_type, "switch_i64")?
+            })
         } else if switch_value.is_pointer_value() {
             // 指针转换为 i64
-            self.builder().build_ptr_to_int(
+            SwitchSubject::Int(self.builder().build_ptr_to_int(
                 switch_value.into_pointer_value(),
                 i64_type,
                 "ptr_to_int",
-            )?
+            )?)
         } else {
             return Err(CodegenError {
                 message: "Switch value must be integer or pointer type".to_string(),
@@ -2366,7 +4633,7 @@ impl<'ctx> CodeGenerator<'ctx> {
         let merge_block = self.context.append_basic_block(function, "switch_merge");
 
         // 递归生成 if-else 链
-        self.generate_switch_arms(0, arms, switch_int, i64_type, merge_block)?;
+        self.generate_switch_arms(0, arms, &subject, merge_block)?;
 
         // 设置合并块位置
         self.builder().position_at_end(merge_block);
@@ -2374,13 +4641,35 @@ impl<'ctx> CodeGenerator<'ctx> {
         Ok(())
     }
 
+    /// 按内容比较两个字符串是否相等
+    fn build_string_equals(
+        &mut self,
+        left: PointerValue<'ctx>,
+        right: PointerValue<'ctx>,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let compare = self.module.get_function("std_string_compare").ok_or_else(|| {
+            CodegenError { message: "std_string_compare function not found".to_string() }
+        })?;
+        let result = self
+            .builder()
+            .build_call(compare, &[left.into(), right.into()], "str_cmp")?
+            .try_as_basic_value()
+            .unwrap_basic()
+            .into_int_value();
+        Ok(self.builder().build_int_compare(
+            inkwell::IntPredicate::EQ,
+            result,
+            result.get_type().const_zero(),
+            "str_eq",
+        )?)
+    }
+
     /// 递归生成 switch 分支的 if-else 链
     fn generate_switch_arms(
         &mut self,
         idx: usize,
         arms: &[SwitchArm],
-        switch_int: inkwell::values::IntValue<'ctx>,
-        i64_type: inkwell::types::IntType<'ctx>,
+        subject: &SwitchSubject<'ctx>,
         merge_block: inkwell::basic_block::BasicBlock<'ctx>,
     ) -> Result<(), CodegenError> {
         if idx >= arms.len() {
@@ -2405,7 +4694,40 @@ impl<'ctx> CodeGenerator<'ctx> {
         };
 
         // 生成条件比较
-        let cond_i1: inkwell::values::IntValue<'ctx> = match &arm.pattern {
+        let cond_i1 = match subject {
+            SwitchSubject::Int(switch_int) => self.build_switch_condition(arm, *switch_int)?,
+            SwitchSubject::Union(value, ty) => {
+                self.build_union_switch_condition(arm, *value, ty)?
+            },
+        };
+
+        // 生成条件分支 (cond_i1 已经是 i1 类型)
+        self.builder().build_conditional_branch(cond_i1, then_block, else_block)?;
+
+        // 在 then 块中生成代码
+        self.builder().position_at_end(then_block);
+        self.generate_statement(&arm.body)?;
+        if self.builder().get_insert_block().unwrap().get_terminator().is_none() {
+            self.builder().build_unconditional_branch(merge_block)?;
+        }
+
+        // 继续处理 else 分支
+        if idx + 1 < arms.len() {
+            self.builder().position_at_end(else_block);
+            self.generate_switch_arms(idx + 1, arms, subject, merge_block)?;
+        }
+
+        Ok(())
+    }
+
+    /// 整数或指针 switch 分支的条件
+    fn build_switch_condition(
+        &mut self,
+        arm: &SwitchArm,
+        switch_int: inkwell::values::IntValue<'ctx>,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let i64_type = self.context.i64_type();
+        let cond_i1 = match &arm.pattern {
             SwitchPattern::Number(n) => {
                 let n_int = i64_type.const_int(*n as u64, false);
                 self.builder().build_int_compare(
@@ -2443,6 +4765,40 @@ impl<'ctx> CodeGenerator<'ctx> {
                     i64_type.const_int(0, false)
                 }
             },
+            SwitchPattern::EnumMember(enum_name, member) => {
+                let value = self
+                    .enum_defs
+                    .get(enum_name)
+                    .and_then(|def| def.member(member))
+                    .map(|m| m.value.clone())
+                    .ok_or_else(|| CodegenError {
+                        message: format!("Enum member {}.{} not found", enum_name, member),
+                    })?;
+                match value {
+                    EnumValue::Number(n) => self.builder().build_int_compare(
+                        inkwell::IntPredicate::EQ,
+                        switch_int,
+                        i64_type.const_int(n as u64, true),
+                        "switch_cond",
+                    )?,
+                    EnumValue::String(_) => {
+                        // 字符串枚举按内容比较
+                        let pattern = self.generate_enum_value(&value, &arm.span)?;
+                        let ptr_type = self.context.ptr_type(AddressSpace::default());
+                        let switch_ptr =
+                            self.builder().build_int_to_ptr(switch_int, ptr_type, "switch_ptr")?;
+                        self.build_string_equals(switch_ptr, pattern.into_pointer_value())?
+                    },
+                }
+            },
+            SwitchPattern::String(value) => {
+                let pattern =
+                    self.generate_expression(&Expression::String(value.clone(), arm.span.clone()))?;
+                let ptr_type = self.context.ptr_type(AddressSpace::default());
+                let switch_ptr =
+                    self.builder().build_int_to_ptr(switch_int, ptr_type, "switch_ptr")?;
+                self.build_string_equals(switch_ptr, pattern.into_pointer_value())?
+            },
             SwitchPattern::Wildcard | SwitchPattern::Default => {
                 // 通配符/default：条件始终为 true
                 // 创建全 1 的 i64 值然后转换为 i1
@@ -2456,23 +4812,158 @@ impl<'ctx> CodeGenerator<'ctx> {
                 )?
             },
         };
+        Ok(cond_i1)
+    }
 
-        // 生成条件分支 (cond_i1 已经是 i1 类型)
-        self.builder().build_conditional_branch(cond_i1, then_block, else_block)?;
+    /// 联合类型 switch 分支的条件：模式的值放入同一联合类型后先比较标签，标签相同再比较负载
+    fn build_union_switch_condition(
+        &mut self,
+        arm: &SwitchArm,
+        value: BasicValueEnum<'ctx>,
+        value_type: &Type,
+    ) -> Result<inkwell::values::IntValue<'ctx>, CodegenError> {
+        let (pattern, pattern_type) = match &arm.pattern {
+            SwitchPattern::Number(n) => (
+                self.context.i32_type().const_int(*n as u64, true).into(),
+                Type::Literal(LiteralType::Number(*n)),
+            ),
+            SwitchPattern::String(s) => (
+                self.generate_expression(&Expression::String(s.clone(), arm.span.clone()))?,
+                Type::String,
+            ),
+            SwitchPattern::EnumMember(enum_name, member) => {
+                let value = self
+                    .enum_defs
+                    .get(enum_name)
+                    .and_then(|def| def.member(member))
+                    .map(|m| m.value.clone())
+                    .ok_or_else(|| CodegenError {
+                        message: format!("Enum member {}.{} not found", enum_name, member),
+                    })?;
+                (self.generate_enum_value(&value, &arm.span)?, Type::Struct(enum_name.clone()))
+            },
+            SwitchPattern::Identifier(name) => {
+                // 类型检查以分支的 Span 记录了变量的类型
+                let pattern = Expression::Identifier(name.clone(), arm.span.clone());
+                let ty = self.expression_type(&pattern)?.unwrap_or(Type::Number);
+                (self.generate_expression(&pattern)?, ty)
+            },
+            SwitchPattern::Wildcard | SwitchPattern::Default => {
+                return Ok(self.context.bool_type().const_all_ones());
+            },
+        };
+        self.build_values_equal(value, value_type, pattern, &pattern_type)
+    }
+}
 
-        // 在 then 块中生成代码
-        self.builder().position_at_end(then_block);
-        self.generate_statement(&arm.body)?;
-        if self.builder().get_insert_block().unwrap().get_terminator().is_none() {
-            self.builder().build_unconditional_branch(merge_block)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inkwell::values::{AnyValue, InstructionOpcode};
+    use nexa_parser::Parser;
+    use nexa_typecheck::TypeChecker;
+
+    /// 解析、类型检查并生成模块，给出模块路径时按非入口模块生成
+    fn generate<'ctx>(
+        context: &'ctx Context,
+        source: &str,
+        module_path: Option<&str>,
+    ) -> Module<'ctx> {
+        let program = Parser::new(source).parse_program().expect("parse failed");
+        let type_info = TypeChecker::new().check_program(&program).expect("type check failed");
+        let mut codegen = CodeGenerator::new(context, module_path.unwrap_or("test"));
+        if let Some(path) = module_path {
+            codegen.set_module_path(path.to_string());
         }
+        codegen.set_type_info(type_info);
+        codegen.declare_builtin_functions();
+        codegen.generate_program(&program).expect("codegen failed");
+        codegen.into_module()
+    }
 
-        // 继续处理 else 分支
-        if idx + 1 < arms.len() {
-            self.builder().position_at_end(else_block);
-            self.generate_switch_arms(idx + 1, arms, switch_int, i64_type, merge_block)?;
-        }
+    #[test]
+    fn test_constant_globals() {
+        let context = Context::create();
+        let module = generate(
+            &context,
+            "const VERSION: string = \"1.0.0\";
+            let count: number = 3;
+            function main(): void { count = count + 1; println(VERSION); }",
+            None,
+        );
+        let version = module.get_global("VERSION").expect("VERSION not declared");
+        assert!(version.is_constant());
+        assert!(version.get_initializer().is_some());
+        let count = module.get_global("count").expect("count not declared");
+        assert!(!count.is_constant());
+        assert_eq!(count.get_initializer().unwrap().print_to_string().to_string(), "i32 3");
+        // 全部是常量初值时不需要模块初始化函数
+        assert!(module.get_function(MODULE_INIT).is_none());
+    }
 
-        Ok(())
+    #[test]
+    fn test_global_initialized_in_module_init() {
+        let context = Context::create();
+        let module = generate(
+            &context,
+            "function answer(): number { return 42; }
+            let value: number = answer();
+            function main(): void { println(value); }",
+            None,
+        );
+        let value = module.get_global("value").expect("value not declared");
+        assert_eq!(value.get_initializer().unwrap().print_to_string().to_string(), "i32 0");
+        let init = module.get_function(MODULE_INIT).expect("module init not generated");
+        let body = init.print_to_string().to_string();
+        assert!(body.contains("call i32 @answer()"), "{}", body);
+        assert!(body.contains("store i32 %"), "{}", body);
+    }
+
+    #[test]
+    fn test_top_level_statements_run_before_main() {
+        let context = Context::create();
+        let module = generate(
+            &context,
+            "println(1);
+            function main(): void { println(2); }",
+            None,
+        );
+        let init = module.get_function(MODULE_INIT).expect("module init not generated");
+        assert_eq!(init.get_linkage(), Linkage::Internal);
+        assert!(init.print_to_string().to_string().contains("call"));
+        let main = module.get_function("main").expect("main not generated");
+        let first = main.get_first_basic_block().and_then(|b| b.get_first_instruction()).unwrap();
+        assert_eq!(first.get_opcode(), InstructionOpcode::Call);
+        assert!(first.print_to_string().to_string().contains(MODULE_INIT));
+    }
+
+    #[test]
+    fn test_private_functions_are_internal() {
+        let context = Context::create();
+        let first = generate(
+            &context,
+            "function helper(): number { return 1; }
+            export function first(): number { return helper(); }",
+            Some("a"),
+        );
+        let second = generate(
+            &context,
+            "function helper(): number { return 2; }
+            export function second(): number { return helper(); }",
+            Some("b"),
+        );
+        let helper = first.get_function("helper").expect("helper not generated");
+        assert_eq!(helper.get_linkage(), Linkage::Internal);
+        let exported = first.get_function(&mangle_symbol("a", "first")).expect("first not mangled");
+        assert_eq!(exported.get_linkage(), Linkage::External);
+
+        // 两个模块中同名的私有函数链接时互不冲突
+        first.link_in_module(second).expect("link failed");
+        assert!(first.get_function(&mangle_symbol("b", "second")).is_some());
+        let helpers = first
+            .get_functions()
+            .filter(|f| f.get_name().to_string_lossy().starts_with("helper"))
+            .count();
+        assert_eq!(helpers, 2);
     }
 }
@@ -2,23 +2,57 @@
 //!
 //! Nexa 语言的 AST 节点定义。
 
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// 语法树节点编号，在整次编译的所有模块中唯一
+///
+/// 类型检查结果以它为键，不同文件中位置相同的节点不会互相覆盖。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// 分配一个新的节点编号
+    pub fn next() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        NodeId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 /// 源位置信息
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Span {
     pub start: (usize, usize), // (line, column)
     pub end: (usize, usize),
+    /// 带有该位置的节点的编号
+    pub id: NodeId,
 }
 
 impl Span {
     pub fn merge(&self, other: &Span) -> Self {
-        Self { start: self.start, end: other.end }
+        Self { start: self.start, end: other.end, id: NodeId::next() }
     }
 }
 
 /// 类型定义 (TypeScript 风格)
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
+    /// `number`，与 `i32` 同宽的默认整数类型
     Number,
+    /// 定长有符号整数 `i8` / `i16` / `i32` / `i64`
+    I8,
+    I16,
+    I32,
+    I64,
+    /// 定长无符号整数 `u8` / `u16` / `u32` / `u64`
+    U8,
+    U16,
+    U32,
+    U64,
+    /// 浮点数 `f32` / `f64`，浮点字面量默认为 `f64`
+    F32,
+    F64,
     Boolean,
     String,
     Void,
@@ -31,6 +65,162 @@ pub enum Type {
     Function(Vec<Type>, Box<Type>),
     Struct(String),
     Object(Vec<(String, Type)>),
+    /// 联合类型 `A | B`
+    Union(Vec<Type>),
+    /// 交叉类型 `A & B`
+    Intersection(Vec<Type>),
+    /// 字面量类型 `"circle"` / `1` / `true`
+    Literal(LiteralType),
+    /// 泛型 struct / class / 接口的实例化 `Box<number>`
+    ///
+    /// 类型参数在定义内部与 struct 一样表示为 `Type::Struct`，由所在的泛型作用域解析。
+    Generic(String, Vec<Type>),
+}
+
+/// 字面量类型的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiteralType {
+    Number(i64),
+    String(String),
+    Boolean(bool),
+}
+
+impl LiteralType {
+    /// 字面量所属的基础类型
+    pub fn base_type(&self) -> Type {
+        match self {
+            LiteralType::Number(_) => Type::Number,
+            LiteralType::String(_) => Type::String,
+            LiteralType::Boolean(_) => Type::Boolean,
+        }
+    }
+}
+
+impl Type {
+    /// 整数类型的位宽和有无符号，`number` 视为 `i32`
+    pub fn int_width(&self) -> Option<(u32, bool)> {
+        match self {
+            Type::Number | Type::I32 => Some((32, true)),
+            Type::I8 => Some((8, true)),
+            Type::I16 => Some((16, true)),
+            Type::I64 => Some((64, true)),
+            Type::U8 => Some((8, false)),
+            Type::U16 => Some((16, false)),
+            Type::U32 => Some((32, false)),
+            Type::U64 => Some((64, false)),
+            _ => None,
+        }
+    }
+
+    /// 浮点类型的位宽
+    pub fn float_width(&self) -> Option<u32> {
+        match self {
+            Type::F32 => Some(32),
+            Type::F64 => Some(64),
+            _ => None,
+        }
+    }
+
+    /// 是否为数值类型（`number`、定长整数或浮点数）
+    pub fn is_numeric(&self) -> bool {
+        self.int_width().is_some() || self.float_width().is_some()
+    }
+
+    /// 把类型参数替换为实参 (`bindings`: 类型参数名 -> 类型实参)
+    pub fn substitute(&self, bindings: &HashMap<String, Type>) -> Type {
+        if bindings.is_empty() {
+            return self.clone();
+        }
+        let all = |types: &[Type]| types.iter().map(|t| t.substitute(bindings)).collect();
+        match self {
+            Type::Struct(name) => bindings.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Type::Array(elem) => Type::Array(Box::new(elem.substitute(bindings))),
+            Type::Pointer(pointee) => Type::Pointer(Box::new(pointee.substitute(bindings))),
+            Type::Function(params, ret) => {
+                Type::Function(all(params), Box::new(ret.substitute(bindings)))
+            },
+            Type::Object(fields) => Type::Object(
+                fields.iter().map(|(name, ty)| (name.clone(), ty.substitute(bindings))).collect(),
+            ),
+            Type::Union(types) => Type::Union(all(types)),
+            Type::Intersection(types) => Type::Intersection(all(types)),
+            Type::Generic(name, args) => Type::Generic(name.clone(), all(args)),
+            _ => self.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Number => write!(f, "number"),
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::F32 => write!(f, "f32"),
+            Type::F64 => write!(f, "f64"),
+            Type::Boolean => write!(f, "boolean"),
+            Type::String => write!(f, "string"),
+            Type::Void => write!(f, "void"),
+            Type::Undefined => write!(f, "undefined"),
+            Type::Null => write!(f, "null"),
+            Type::Any => write!(f, "any"),
+            Type::Never => write!(f, "never"),
+            Type::Array(elem) => write!(f, "{}[]", elem),
+            Type::Pointer(pointee) => write!(f, "*{}", pointee),
+            Type::Function(params, ret) => {
+                write!(f, "(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param)?;
+                }
+                write!(f, ") => {}", ret)
+            },
+            Type::Struct(name) => write!(f, "{}", name),
+            Type::Object(fields) => {
+                write!(f, "{{ ")?;
+                for (name, ty) in fields {
+                    write!(f, "{}: {}; ", name, ty)?;
+                }
+                write!(f, "}}")
+            },
+            Type::Union(types) | Type::Intersection(types) => {
+                let separator = if matches!(self, Type::Union(_)) { " | " } else { " & " };
+                for (i, ty) in types.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", separator)?;
+                    }
+                    match ty {
+                        Type::Union(_) | Type::Intersection(_) | Type::Function(..) => {
+                            write!(f, "({})", ty)?
+                        },
+                        _ => write!(f, "{}", ty)?,
+                    }
+                }
+                Ok(())
+            },
+            Type::Literal(LiteralType::Number(n)) => write!(f, "{}", n),
+            Type::Literal(LiteralType::String(s)) => write!(f, "\"{}\"", s),
+            Type::Literal(LiteralType::Boolean(b)) => write!(f, "{}", b),
+            Type::Generic(name, args) => {
+                write!(f, "{}<", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ">")
+            },
+        }
+    }
 }
 
 /// 表达式
@@ -41,9 +231,13 @@ pub enum Expression {
     Float(f64, Span),
     String(String, Span),
     Boolean(bool, Span),
+    Null(Span),
+    Undefined(Span),
     Identifier(String, Span),
     /// this 关键字
     This(Span),
+    /// super 关键字 (super(...) 调用父类构造函数，super.method() 调用父类方法)
+    Super(Span),
 
     /// 赋值表达式
     Assignment {
@@ -70,6 +264,8 @@ pub enum Expression {
     /// 函数调用
     Call {
         callee: Box<Expression>,
+        /// 显式的类型实参 `f<number>(x)`，省略时由类型检查推导
+        type_arguments: Vec<Type>,
         arguments: Vec<Expression>,
         span: Span,
     },
@@ -91,6 +287,8 @@ pub enum Expression {
     /// new 表达式 (构造函数调用)
     New {
         type_name: String,
+        /// 泛型 class 的显式类型实参 `new Box<number>(1)`
+        type_arguments: Vec<Type>,
         args: Vec<Expression>,
         span: Span,
     },
@@ -98,6 +296,8 @@ pub enum Expression {
     /// struct 字面量
     StructLiteral {
         name: String,
+        /// 泛型 struct 的显式类型实参 `Pair<number, string> { ... }`
+        type_arguments: Vec<Type>,
         fields: Vec<(String, Expression)>,
         span: Span,
     },
@@ -129,6 +329,13 @@ pub enum Expression {
         span: Span,
     },
 
+    /// 数值类型转换 `expr as u8`
+    Cast {
+        expression: Box<Expression>,
+        target: Type,
+        span: Span,
+    },
+
     /// typeof 表达式
     Typeof {
         operand: Box<Expression>,
@@ -171,13 +378,60 @@ pub enum Expression {
     },
 }
 
+impl Expression {
+    /// 获取表达式的源位置
+    pub fn span(&self) -> &Span {
+        match self {
+            Expression::Number(_, span)
+            | Expression::Float(_, span)
+            | Expression::String(_, span)
+            | Expression::Boolean(_, span)
+            | Expression::Null(span)
+            | Expression::Undefined(span)
+            | Expression::Identifier(_, span)
+            | Expression::This(span)
+            | Expression::Super(span) => span,
+            Expression::Assignment { span, .. }
+            | Expression::Binary { span, .. }
+            | Expression::Unary { span, .. }
+            | Expression::Call { span, .. }
+            | Expression::Index { span, .. }
+            | Expression::Member { span, .. }
+            | Expression::New { span, .. }
+            | Expression::StructLiteral { span, .. }
+            | Expression::FunctionExpression { span, .. }
+            | Expression::ArrayLiteral { span, .. }
+            | Expression::Ternary { span, .. }
+            | Expression::Cast { span, .. }
+            | Expression::Typeof { span, .. }
+            | Expression::Instanceof { span, .. }
+            | Expression::In { span, .. }
+            | Expression::Delete { span, .. }
+            | Expression::NullishCoalescing { span, .. }
+            | Expression::OptionalChain { span, .. } => span,
+        }
+    }
+
+    /// 字面量表达式 (包括取负的数字字面量) 的类型，与类型检查器对未标注变量的推断一致
+    pub fn literal_type(&self) -> Option<Type> {
+        match self {
+            Expression::Number(..) => Some(Type::Number),
+            Expression::Float(..) => Some(Type::F64),
+            Expression::String(..) => Some(Type::String),
+            Expression::Boolean(..) => Some(Type::Boolean),
+            Expression::Unary { op: UnaryOp::Negate, operand, .. } => operand.literal_type(),
+            _ => None,
+        }
+    }
+}
+
 /// 闭包捕获的变量
 #[derive(Debug, Clone, PartialEq)]
 pub struct Capture {
     /// 捕获的变量名
     pub name: String,
-    /// 捕获方式（按值或按引用）
-    pub by_ref: bool,
+    /// 闭包内 (包括嵌套的闭包) 是否给该变量赋值
+    pub assigned: bool,
 }
 
 /// 可选链项目
@@ -258,10 +512,11 @@ pub enum Statement {
     },
 
     /// while 循环
-    While { condition: Expression, body: Box<Statement>, span: Span },
+    While { label: Option<String>, condition: Expression, body: Box<Statement>, span: Span },
 
     /// for 循环
     For {
+        label: Option<String>,
         initializer: Box<Statement>,
         condition: Option<Expression>,
         update: Option<Expression>,
@@ -281,14 +536,14 @@ pub enum Statement {
     /// return 语句
     Return(Option<Expression>, Span),
 
-    /// break 语句
-    Break(Span),
+    /// break 语句 (break; 或 break label;)
+    Break { label: Option<String>, span: Span },
 
-    /// continue 语句
-    Continue(Span),
+    /// continue 语句 (continue; 或 continue label;)
+    Continue { label: Option<String>, span: Span },
 
     /// do...while 循环
-    DoWhile { body: Box<Statement>, condition: Expression, span: Span },
+    DoWhile { label: Option<String>, body: Box<Statement>, condition: Expression, span: Span },
 
     /// throw 语句
     Throw { value: Expression, span: Span },
@@ -313,12 +568,30 @@ pub enum Statement {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Function {
     pub name: String,
+    /// 类型参数 `function f<T, U extends Shape>(...)`
+    pub type_parameters: Vec<TypeParameter>,
     pub parameters: Vec<Parameter>,
     pub return_type: Type,
     pub body: Statement,
     pub span: Span,
     #[allow(dead_code)]
     pub is_variadic: bool,
+    /// `declare function` 声明的外部函数，没有函数体，由运行时以同名符号提供
+    pub is_declare: bool,
+}
+
+/// 类型参数及其约束 `T extends Shape`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeParameter {
+    pub name: String,
+    pub constraint: Option<Type>,
+}
+
+impl TypeParameter {
+    /// 类型参数名到类型实参的映射
+    pub fn bind(parameters: &[TypeParameter], arguments: &[Type]) -> HashMap<String, Type> {
+        parameters.iter().map(|p| p.name.clone()).zip(arguments.iter().cloned()).collect()
+    }
 }
 
 /// 函数参数
@@ -332,11 +605,66 @@ pub struct Parameter {
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructDefinition {
     pub name: String,
+    /// 泛型 struct / class 的类型参数，方法和构造函数共享这些参数
+    pub type_parameters: Vec<TypeParameter>,
     pub fields: Vec<StructField>,
     pub methods: Vec<Function>,
     pub constructor: Option<Function>,
-    /// 类实现的接口列表
-    pub implements: Vec<String>,
+    /// 父类 (单继承)
+    pub extends: Option<String>,
+    /// 类实现的接口列表 (`Struct` 或泛型接口的 `Generic`)
+    pub implements: Vec<Type>,
+    /// 是否为 class (引用语义，运行时以对象指针表示)
+    pub is_class: bool,
+    pub span: Span,
+}
+
+/// 枚举定义
+///
+/// 成员的值在解析时确定，类型注解中的枚举名与 struct 一样表示为 `Type::Struct`。
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDefinition {
+    pub name: String,
+    pub members: Vec<EnumMember>,
+    /// 是否为 const enum (只能通过成员访问使用)
+    pub is_const: bool,
+    pub span: Span,
+}
+
+impl EnumDefinition {
+    /// 按名称查找成员
+    pub fn member(&self, name: &str) -> Option<&EnumMember> {
+        self.members.iter().find(|m| m.name == name)
+    }
+
+    /// 是否为字符串枚举
+    pub fn is_string(&self) -> bool {
+        self.members.iter().any(|m| matches!(m.value, EnumValue::String(_)))
+    }
+}
+
+/// 枚举成员
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumMember {
+    pub name: String,
+    pub value: EnumValue,
+    pub span: Span,
+}
+
+/// 枚举成员的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumValue {
+    Number(i64),
+    String(String),
+}
+
+/// 类型别名 `type Name = T;`
+///
+/// 与 struct 一样，类型注解中的别名名称表示为 `Type::Struct`，由检查器和代码生成器按需展开。
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAlias {
+    pub name: String,
+    pub ty: Type,
     pub span: Span,
 }
 
@@ -371,6 +699,10 @@ pub enum SwitchPattern {
     Number(i64),
     /// 标识符（变量名或通配符）
     Identifier(String),
+    /// 字符串字面量
+    String(String),
+    /// 枚举成员 (枚举名, 成员名)，如 Color.Red
+    EnumMember(String, String),
     /// 通配符（默认分支）
     Wildcard,
     /// default 分支
@@ -384,8 +716,10 @@ pub struct ImportDeclaration {
     pub module_path: String,
     /// 导入的符号列表
     pub imports: Vec<ImportSpecifier>,
-    /// 别名 (如 import { foo as bar })
+    /// 命名空间别名 (如 import * as ns)
     pub alias: Option<String>,
+    /// 默认导入的本地名称 (如 import foo from "module")
+    pub default: Option<String>,
     pub span: Span,
 }
 
@@ -411,8 +745,10 @@ pub struct ExportDeclaration {
 pub enum ExportKind {
     /// 导出指定符号
     Named(Vec<ExportSpecifier>),
-    /// 导出默认值
-    Default(Expression),
+    /// 导出默认值 (export default)，值为模块中声明的名称
+    ///
+    /// `export default <字面量>` 由解析器保存到名为 default 的模块常量中。
+    Default(String),
     /// 重新导出 (export * from "module")
     ReExport(String),
 }
@@ -433,6 +769,8 @@ pub struct Program {
     pub methods: Vec<Function>,
     pub structs: Vec<StructDefinition>,
     pub interfaces: Vec<InterfaceDefinition>,
+    pub enums: Vec<EnumDefinition>,
+    pub type_aliases: Vec<TypeAlias>,
     pub statements: Vec<Statement>,
     pub imports: Vec<ImportDeclaration>,
     pub exports: Vec<ExportDeclaration>,
@@ -442,6 +780,7 @@ pub struct Program {
 #[derive(Debug, Clone, PartialEq)]
 pub struct InterfaceDefinition {
     pub name: String,
+    pub type_parameters: Vec<TypeParameter>,
     pub fields: Vec<InterfaceField>,
     pub methods: Vec<InterfaceMethod>,
     pub span: Span,
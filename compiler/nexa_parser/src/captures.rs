@@ -0,0 +1,248 @@
+//! 闭包捕获分析
+//!
+//! 找出函数表达式体中引用、但未在闭包内部声明的变量，`this` 同样作为变量捕获。
+//! 被闭包捕获、且在所在函数中任何位置 (包括闭包内) 被赋值的变量放在堆上的单元中，
+//! 由外层函数和闭包共享 (见 `cell_variables`)；其余变量按值捕获。
+//! 这里只做语法层面的分析：引用的全局函数等名称也会出现在结果中，
+//! 由代码生成阶段根据外层作用域过滤。
+
+use std::collections::HashSet;
+
+use crate::ast::*;
+
+/// 分析函数表达式需要捕获的变量
+pub(crate) fn analyze_captures(parameters: &[Parameter], body: &Statement) -> Vec<Capture> {
+    let mut analyzer = CaptureAnalyzer::default();
+    analyzer.scopes.push(parameters.iter().map(|p| p.name.clone()).collect());
+    analyzer.visit_statement(body);
+    analyzer.captures
+}
+
+/// 函数中需要放在堆上单元中的变量：被内层闭包捕获，并且在函数中任何位置被赋值
+///
+/// 按名称判断，同名的不同变量会一并放入单元，只影响存放位置而不影响语义。
+pub fn cell_variables(parameters: &[Parameter], body: &[&Statement]) -> HashSet<String> {
+    let mut analyzer = CaptureAnalyzer::default();
+    analyzer.scopes.push(parameters.iter().map(|p| p.name.clone()).collect());
+    for stmt in body {
+        analyzer.visit_statement(stmt);
+    }
+    analyzer.captured.intersection(&analyzer.assigned).cloned().collect()
+}
+
+#[derive(Default)]
+struct CaptureAnalyzer {
+    /// 闭包内部的作用域栈
+    scopes: Vec<HashSet<String>>,
+    /// 按首次出现顺序记录的捕获
+    captures: Vec<Capture>,
+    /// 被赋值的全部变量 (包括局部变量和内层闭包中的赋值)
+    assigned: HashSet<String>,
+    /// 被内层闭包捕获的变量
+    captured: HashSet<String>,
+}
+
+impl CaptureAnalyzer {
+    fn is_local(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    fn declare(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string());
+        }
+    }
+
+    /// 记录一次对外部变量的使用
+    fn use_name(&mut self, name: &str, assigned: bool) {
+        if assigned {
+            self.assigned.insert(name.to_string());
+        }
+        if self.is_local(name) {
+            return;
+        }
+        match self.captures.iter_mut().find(|c| c.name == name) {
+            Some(capture) => capture.assigned |= assigned,
+            None => self.captures.push(Capture { name: name.to_string(), assigned }),
+        }
+    }
+
+    fn visit_scoped(&mut self, stmt: &Statement) {
+        self.scopes.push(HashSet::new());
+        self.visit_statement(stmt);
+        self.scopes.pop();
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Block(stmts, _) => {
+                self.scopes.push(HashSet::new());
+                for s in stmts {
+                    self.visit_statement(s);
+                }
+                self.scopes.pop();
+            },
+            Statement::VariableDeclaration { name, initializer, .. } => {
+                if let Some(init) = initializer {
+                    self.visit_expression(init);
+                }
+                self.declare(name);
+            },
+            Statement::Assignment { target, value, .. } => {
+                self.visit_assignment_target(target);
+                self.visit_expression(value);
+            },
+            Statement::ExpressionStatement(expr) | Statement::Throw { value: expr, .. } => {
+                self.visit_expression(expr);
+            },
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.visit_expression(condition);
+                self.visit_scoped(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.visit_scoped(else_branch);
+                }
+            },
+            Statement::While { condition, body, .. }
+            | Statement::DoWhile { condition, body, .. } => {
+                self.visit_expression(condition);
+                self.visit_scoped(body);
+            },
+            Statement::For { initializer, condition, update, body, .. } => {
+                self.scopes.push(HashSet::new());
+                self.visit_statement(initializer);
+                if let Some(condition) = condition {
+                    self.visit_expression(condition);
+                }
+                if let Some(update) = update {
+                    self.visit_expression(update);
+                }
+                self.visit_scoped(body);
+                self.scopes.pop();
+            },
+            Statement::Switch { value, arms, .. } => {
+                self.visit_expression(value);
+                for arm in arms {
+                    if let SwitchPattern::Identifier(name) = &arm.pattern {
+                        self.use_name(name, false);
+                    }
+                    self.visit_scoped(&arm.body);
+                }
+            },
+            Statement::Return(value, _) => {
+                if let Some(value) = value {
+                    self.visit_expression(value);
+                }
+            },
+            Statement::TryCatchFinally {
+                try_body, catch_var, catch_body, finally_body, ..
+            } => {
+                self.visit_scoped(try_body);
+                if let Some(catch_body) = catch_body {
+                    self.scopes.push(HashSet::new());
+                    if let Some(var) = catch_var {
+                        self.declare(var);
+                    }
+                    self.visit_statement(catch_body);
+                    self.scopes.pop();
+                }
+                if let Some(finally_body) = finally_body {
+                    self.visit_scoped(finally_body);
+                }
+            },
+            Statement::Break { .. } | Statement::Continue { .. } | Statement::Empty(_) => {},
+        }
+    }
+
+    /// 赋值目标中的标识符按引用捕获
+    fn visit_assignment_target(&mut self, target: &Expression) {
+        match target {
+            Expression::Identifier(name, _) => self.use_name(name, true),
+            other => self.visit_expression(other),
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Number(..)
+            | Expression::Float(..)
+            | Expression::String(..)
+            | Expression::Boolean(..)
+            | Expression::Null(_)
+            | Expression::Undefined(_) => {},
+            // super 同样通过 this 访问当前实例
+            Expression::This(_) | Expression::Super(_) => self.use_name("this", false),
+            Expression::Identifier(name, _) => self.use_name(name, false),
+            Expression::Assignment { target, value, .. } => {
+                self.visit_assignment_target(target);
+                self.visit_expression(value);
+            },
+            Expression::Binary { left, right, .. }
+            | Expression::Instanceof { left, right, .. }
+            | Expression::In { left, right, .. }
+            | Expression::NullishCoalescing { left, right, .. } => {
+                self.visit_expression(left);
+                self.visit_expression(right);
+            },
+            Expression::Unary { op: UnaryOp::AddressOf, operand, .. } => {
+                // 取地址后可能被修改，按引用捕获
+                self.visit_assignment_target(operand);
+            },
+            Expression::Unary { operand, .. }
+            | Expression::Typeof { operand, .. }
+            | Expression::Delete { operand, .. }
+            | Expression::Cast { expression: operand, .. } => self.visit_expression(operand),
+            Expression::Call { callee, arguments, .. } => {
+                self.visit_expression(callee);
+                for arg in arguments {
+                    self.visit_expression(arg);
+                }
+            },
+            Expression::Index { array, index, .. } => {
+                self.visit_expression(array);
+                self.visit_expression(index);
+            },
+            Expression::Member { object, .. } => self.visit_expression(object),
+            Expression::New { args, .. } => {
+                for arg in args {
+                    self.visit_expression(arg);
+                }
+            },
+            Expression::StructLiteral { fields, .. } => {
+                for (_, value) in fields {
+                    self.visit_expression(value);
+                }
+            },
+            Expression::FunctionExpression { captures, .. } => {
+                // 内层闭包的捕获已经分析过，对外层来说同样是使用
+                for capture in captures {
+                    self.captured.insert(capture.name.clone());
+                    self.use_name(&capture.name, capture.assigned);
+                }
+            },
+            Expression::ArrayLiteral { elements, .. } => {
+                for element in elements {
+                    self.visit_expression(element);
+                }
+            },
+            Expression::Ternary { condition, then_expr, else_expr, .. } => {
+                self.visit_expression(condition);
+                self.visit_expression(then_expr);
+                self.visit_expression(else_expr);
+            },
+            Expression::OptionalChain { base, chains, .. } => {
+                self.visit_expression(base);
+                for chain in chains {
+                    match chain {
+                        OptionalChainItem::Call(args) => {
+                            for arg in args {
+                                self.visit_expression(arg);
+                            }
+                        },
+                        OptionalChainItem::Index(index) => self.visit_expression(index),
+                        OptionalChainItem::Member(_) => {},
+                    }
+                }
+            },
+        }
+    }
+}
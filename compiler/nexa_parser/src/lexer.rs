@@ -144,6 +144,7 @@ pub enum Token {
 }
 
 /// 词法分析器
+#[derive(Clone)]
 pub struct Lexer<'a> {
     source: Peekable<Chars<'a>>,
     position: usize,
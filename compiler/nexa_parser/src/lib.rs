@@ -3,11 +3,15 @@
 //! 词法分析和语法分析，将源代码转换为 AST。
 
 pub mod ast;
+mod captures;
 pub mod lexer;
 pub mod module;
 pub mod parser;
 
 pub use ast::*;
+pub use captures::cell_variables;
 pub use lexer::{Lexer, Token};
-pub use module::{ExportedSymbol, Module, ModuleError, ModuleLoader, SymbolTable};
+pub use module::{
+    ExportedSymbol, ImportGraph, ImportStep, Module, ModuleError, ModuleLoader, SymbolTable,
+};
 pub use parser::{ParseError, Parser};
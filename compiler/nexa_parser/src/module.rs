@@ -2,28 +2,40 @@
 //!
 //! 提供模块加载、符号解析和多文件编译支持。
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::ast::*;
-use crate::lexer::Lexer;
 use crate::parser::Parser;
 
+/// 内置模块所在的虚拟根目录，内置模块的文件路径形如 `<nexa>/std/io.ts`
+const BUNDLED_ROOT: &str = "<nexa>";
+
 /// 模块加载错误
 #[derive(Debug)]
 pub enum ModuleError {
     IoError(String),
     ParseError(String),
     ModuleNotFound(String),
-    SymbolNotFound(String),
-    CircularDependency(String),
+    /// 导入的名称没有被模块导出 (名称, 所在的 import)
+    SymbolNotFound(String, ImportStep),
+    /// 不允许的循环导入，按导入顺序列出环上的每条 import
+    CircularDependency(Vec<ImportStep>),
+    /// 同一名称从多个模块导入
+    AmbiguousImport(String),
+    /// 多条 export * 重新导出了来自不同模块的同名符号
+    ConflictingExport(String),
+    /// 模块文件不在任何搜索路径下，无法得到与机器无关的模块路径 (文件路径)
+    OutsideModuleRoots(String),
+    /// 导出的模块级变量没有类型注解，且类型无法从字面量初始化表达式推断 (名称, 所在文件, 声明位置)
+    UntypedExport(String, String, Span),
 }
 
 /// 模块信息
 #[derive(Debug, Clone)]
 pub struct Module {
-    /// 模块路径 (如 "std/io")
+    /// 模块路径 (如 "std/io")，由模块文件决定，与导入时使用的说明符无关
     pub path: String,
     /// 模块文件路径
     pub file_path: String,
@@ -31,6 +43,45 @@ pub struct Module {
     pub program: Program,
     /// 导出的符号表
     pub exports: HashMap<String, ExportedSymbol>,
+    /// 经 export * 重新导出的名称 -> 定义它的模块路径
+    pub export_origins: HashMap<String, String>,
+}
+
+impl Module {
+    /// 导出名称实际定义所在的模块路径
+    pub fn origin_of(&self, name: &str) -> &str {
+        self.export_origins.get(name).map_or(&self.path, String::as_str)
+    }
+}
+
+/// 导入链中的一条 import
+#[derive(Debug, Clone)]
+pub struct ImportStep {
+    /// 发起导入的文件
+    pub file_path: String,
+    /// 被导入的模块路径
+    pub module_path: String,
+    /// import 声明在发起导入的文件中的位置
+    pub span: Span,
+}
+
+/// 入口模块的导入图
+#[derive(Debug, Clone, Default)]
+pub struct ImportGraph {
+    /// 直接或间接导入的模块，每个模块都排在导入它的模块之前 (环上的模块除外)
+    pub modules: Vec<Module>,
+    /// 入口模块被环上的模块导入时使用的模块路径
+    pub entry_path: Option<String>,
+}
+
+/// 导入图遍历中正在处理的模块
+struct ImportFrame {
+    file_path: String,
+    /// 规范化后的文件路径，用于识别同一个文件
+    canonical: PathBuf,
+    exports: HashMap<String, ExportedSymbol>,
+    /// 当前正在跟随的 import
+    import: Option<ImportDeclaration>,
 }
 
 /// 导出的符号
@@ -38,8 +89,12 @@ pub struct Module {
 #[allow(clippy::large_enum_variant)]
 pub enum ExportedSymbol {
     Function(Box<Function>),
-    Variable(String, Type),
+    /// 模块级变量 (名称, 类型, 是否可变)
+    Variable(String, Type, bool),
     Struct(StructDefinition),
+    Interface(InterfaceDefinition),
+    Enum(EnumDefinition),
+    TypeAlias(TypeAlias),
     Constant(String),
 }
 
@@ -50,12 +105,14 @@ pub struct SymbolTable {
     symbols: HashMap<String, ExportedSymbol>,
     /// 别名映射
     aliases: HashMap<String, String>,
+    /// 命名空间导入 (import * as ns)：命名空间名称 -> 模块的全部导出
+    namespaces: HashMap<String, HashMap<String, ExportedSymbol>>,
 }
 
 impl SymbolTable {
     /// 创建新的符号表
     pub fn new() -> Self {
-        Self { symbols: HashMap::new(), aliases: HashMap::new() }
+        Self { symbols: HashMap::new(), aliases: HashMap::new(), namespaces: HashMap::new() }
     }
 
     /// 添加符号
@@ -82,29 +139,47 @@ impl SymbolTable {
     pub fn symbols(&self) -> Vec<String> {
         self.symbols.keys().cloned().collect()
     }
+
+    /// 添加命名空间导入，同一命名空间的多次添加合并在一起
+    pub fn add_namespace(&mut self, alias: String, exports: HashMap<String, ExportedSymbol>) {
+        self.namespaces.entry(alias).or_default().extend(exports);
+    }
+
+    /// 查找命名空间导入的全部导出
+    pub fn namespace(&self, alias: &str) -> Option<&HashMap<String, ExportedSymbol>> {
+        self.namespaces.get(alias)
+    }
+
+    /// 获取所有命名空间名称
+    pub fn namespaces(&self) -> Vec<String> {
+        self.namespaces.keys().cloned().collect()
+    }
 }
 
 /// 模块加载器
 pub struct ModuleLoader {
-    /// 已加载的模块缓存
-    loaded_modules: HashMap<String, Module>,
+    /// 已加载的模块缓存 (规范化的文件路径 -> 模块)
+    loaded_modules: HashMap<PathBuf, Module>,
     /// 模块搜索路径
     search_paths: Vec<String>,
-    /// 全局符号表
-    global_symbols: HashMap<String, SymbolTable>,
+    /// 正在合并 export * 的模块，用于检测重新导出的环
+    reexporting: Vec<PathBuf>,
+    /// 编译器内置的模块源码 (模块路径 -> 源码)，如标准库的 "std/io"
+    bundled: HashMap<String, String>,
 }
 
 impl ModuleLoader {
     /// 创建新的模块加载器
+    ///
+    /// 没有默认的搜索路径，裸说明符和模块路径都不依赖当前工作目录，
+    /// 由调用方添加 -I 指定的路径和入口文件所在的目录。
     pub fn new() -> Self {
-        let search_paths = vec![
-            ".".to_string(),
-            "examples".to_string(),
-            "examples/std".to_string(),
-            "compiler/nexac/std".to_string(),
-        ];
-
-        Self { loaded_modules: HashMap::new(), search_paths, global_symbols: HashMap::new() }
+        Self {
+            loaded_modules: HashMap::new(),
+            search_paths: Vec::new(),
+            reexporting: Vec::new(),
+            bundled: HashMap::new(),
+        }
     }
 
     /// 添加搜索路径
@@ -112,162 +187,555 @@ impl ModuleLoader {
         self.search_paths.push(path);
     }
 
+    /// 添加内置模块
+    ///
+    /// 内置模块不对应磁盘上的文件，以模块路径 (如 "std/io") 导入时优先于搜索路径，
+    /// 内置模块之间可以使用相对导入。
+    pub fn add_bundled_module(&mut self, module_path: &str, source: &str) {
+        self.bundled.insert(module_path.to_string(), source.to_string());
+    }
+
+    /// 读取模块文件的源码，内置模块返回编译器中的源码
+    pub fn read_source(&self, file_path: &Path) -> Result<String, ModuleError> {
+        match self.bundled_path(file_path) {
+            Some(module_path) => Ok(self.bundled[module_path].clone()),
+            None => fs::read_to_string(file_path).map_err(|e| ModuleError::IoError(e.to_string())),
+        }
+    }
+
+    /// 内置模块的文件路径对应的模块路径
+    fn bundled_path<'a>(&self, file_path: &'a Path) -> Option<&'a str> {
+        let module_path = file_path
+            .to_str()?
+            .strip_prefix(BUNDLED_ROOT)?
+            .strip_prefix('/')?
+            .strip_suffix(".ts")?;
+        self.bundled.contains_key(module_path).then_some(module_path)
+    }
+
+    /// 规范化的模块文件路径，内置模块的路径本身就是规范的
+    fn canonical_path(&self, file_path: &Path) -> Result<PathBuf, ModuleError> {
+        if self.bundled_path(file_path).is_some() {
+            return Ok(file_path.to_path_buf());
+        }
+        fs::canonicalize(file_path).map_err(|e| ModuleError::IoError(e.to_string()))
+    }
+
+    /// 模块路径对应的内置模块文件
+    fn bundled_file(&self, module_path: &str) -> Option<PathBuf> {
+        self.bundled
+            .contains_key(module_path)
+            .then(|| PathBuf::from(format!("{}/{}.ts", BUNDLED_ROOT, module_path)))
+    }
+
     /// 加载模块
-    pub fn load_module(&mut self, module_path: &str) -> Result<Module, ModuleError> {
+    ///
+    /// `importer` 是发起导入的文件，`./` 和 `../` 开头的说明符相对它所在的目录解析。
+    pub fn load_module(&mut self, specifier: &str, importer: &Path) -> Result<Module, ModuleError> {
+        // 查找模块文件
+        let file_path = self.find_module_file(specifier, importer)?;
+        let canonical = self.canonical_path(&file_path)?;
+
         // 检查缓存
-        if let Some(module) = self.loaded_modules.get(module_path) {
+        if let Some(module) = self.loaded_modules.get(&canonical) {
             return Ok(module.clone());
         }
 
-        // 查找模块文件
-        let file_path = self.find_module_file(module_path)?;
-
         // 读取源文件
-        let source =
-            fs::read_to_string(&file_path).map_err(|e| ModuleError::IoError(e.to_string()))?;
+        let source = self.read_source(&file_path)?;
 
         // 解析模块
-        let _lexer = Lexer::new(&source);
         let mut parser = Parser::new(&source);
         let program = parser.parse_program().map_err(|e| ModuleError::ParseError(e.message))?;
 
-        // 提取导出的符号
-        let exports = self.extract_exports(&program);
+        // 提取导出的符号，并合并 export * 重新导出的符号
+        let mut exports = self.extract_exports(&program, &file_path)?;
+        self.reexporting.push(canonical.clone());
+        let export_origins = self.merge_reexports(&program, &file_path, &mut exports);
+        self.reexporting.pop();
+        let export_origins = export_origins?;
 
         // 创建模块
-        let module = Module { path: module_path.to_string(), file_path, program, exports };
+        let module = Module {
+            path: self.module_id(&canonical)?,
+            file_path: file_path.to_string_lossy().into_owned(),
+            program,
+            exports,
+            export_origins,
+        };
 
         // 缓存模块
         let result = module.clone();
-        self.loaded_modules.insert(module_path.to_string(), module);
+        self.loaded_modules.insert(canonical, module);
 
         Ok(result)
     }
 
-    /// 查找模块文件
-    fn find_module_file(&self, module_path: &str) -> Result<String, ModuleError> {
-        // 尝试不同的文件扩展名
-        let extensions = ["ts", "nexa", "nex"];
+    /// 合并 `export * from` 重新导出的符号，返回重新导出的名称 -> 定义它的模块路径
+    ///
+    /// 与 TS 一致：default 不会被重新导出，模块自己的导出优先于重新导出的同名符号，
+    /// 两条 export * 提供来自不同模块的同名符号时报错。
+    fn merge_reexports(
+        &mut self,
+        program: &Program,
+        file_path: &Path,
+        exports: &mut HashMap<String, ExportedSymbol>,
+    ) -> Result<HashMap<String, String>, ModuleError> {
+        let local: HashSet<String> = exports.keys().cloned().collect();
+        let mut origins: HashMap<String, String> = HashMap::new();
+
+        for export in &program.exports {
+            let ExportKind::ReExport(specifier) = &export.kind else { continue };
+            let target = self.canonical_path(&self.find_module_file(specifier, file_path)?)?;
+            if self.reexporting.contains(&target) {
+                return Err(ModuleError::CircularDependency(vec![ImportStep {
+                    file_path: file_path.to_string_lossy().into_owned(),
+                    module_path: specifier.clone(),
+                    span: export.span.clone(),
+                }]));
+            }
+
+            let module = self.load_module(specifier, file_path)?;
+            for (name, symbol) in module.exports {
+                if name == "default" || local.contains(&name) {
+                    continue;
+                }
+                let origin = module
+                    .export_origins
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_else(|| module.path.clone());
+                if let Some(previous) = origins.get(&name) {
+                    if *previous != origin {
+                        return Err(ModuleError::ConflictingExport(format!(
+                            "'{}' is re-exported from both '{}' and '{}'",
+                            name, previous, origin
+                        )));
+                    }
+                }
+                origins.insert(name.clone(), origin);
+                exports.insert(name, symbol);
+            }
+        }
 
-        // 将模块路径转换为文件路径
-        let relative_path = module_path.replace('.', "/");
+        Ok(origins)
+    }
+
+    /// 查找模块文件
+    ///
+    /// 相对说明符 (`./`、`../`) 相对导入方所在目录解析，其余说明符按字面路径先查找内置模块，
+    /// 再依次在搜索路径中查找。旧的 `std.io` 写法只作为内置模块 `std/io` 的别名。
+    /// 说明符可以指向带扩展名的文件、省略扩展名的文件或包含 `index` 文件的目录。
+    fn find_module_file(&self, specifier: &str, importer: &Path) -> Result<PathBuf, ModuleError> {
+        if specifier.starts_with("./") || specifier.starts_with("../") {
+            let not_found = || {
+                ModuleError::ModuleNotFound(format!(
+                    "Module '{}' not found relative to '{}'",
+                    specifier,
+                    importer.display()
+                ))
+            };
+            // 内置模块之间的相对导入在虚拟根目录中解析
+            if let Some(module_path) = self.bundled_path(importer) {
+                return Self::join_module_path(module_path, specifier)
+                    .and_then(|path| self.bundled_file(&path))
+                    .ok_or_else(not_found);
+            }
+            let base = importer.parent().unwrap_or(Path::new(".")).join(specifier);
+            return Self::module_file_candidate(&base).ok_or_else(not_found);
+        }
 
+        let std_alias = specifier.strip_prefix("std.").map(|rest| format!("std/{}", rest));
+        if let Some(file) = self.bundled_file(std_alias.as_deref().unwrap_or(specifier)) {
+            return Ok(file);
+        }
         for search_path in &self.search_paths {
-            for ext in &extensions {
-                let file_path = format!("{}/{}.{}", search_path, relative_path, ext);
-                if Path::new(&file_path).exists() {
-                    return Ok(file_path);
-                }
+            if let Some(file) = Self::module_file_candidate(&Path::new(search_path).join(specifier))
+            {
+                return Ok(file);
             }
         }
 
         Err(ModuleError::ModuleNotFound(format!(
             "Module '{}' not found in search paths: {:?}",
-            module_path, self.search_paths
+            specifier, self.search_paths
         )))
     }
 
-    /// 从 Program 中提取导出的符号
-    fn extract_exports(&self, program: &Program) -> HashMap<String, ExportedSymbol> {
-        let mut exports = HashMap::new();
+    /// 将相对说明符拼接到模块路径所在的目录，返回目标模块路径
+    fn join_module_path(module_path: &str, specifier: &str) -> Option<String> {
+        let mut parts: Vec<&str> = module_path.split('/').collect();
+        parts.pop();
+        for part in specifier.split('/') {
+            match part {
+                "." | "" => {},
+                ".." => {
+                    parts.pop()?;
+                },
+                part => parts.push(part),
+            }
+        }
+        let path = parts.join("/");
+        Some(path.strip_suffix(".ts").map(str::to_string).unwrap_or(path))
+    }
 
-        // 处理函数导出
-        for func in &program.functions {
-            // 检查函数是否在导出列表中
-            let is_exported = program.exports.iter().any(|exp| match &exp.kind {
-                ExportKind::Named(specs) => specs.iter().any(|s| s.name == func.name),
-                _ => false,
-            });
+    /// 按 `<base>`、`<base>.<ext>`、`<base>/index.<ext>` 的顺序查找模块文件
+    fn module_file_candidate(base: &Path) -> Option<PathBuf> {
+        // 尝试不同的文件扩展名
+        let extensions = ["ts", "nexa", "nex"];
 
-            // 如果有 export 声明且函数被列出，则导出
-            // 否则默认导出所有函数（暂时这样处理）
-            if !program.exports.is_empty() && is_exported {
-                exports.insert(func.name.clone(), ExportedSymbol::Function(Box::new(func.clone())));
-            } else if program.exports.is_empty() {
-                // 没有显式导出，默认导出所有函数
-                exports.insert(func.name.clone(), ExportedSymbol::Function(Box::new(func.clone())));
+        let has_extension =
+            base.extension().is_some_and(|ext| extensions.iter().any(|e| ext == *e));
+        if has_extension && base.is_file() {
+            return Some(base.to_path_buf());
+        }
+        for ext in &extensions {
+            let mut file = base.as_os_str().to_owned();
+            file.push(format!(".{}", ext));
+            let file = PathBuf::from(file);
+            if file.is_file() {
+                return Some(file);
             }
         }
+        extensions.iter().map(|ext| base.join(format!("index.{}", ext))).find(|file| file.is_file())
+    }
 
-        // 处理变量导出
-        for stmt in &program.statements {
-            if let Statement::VariableDeclaration {
-                name,
-                type_annotation,
-                initializer,
-                mutable: _,
-                span: _,
-            } = stmt
-            {
-                // 检查是否在导出列表中
-                let is_exported = program.exports.iter().any(|exp| match &exp.kind {
-                    ExportKind::Named(specs) => specs.iter().any(|s| s.name == *name),
-                    _ => false,
-                });
-
-                if !program.exports.is_empty() && is_exported {
-                    let var_type = type_annotation.clone().unwrap_or(Type::Number);
-                    exports.insert(name.clone(), ExportedSymbol::Variable(name.clone(), var_type));
-                } else if program.exports.is_empty() && initializer.is_some() {
-                    // 默认导出带初始化的变量
-                    let var_type = type_annotation.clone().unwrap_or(Type::Number);
-                    exports.insert(name.clone(), ExportedSymbol::Variable(name.clone(), var_type));
-                }
+    /// 模块文件对应的模块路径
+    ///
+    /// 取去掉扩展名的文件路径相对于第一个包含它的搜索路径 (-I 指定的路径、入口文件所在目录)
+    /// 的部分，这样同一文件总是得到相同的模块路径。模块路径决定导出符号的修饰名，
+    /// 不能依赖当前工作目录或检出位置，所以不在任何搜索路径下的文件报告错误。
+    fn module_id(&self, canonical: &Path) -> Result<String, ModuleError> {
+        if let Some(module_path) = self.bundled_path(canonical) {
+            return Ok(module_path.to_string());
+        }
+        let stem = canonical.with_extension("");
+        self.search_paths
+            .iter()
+            .filter_map(|root| fs::canonicalize(root).ok())
+            .find_map(|root| stem.strip_prefix(&root).ok().map(Path::to_path_buf))
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+            .ok_or_else(|| {
+                ModuleError::OutsideModuleRoots(canonical.to_string_lossy().into_owned())
+            })
+    }
+
+    /// 从 Program 中提取导出的符号
+    ///
+    /// 与 TS 一致，只有 export 标记的声明可以被其他模块导入。
+    fn extract_exports(
+        &self,
+        program: &Program,
+        file_path: &Path,
+    ) -> Result<HashMap<String, ExportedSymbol>, ModuleError> {
+        let mut exports = HashMap::new();
+
+        for export in &program.exports {
+            match &export.kind {
+                // export { foo, bar as baz } 以及 export function / const / class
+                ExportKind::Named(specs) => {
+                    for spec in specs {
+                        if let Some(symbol) = Self::declared_symbol(program, &spec.name, file_path)?
+                        {
+                            let exported_name = spec.alias.as_ref().unwrap_or(&spec.name);
+                            exports.insert(exported_name.clone(), symbol);
+                        }
+                    }
+                },
+                // export default name
+                ExportKind::Default(name) => {
+                    if let Some(symbol) = Self::declared_symbol(program, name, file_path)? {
+                        exports.insert("default".to_string(), symbol);
+                    }
+                },
+                // export * from 由 merge_reexports 合并
+                ExportKind::ReExport(_) => {},
             }
         }
 
-        // 处理 struct 导出
-        for struct_def in &program.structs {
-            exports.insert(struct_def.name.clone(), ExportedSymbol::Struct(struct_def.clone()));
+        Ok(exports)
+    }
+
+    /// 模块中声明的名称对应的符号
+    fn declared_symbol(
+        program: &Program,
+        name: &str,
+        file_path: &Path,
+    ) -> Result<Option<ExportedSymbol>, ModuleError> {
+        if let Some(func) = program.functions.iter().find(|f| f.name == name) {
+            return Ok(Some(ExportedSymbol::Function(Box::new(func.clone()))));
+        }
+        if let Some(struct_def) = program.structs.iter().find(|s| s.name == name) {
+            return Ok(Some(ExportedSymbol::Struct(struct_def.clone())));
+        }
+        if let Some(interface) = program.interfaces.iter().find(|i| i.name == name) {
+            return Ok(Some(ExportedSymbol::Interface(interface.clone())));
+        }
+        if let Some(enum_def) = program.enums.iter().find(|e| e.name == name) {
+            return Ok(Some(ExportedSymbol::Enum(enum_def.clone())));
         }
+        if let Some(alias) = program.type_aliases.iter().find(|a| a.name == name) {
+            return Ok(Some(ExportedSymbol::TypeAlias(alias.clone())));
+        }
+        program
+            .statements
+            .iter()
+            .find_map(|stmt| match stmt {
+                Statement::VariableDeclaration {
+                    name: var_name,
+                    type_annotation,
+                    initializer,
+                    mutable,
+                    span,
+                } if var_name == name => Some(
+                    Self::variable_symbol(name, type_annotation, initializer, *mutable).ok_or_else(
+                        || {
+                            ModuleError::UntypedExport(
+                                name.to_string(),
+                                file_path.to_string_lossy().into_owned(),
+                                span.clone(),
+                            )
+                        },
+                    ),
+                ),
+                _ => None,
+            })
+            .transpose()
+    }
 
-        exports
+    /// 模块级变量的符号
+    ///
+    /// 未标注类型时按字面量初始化表达式推断；其他初始化表达式的类型要到类型检查时才知道，
+    /// 导入方无法据此声明外部变量，返回 None 要求导出方标注类型。
+    fn variable_symbol(
+        name: &str,
+        type_annotation: &Option<Type>,
+        initializer: &Option<Expression>,
+        mutable: bool,
+    ) -> Option<ExportedSymbol> {
+        let var_type = type_annotation
+            .clone()
+            .or_else(|| initializer.as_ref().and_then(Expression::literal_type))?;
+        Some(ExportedSymbol::Variable(name.to_string(), var_type, mutable))
     }
 
-    /// 解析导入并返回符号表
+    /// 解析导入并返回符号表，键为被导入模块的模块路径
+    ///
+    /// `importer` 是 `program` 所在的文件，用于解析相对导入。
     pub fn resolve_imports(
         &mut self,
         program: &Program,
+        importer: &Path,
     ) -> Result<HashMap<String, SymbolTable>, ModuleError> {
-        let mut import_symbols = HashMap::new();
+        let mut import_symbols: HashMap<String, SymbolTable> = HashMap::new();
+        // 本地名称 -> 来源模块，用于检测从不同模块导入的同名符号
+        let mut origins: HashMap<String, String> = HashMap::new();
 
         for import in &program.imports {
             // 加载导入的模块
-            let module = self.load_module(&import.module_path)?;
-
-            // 创建模块的符号表
-            let mut symbols = SymbolTable::new();
+            let module = self.load_module(&import.module_path, importer)?;
 
-            // 处理命名空间导入 (import * as ns)
+            // 处理命名空间导入 (import * as ns)，成员通过 ns.member 访问
             if let Some(alias) = &import.alias {
-                symbols = module.exports.clone().into();
-                self.global_symbols.insert(alias.clone(), symbols.clone());
+                Self::record_origin(&mut origins, alias, &module.path)?;
+                // 重新导出的成员记在定义它的模块下，链接时使用该模块中的名称
+                let mut by_origin: HashMap<&str, HashMap<String, ExportedSymbol>> = HashMap::new();
+                for (name, symbol) in &module.exports {
+                    by_origin
+                        .entry(module.origin_of(name))
+                        .or_default()
+                        .insert(name.clone(), symbol.clone());
+                }
+                for (origin, exports) in by_origin {
+                    let symbols = import_symbols.entry(origin.to_string()).or_default();
+                    symbols.add_namespace(alias.clone(), exports);
+                }
             }
+
             // 处理命名导入 (import { foo, bar })
-            else if !import.imports.is_empty() {
-                for spec in &import.imports {
-                    let name = &spec.name;
-                    if let Some(symbol) = module.exports.get(name) {
-                        let target_name = spec.alias.as_ref().unwrap_or(name);
-                        symbols.add(target_name.clone(), symbol.clone());
-                    }
-                }
-                // 使用模块路径作为命名空间键
-                import_symbols.insert(import.module_path.clone(), symbols);
+            for spec in &import.imports {
+                let name = &spec.name;
+                let symbol = Self::find_export(&module, name, import, importer)?;
+                // 以定义符号的模块路径作为键，同一模块的多条 import 合并到一张表
+                let origin = module.origin_of(name);
+                let target_name = spec.alias.as_ref().unwrap_or(name);
+                Self::record_origin(&mut origins, target_name, origin)?;
+                let symbols = import_symbols.entry(origin.to_string()).or_default();
+                symbols.add(target_name.clone(), symbol);
             }
+
             // 处理默认导入 (import foo from "module")
-            else if import.alias.is_some() {
-                let default_name = import.alias.as_ref().unwrap();
-                // 默认导入第一个导出的符号
-                if let Some((_name, symbol)) = module.exports.iter().next() {
-                    symbols.add(default_name.clone(), symbol.clone());
-                }
-                import_symbols.insert(import.module_path.clone(), symbols);
+            if let Some(default_name) = &import.default {
+                let symbol = Self::find_export(&module, "default", import, importer)?;
+                Self::record_origin(&mut origins, default_name, &module.path)?;
+                let symbols = import_symbols.entry(module.path.clone()).or_default();
+                symbols.add(default_name.clone(), symbol);
             }
         }
 
         Ok(import_symbols)
     }
+
+    /// 加载入口程序的整个导入图
+    ///
+    /// 循环导入按 TS 的模块语义处理：函数和 class 声明会被提升，环上的模块可以互相引用；
+    /// 模块级变量要等模块的顶层语句执行后才初始化，所以环上的任何一条 import
+    /// 引入变量时都会报告 `CircularDependency`。环上模块的初始化顺序由遍历顺序决定。
+    pub fn load_import_graph(
+        &mut self,
+        entry_file: &str,
+        program: &Program,
+    ) -> Result<ImportGraph, ModuleError> {
+        let canonical =
+            fs::canonicalize(entry_file).map_err(|e| ModuleError::IoError(e.to_string()))?;
+        let mut stack = vec![ImportFrame {
+            file_path: entry_file.to_string(),
+            canonical: canonical.clone(),
+            exports: self.extract_exports(program, Path::new(entry_file))?,
+            import: None,
+        }];
+        let mut visited = HashSet::from([canonical]);
+        let mut graph = ImportGraph::default();
+        self.visit_imports(program, &mut stack, &mut visited, &mut graph)?;
+        Ok(graph)
+    }
+
+    /// 依次访问栈顶模块的每条 import
+    fn visit_imports(
+        &mut self,
+        program: &Program,
+        stack: &mut Vec<ImportFrame>,
+        visited: &mut HashSet<PathBuf>,
+        graph: &mut ImportGraph,
+    ) -> Result<(), ModuleError> {
+        // export * from 的目标模块同样需要编译，视为不引入名称的导入
+        let reexports = program.exports.iter().filter_map(|export| match &export.kind {
+            ExportKind::ReExport(specifier) => Some(ImportDeclaration {
+                module_path: specifier.clone(),
+                imports: Vec::new(),
+                alias: None,
+                default: None,
+                span: export.span.clone(),
+            }),
+            _ => None,
+        });
+        for import in program.imports.iter().cloned().chain(reexports) {
+            let specifier = import.module_path.clone();
+            if let Some(frame) = stack.last_mut() {
+                frame.import = Some(import);
+            }
+            self.visit_module(&specifier, stack, visited, graph)?;
+        }
+        Ok(())
+    }
+
+    /// 深度优先访问模块，依赖先于模块本身加入导入图
+    fn visit_module(
+        &mut self,
+        specifier: &str,
+        stack: &mut Vec<ImportFrame>,
+        visited: &mut HashSet<PathBuf>,
+        graph: &mut ImportGraph,
+    ) -> Result<(), ModuleError> {
+        let importer = PathBuf::from(stack.last().map_or("", |frame| frame.file_path.as_str()));
+        let file_path = self.find_module_file(specifier, &importer)?;
+        let canonical = self.canonical_path(&file_path)?;
+
+        // 模块仍在栈上说明遇到了环
+        if let Some(index) = stack.iter().position(|frame| frame.canonical == canonical) {
+            Self::check_cycle(&stack[index..])?;
+            if index == 0 && graph.entry_path.is_none() {
+                graph.entry_path = Some(self.module_id(&canonical)?);
+            }
+            return Ok(());
+        }
+        if !visited.insert(canonical.clone()) {
+            return Ok(());
+        }
+
+        let module = self.load_module(specifier, &importer)?;
+        stack.push(ImportFrame {
+            file_path: module.file_path.clone(),
+            canonical,
+            exports: module.exports.clone(),
+            import: None,
+        });
+        self.visit_imports(&module.program, stack, visited, graph)?;
+        stack.pop();
+        graph.modules.push(module);
+        Ok(())
+    }
+}
+
+impl ModuleLoader {
+    /// 查找模块导出的名称，未导出时报告所在的 import
+    fn find_export(
+        module: &Module,
+        name: &str,
+        import: &ImportDeclaration,
+        importer: &Path,
+    ) -> Result<ExportedSymbol, ModuleError> {
+        module.exports.get(name).cloned().ok_or_else(|| {
+            ModuleError::SymbolNotFound(
+                name.to_string(),
+                ImportStep {
+                    file_path: importer.to_string_lossy().into_owned(),
+                    module_path: import.module_path.clone(),
+                    span: import.span.clone(),
+                },
+            )
+        })
+    }
+
+    /// 检查环上的 import：只允许引入提升的声明 (函数、class 和 struct)
+    fn check_cycle(cycle: &[ImportFrame]) -> Result<(), ModuleError> {
+        let mut steps = Vec::new();
+        let mut imports_value = false;
+        for (index, frame) in cycle.iter().enumerate() {
+            let Some(import) = &frame.import else { continue };
+            let target = &cycle[(index + 1) % cycle.len()];
+            imports_value |= Self::imports_value(import, &target.exports);
+            steps.push(ImportStep {
+                file_path: frame.file_path.clone(),
+                module_path: import.module_path.clone(),
+                span: import.span.clone(),
+            });
+        }
+        if imports_value {
+            return Err(ModuleError::CircularDependency(steps));
+        }
+        Ok(())
+    }
+
+    /// import 是否引入了模块级变量
+    fn imports_value(
+        import: &ImportDeclaration,
+        exports: &HashMap<String, ExportedSymbol>,
+    ) -> bool {
+        let is_value = |symbol: &ExportedSymbol| {
+            matches!(symbol, ExportedSymbol::Variable(..) | ExportedSymbol::Constant(_))
+        };
+        // 命名空间导入引入全部导出，纯副作用导入不引入任何名称
+        let namespace = import.alias.is_some() && exports.values().any(is_value);
+        let default = import.default.is_some() && exports.get("default").is_some_and(is_value);
+        namespace
+            || default
+            || import.imports.iter().any(|spec| exports.get(&spec.name).is_some_and(is_value))
+    }
+
+    /// 记录导入名称的来源模块，同名符号来自不同模块时报错
+    fn record_origin(
+        origins: &mut HashMap<String, String>,
+        name: &str,
+        module_path: &str,
+    ) -> Result<(), ModuleError> {
+        match origins.insert(name.to_string(), module_path.to_string()) {
+            Some(previous) if previous != module_path => Err(ModuleError::AmbiguousImport(
+                format!("'{}' is imported from both '{}' and '{}'", name, previous, module_path),
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl Default for ModuleLoader {
@@ -278,6 +746,248 @@ impl Default for ModuleLoader {
 
 impl From<HashMap<String, ExportedSymbol>> for SymbolTable {
     fn from(map: HashMap<String, ExportedSymbol>) -> Self {
-        Self { symbols: map, aliases: HashMap::new() }
+        Self { symbols: map, aliases: HashMap::new(), namespaces: HashMap::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// 测试用的模块目录，离开作用域时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("nexa_{}_{}", name, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        /// 目录中的文件路径
+        fn path(&self, file: &str) -> PathBuf {
+            self.0.join(file)
+        }
+
+        /// 写入目录中的文件 (按需创建子目录)，返回文件路径
+        fn write(&self, file: &str, source: &str) -> PathBuf {
+            let path = self.path(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, source).unwrap();
+            path
+        }
+
+        /// 以该目录为搜索路径的模块加载器
+        fn loader(&self) -> ModuleLoader {
+            let mut loader = ModuleLoader::new();
+            loader.add_search_path(self.0.to_string_lossy().into_owned());
+            loader
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_imports_keyed_by_module() {
+        let dir = TempDir::new("modules");
+        dir.write("mod_one.nexa", "export function f(): number { return 1; }");
+        dir.write(
+            "mod_two.nexa",
+            "export function f(): number { return 2; }\nfunction hidden(): number { return 3; }",
+        );
+        let mut loader = dir.loader();
+
+        let importer = dir.path("main.nexa");
+        let source = "import { f } from \"mod_one\"; import { f as g } from \"mod_two\";";
+        let symbols = loader
+            .resolve_imports(&Parser::new(source).parse_program().unwrap(), &importer)
+            .unwrap();
+        assert!(symbols["mod_one"].find("f").is_some());
+        assert!(symbols["mod_two"].find("g").is_some());
+
+        let source = "import { f } from \"mod_one\"; import { f } from \"mod_two\";";
+        let result =
+            loader.resolve_imports(&Parser::new(source).parse_program().unwrap(), &importer);
+        assert!(matches!(result, Err(ModuleError::AmbiguousImport(_))));
+
+        // 未导出的声明对导入方不可见
+        let source = "import { hidden } from \"mod_two\";";
+        let result =
+            loader.resolve_imports(&Parser::new(source).parse_program().unwrap(), &importer);
+        assert!(matches!(result, Err(ModuleError::SymbolNotFound(name, _)) if name == "hidden"));
+    }
+
+    #[test]
+    fn test_exported_variable_types() {
+        let mut loader = ModuleLoader::new();
+        loader.add_bundled_module(
+            "std/consts",
+            "export const N = 1; export const F = -2.5; export const S = \"s\"; export default 7;",
+        );
+        loader.add_bundled_module(
+            "std/untyped",
+            "function one(): number { return 1; }\nexport const X = one();",
+        );
+        let importer = Path::new("main.nexa");
+
+        let program =
+            Parser::new("import D, { N, F, S } from \"std/consts\";").parse_program().unwrap();
+        let symbols = loader.resolve_imports(&program, importer).unwrap();
+        let var_type = |name| match symbols["std/consts"].find(name) {
+            Some(ExportedSymbol::Variable(_, ty, _)) => ty.clone(),
+            other => panic!("unexpected symbol {:?}", other),
+        };
+        assert_eq!(var_type("N"), Type::Number);
+        assert_eq!(var_type("F"), Type::F64);
+        assert_eq!(var_type("S"), Type::String);
+        assert_eq!(var_type("D"), Type::Number);
+
+        // 非字面量初始化的变量需要类型注解才能导出
+        let program = Parser::new("import { X } from \"std/untyped\";").parse_program().unwrap();
+        let result = loader.resolve_imports(&program, importer);
+        assert!(matches!(result, Err(ModuleError::UntypedExport(name, ..)) if name == "X"));
+    }
+
+    #[test]
+    fn test_relative_imports() {
+        let dir = TempDir::new("relative");
+        dir.write("lib/index.nexa", "export function lib(): number { return 1; }");
+        dir.write("app/helpers.ts", "export function helper(): number { return 2; }");
+        let mut loader = dir.loader();
+
+        // 相对说明符相对导入方所在目录解析，目录解析到其中的 index 文件
+        let source = "import { helper } from \"./helpers\"; import { lib } from \"../lib\";";
+        let program = Parser::new(source).parse_program().unwrap();
+        let symbols = loader.resolve_imports(&program, &dir.path("app/main.nexa")).unwrap();
+        assert!(symbols["app/helpers"].find("helper").is_some());
+        assert!(symbols["lib/index"].find("lib").is_some());
+
+        // 同一文件以裸说明符导入时得到相同的模块路径
+        let program =
+            Parser::new("import { helper } from \"app/helpers\";").parse_program().unwrap();
+        let symbols = loader.resolve_imports(&program, &dir.path("main.nexa")).unwrap();
+        assert!(symbols.contains_key("app/helpers"));
+
+        // 裸说明符按字面路径解析，文件名中的 '.' 不是路径分隔符
+        dir.write("vendor/lodash.merge.ts", "export function merge(): number { return 3; }");
+        let program =
+            Parser::new("import { merge } from \"vendor/lodash.merge\";").parse_program().unwrap();
+        let symbols = loader.resolve_imports(&program, &dir.path("main.nexa")).unwrap();
+        assert!(symbols["vendor/lodash.merge"].find("merge").is_some());
+
+        // 不在任何搜索路径下的模块没有稳定的模块路径
+        let mut loader = ModuleLoader::new();
+        loader.add_search_path(dir.path("app").to_string_lossy().into_owned());
+        let program = Parser::new("import { lib } from \"../lib\";").parse_program().unwrap();
+        let result = loader.resolve_imports(&program, &dir.path("app/main.nexa"));
+        assert!(matches!(result, Err(ModuleError::OutsideModuleRoots(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn test_bundled_modules() {
+        let mut loader = ModuleLoader::new();
+        loader.add_bundled_module(
+            "std/io",
+            "declare function puts(s: string): number;\n\
+             export function println(s: string): void { puts(s); }",
+        );
+        loader.add_bundled_module("std/index", "export * from \"./io\";");
+
+        // 内置模块不依赖当前目录，内置模块之间的相对导入在虚拟根目录中解析，
+        // "std.io" 是 "std/io" 的别名
+        let source = "import { println } from \"std.io\"; import * as std from \"std/index\";";
+        let program = Parser::new(source).parse_program().unwrap();
+        let dir = TempDir::new("std");
+        let importer = dir.write("main.nexa", source);
+        let symbols = loader.resolve_imports(&program, &importer).unwrap();
+        assert!(matches!(symbols["std/io"].find("println"), Some(ExportedSymbol::Function(_))));
+        assert!(symbols["std/io"].namespace("std").unwrap().contains_key("println"));
+
+        let graph = loader.load_import_graph(&importer.to_string_lossy(), &program).unwrap();
+        let paths: Vec<&str> = graph.modules.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, ["std/io", "std/index"]);
+    }
+
+    #[test]
+    fn test_reexports_and_default_import() {
+        let dir = TempDir::new("reexport");
+        dir.write("re_math.nexa", "function add(): number { return 1; } export { add };");
+        dir.write("re_text.nexa", "function add(): number { return 2; } export { add };");
+        dir.write(
+            "re_facade.nexa",
+            "export * from \"./re_math\"; function main(): number { return 0; } export default main;",
+        );
+        let mut loader = dir.loader();
+        let importer = dir.path("main.nexa");
+
+        // 重新导出的符号记在定义它的模块下，默认导入记在导入的模块下
+        let source = "import entry, { add } from \"./re_facade\";";
+        let program = Parser::new(source).parse_program().unwrap();
+        let symbols = loader.resolve_imports(&program, &importer).unwrap();
+        assert!(symbols["re_math"].find("add").is_some());
+        assert!(matches!(
+            symbols["re_facade"].find("entry"),
+            Some(ExportedSymbol::Function(f)) if f.name == "main"
+        ));
+
+        // 两条 export * 提供来自不同模块的同名符号
+        dir.write("re_conflict.nexa", "export * from \"./re_math\"; export * from \"./re_text\";");
+        let program =
+            Parser::new("import { add } from \"./re_conflict\";").parse_program().unwrap();
+        let result = loader.resolve_imports(&program, &importer);
+        assert!(matches!(result, Err(ModuleError::ConflictingExport(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn test_import_graph_order() {
+        let dir = TempDir::new("graph");
+        dir.write("graph_base.nexa", "export function base(): number { return 1; }");
+        let middle =
+            "import { base } from \"graph_base\"; export function middle(): number { return base(); }";
+        dir.write("graph_middle.nexa", middle);
+        let mut loader = dir.loader();
+
+        let source =
+            "import { middle } from \"graph_middle\"; import { base } from \"graph_base\";";
+        let entry = dir.write("graph_main.nexa", source);
+        let program = Parser::new(source).parse_program().unwrap();
+        let graph = loader.load_import_graph(&entry.to_string_lossy(), &program).unwrap();
+        let paths: Vec<&str> = graph.modules.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, ["graph_base", "graph_middle"]);
+    }
+
+    #[test]
+    fn test_circular_imports() {
+        let dir = TempDir::new("cycle");
+        let source =
+            "import { b } from \"cycle_b\"; export let count = 1; export function a(): number { return b(); }";
+        let entry = dir.write("cycle_a.nexa", source);
+        let program = Parser::new(source).parse_program().unwrap();
+
+        // 环上只引入函数是允许的
+        let cycle_b = "import { a } from \"cycle_a\"; export function b(): number { return a(); }";
+        dir.write("cycle_b.nexa", cycle_b);
+        let mut loader = dir.loader();
+        let graph = loader.load_import_graph(&entry.to_string_lossy(), &program).unwrap();
+        assert_eq!(graph.entry_path.as_deref(), Some("cycle_a"));
+        assert_eq!(graph.modules.len(), 1);
+
+        // 引入变量时报告整条导入链
+        let cycle_b =
+            "import { count } from \"cycle_a\"; export function b(): number { return count; }";
+        dir.write("cycle_b.nexa", cycle_b);
+        let mut loader = dir.loader();
+        match loader.load_import_graph(&entry.to_string_lossy(), &program) {
+            Err(ModuleError::CircularDependency(steps)) => {
+                let modules: Vec<&str> = steps.iter().map(|s| s.module_path.as_str()).collect();
+                assert_eq!(modules, ["cycle_b", "cycle_a"]);
+            },
+            other => panic!("expected circular dependency, got {:?}", other),
+        }
     }
 }
@@ -3,6 +3,7 @@
 //! 将 Token 流解析为 AST。
 
 use crate::ast::*;
+use crate::captures::analyze_captures;
 use crate::lexer::{Lexer, Token};
 
 /// 解析错误
@@ -12,6 +13,17 @@ pub struct ParseError {
     pub span: Span,
 }
 
+/// 导出语句中附带的声明 (如 export default function foo() {})
+enum Declaration {
+    Function(Function),
+    /// struct 或 class
+    Struct(StructDefinition),
+    Interface(InterfaceDefinition),
+    Enum(EnumDefinition),
+    TypeAlias(TypeAlias),
+    Statement(Statement),
+}
+
 /// 解析器
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
@@ -44,6 +56,11 @@ impl<'a> Parser<'a> {
         &self.current
     }
 
+    /// 查看下一个 Token（不消耗当前 Token）
+    fn peek_next(&self) -> Token {
+        self.lexer.clone().next_token()
+    }
+
     /// 检查当前 Token 是否为指定类型
     fn check(&self, expected: &Token) -> bool {
         match (&self.current, expected) {
@@ -58,7 +75,7 @@ impl<'a> Parser<'a> {
 
     /// 创建 span
     fn span(&self, start: (usize, usize)) -> Span {
-        Span { start, end: self.position }
+        Span { start, end: self.position, id: NodeId::next() }
     }
 
     /// 解析程序
@@ -68,6 +85,8 @@ impl<'a> Parser<'a> {
         let mut statements = Vec::new();
         let mut structs = Vec::new();
         let mut interfaces = Vec::new();
+        let mut enums = Vec::new();
+        let mut type_aliases = Vec::new();
         let mut imports = Vec::new();
         let mut exports = Vec::new();
 
@@ -78,13 +97,31 @@ impl<'a> Parser<'a> {
                     Err(e) => return Err(e),
                 },
                 Token::Export => match self.parse_export_declaration() {
-                    Ok(export) => exports.push(export),
+                    Ok((export, declaration)) => {
+                        match declaration {
+                            Some(Declaration::Function(func)) => functions.push(func),
+                            Some(Declaration::Struct(struct_def)) => {
+                                methods.extend(struct_def.methods.iter().cloned());
+                                structs.push(struct_def);
+                            },
+                            Some(Declaration::Interface(interface)) => interfaces.push(interface),
+                            Some(Declaration::Enum(enum_def)) => enums.push(enum_def),
+                            Some(Declaration::TypeAlias(alias)) => type_aliases.push(alias),
+                            Some(Declaration::Statement(stmt)) => statements.push(stmt),
+                            None => {},
+                        }
+                        exports.push(export);
+                    },
                     Err(e) => return Err(e),
                 },
                 Token::Function => match self.parse_function() {
                     Ok(func) => functions.push(func),
                     Err(e) => return Err(e),
                 },
+                Token::Declare => match self.parse_declare_function() {
+                    Ok(func) => functions.push(func),
+                    Err(e) => return Err(e),
+                },
                 Token::Struct => match self.parse_struct_definition() {
                     Ok(s) => structs.push(s),
                     Err(e) => return Err(e),
@@ -93,6 +130,20 @@ impl<'a> Parser<'a> {
                     Ok(i) => interfaces.push(i),
                     Err(e) => return Err(e),
                 },
+                Token::Enum => match self.parse_enum_definition() {
+                    Ok(e) => enums.push(e),
+                    Err(e) => return Err(e),
+                },
+                Token::Const if self.peek_next() == Token::Enum => {
+                    match self.parse_enum_definition() {
+                        Ok(e) => enums.push(e),
+                        Err(e) => return Err(e),
+                    }
+                },
+                Token::Type => match self.parse_type_alias() {
+                    Ok(alias) => type_aliases.push(alias),
+                    Err(e) => return Err(e),
+                },
                 Token::Class => match self.parse_class_definition() {
                     Ok(c) => {
                         // 提取类中的方法并添加到 methods 列表
@@ -111,7 +162,17 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(Program { functions, methods, structs, interfaces, statements, imports, exports })
+        Ok(Program {
+            functions,
+            methods,
+            structs,
+            interfaces,
+            enums,
+            type_aliases,
+            statements,
+            imports,
+            exports,
+        })
     }
 
     /// 解析导入声明
@@ -133,6 +194,7 @@ impl<'a> Parser<'a> {
                     module_path: path,
                     imports: Vec::new(),
                     alias: None,
+                    default: None,
                     span: self.span(start),
                 })
             },
@@ -172,6 +234,7 @@ impl<'a> Parser<'a> {
                     module_path,
                     imports: Vec::new(),
                     alias: Some(alias),
+                    default: None,
                     span: self.span(start),
                 })
             },
@@ -240,7 +303,13 @@ impl<'a> Parser<'a> {
                 };
                 self.expect_token(&Token::SemiColon)?;
 
-                Ok(ImportDeclaration { module_path, imports, alias: None, span: self.span(start) })
+                Ok(ImportDeclaration {
+                    module_path,
+                    imports,
+                    alias: None,
+                    default: None,
+                    span: self.span(start),
+                })
             },
             Token::Identifier(default_import) => {
                 // 形式: import defaultExport from "module";
@@ -315,7 +384,8 @@ impl<'a> Parser<'a> {
                     Ok(ImportDeclaration {
                         module_path,
                         imports,
-                        alias: Some(default_name),
+                        alias: None,
+                        default: Some(default_name),
                         span: self.span(start),
                     })
                 } else {
@@ -339,7 +409,8 @@ impl<'a> Parser<'a> {
                     Ok(ImportDeclaration {
                         module_path,
                         imports: Vec::new(),
-                        alias: Some(default_name),
+                        alias: None,
+                        default: Some(default_name),
                         span: self.span(start),
                     })
                 }
@@ -355,9 +426,14 @@ impl<'a> Parser<'a> {
     /// 语法: export { foo, bar as baz };
     ///       export default expression;
     ///       export * from "module";
+    ///       export default function foo() {}
     ///       export function foo() {}
     ///       export class Foo {}
-    fn parse_export_declaration(&mut self) -> Result<ExportDeclaration, ParseError> {
+    ///
+    /// 导出语句中的声明 (如 export default 的函数) 一并返回，由调用者加入程序。
+    fn parse_export_declaration(
+        &mut self,
+    ) -> Result<(ExportDeclaration, Option<Declaration>), ParseError> {
         let start = self.position();
         self.expect_token(&Token::Export)?;
 
@@ -411,10 +487,13 @@ impl<'a> Parser<'a> {
                 self.expect_token(&Token::RightBrace)?;
                 self.expect_token(&Token::SemiColon)?;
 
-                Ok(ExportDeclaration {
-                    kind: ExportKind::Named(specifiers),
-                    span: self.span(start),
-                })
+                Ok((
+                    ExportDeclaration {
+                        kind: ExportKind::Named(specifiers),
+                        span: self.span(start),
+                    },
+                    None,
+                ))
             },
             Token::Star => {
                 // 形式: export * from "module";
@@ -435,108 +514,330 @@ impl<'a> Parser<'a> {
                 };
                 self.expect_token(&Token::SemiColon)?;
 
-                Ok(ExportDeclaration {
-                    kind: ExportKind::ReExport(module_path),
-                    span: self.span(start),
-                })
+                Ok((
+                    ExportDeclaration {
+                        kind: ExportKind::ReExport(module_path),
+                        span: self.span(start),
+                    },
+                    None,
+                ))
             },
             Token::Default => {
-                // 形式: export default expression;
+                // 形式: export default function foo() {} / export default class Foo {}
+                //       export default expression;
                 self.advance();
-                let expr = self.parse_expression()?;
-                self.expect_token(&Token::SemiColon)?;
-
-                Ok(ExportDeclaration { kind: ExportKind::Default(expr), span: self.span(start) })
-            },
-            Token::Function => {
-                // 形式: export function foo() {}
-                self.advance();
-                let func = self.parse_function()?;
-                // 将函数添加到函数列表中
-                // 注意: 这里需要修改调用者来处理
-                // 为了简化，我们这里返回导出声明，实际函数已经通过 parse_function 添加
-                Ok(ExportDeclaration {
-                    kind: ExportKind::Named(vec![ExportSpecifier {
-                        name: func.name.clone(),
-                        alias: None,
-                    }]),
-                    span: self.span(start),
-                })
+                let (name, declaration) = match self.peek() {
+                    Token::Function if matches!(self.peek_next(), Token::Identifier(_)) => {
+                        let func = self.parse_function()?;
+                        (func.name.clone(), Some(Declaration::Function(func)))
+                    },
+                    Token::Class => {
+                        let class = self.parse_class_definition()?;
+                        (class.name.clone(), Some(Declaration::Struct(class)))
+                    },
+                    _ => {
+                        let expr_start = self.position();
+                        let expr = self.parse_expression()?;
+                        self.expect_token(&Token::SemiColon)?;
+                        match expr {
+                            Expression::Identifier(name, _) => (name, None),
+                            // 字面量保存到名为 default 的模块常量中，导入方按字面量的类型声明它
+                            expr if expr.literal_type().is_some() => {
+                                let stmt = Statement::VariableDeclaration {
+                                    name: "default".to_string(),
+                                    type_annotation: None,
+                                    initializer: Some(expr),
+                                    mutable: false,
+                                    span: self.span(expr_start),
+                                };
+                                ("default".to_string(), Some(Declaration::Statement(stmt)))
+                            },
+                            // 其他表达式的类型要到类型检查时才知道，导入方无法声明它
+                            _ => {
+                                return Err(ParseError {
+                                    message: "'export default' expects a declaration, a name or \
+                                              a literal; declare the value with a type annotation \
+                                              and export its name"
+                                        .to_string(),
+                                    span: self.span(expr_start),
+                                });
+                            },
+                        }
+                    },
+                };
+                let span = self.span(start);
+                Ok((ExportDeclaration { kind: ExportKind::Default(name), span }, declaration))
             },
-            Token::Class => {
-                // 形式: export class Foo {}
-                self.advance();
-                let class_name = match self.peek() {
-                    Token::Identifier(name) => {
-                        let n = name.clone();
-                        self.advance();
-                        n
+            Token::Function
+            | Token::Declare
+            | Token::Class
+            | Token::Struct
+            | Token::Interface
+            | Token::Enum
+            | Token::Type
+            | Token::Const
+            | Token::Let => {
+                // 形式: export function foo() {} / export class Foo {} / export const foo = 1;
+                let (name, declaration) = match self.peek() {
+                    Token::Function => {
+                        let func = self.parse_function()?;
+                        (func.name.clone(), Declaration::Function(func))
+                    },
+                    Token::Declare => {
+                        let func = self.parse_declare_function()?;
+                        (func.name.clone(), Declaration::Function(func))
+                    },
+                    Token::Class => {
+                        let class = self.parse_class_definition()?;
+                        (class.name.clone(), Declaration::Struct(class))
+                    },
+                    Token::Struct => {
+                        let struct_def = self.parse_struct_definition()?;
+                        (struct_def.name.clone(), Declaration::Struct(struct_def))
+                    },
+                    Token::Interface => {
+                        let interface = self.parse_interface_definition()?;
+                        (interface.name.clone(), Declaration::Interface(interface))
+                    },
+                    Token::Enum => {
+                        let enum_def = self.parse_enum_definition()?;
+                        (enum_def.name.clone(), Declaration::Enum(enum_def))
+                    },
+                    Token::Const if self.peek_next() == Token::Enum => {
+                        let enum_def = self.parse_enum_definition()?;
+                        (enum_def.name.clone(), Declaration::Enum(enum_def))
+                    },
+                    Token::Type => {
+                        let alias = self.parse_type_alias()?;
+                        (alias.name.clone(), Declaration::TypeAlias(alias))
                     },
                     _ => {
-                        return Err(ParseError {
-                            message: "Expected class name".to_string(),
-                            span: self.span(start),
-                        });
+                        let stmt = self.parse_statement()?;
+                        let Statement::VariableDeclaration { name, .. } = &stmt else {
+                            return Err(ParseError {
+                                message: "Expected variable declaration".to_string(),
+                                span: self.span(start),
+                            });
+                        };
+                        (name.clone(), Declaration::Statement(stmt))
                     },
                 };
-                // 暂时跳过类主体的解析
-                self.expect_token(&Token::LeftBrace)?;
-                while *self.peek() != Token::RightBrace && *self.peek() != Token::Eof {
-                    self.advance();
-                }
-                self.expect_token(&Token::RightBrace)?;
-                self.expect_token(&Token::SemiColon)?;
+                let kind = ExportKind::Named(vec![ExportSpecifier { name, alias: None }]);
+                Ok((ExportDeclaration { kind, span: self.span(start) }, Some(declaration)))
+            },
+            _ => Err(ParseError {
+                message: "Invalid export syntax".to_string(),
+                span: self.span(start),
+            }),
+        }
+    }
 
-                Ok(ExportDeclaration {
-                    kind: ExportKind::Named(vec![ExportSpecifier {
-                        name: class_name,
-                        alias: None,
-                    }]),
+    /// 解析枚举定义: [const] enum Name { A, B = 5, C = "c" }
+    ///
+    /// 没有初始化表达式的成员取上一个数值成员加一，第一个成员为 0；
+    /// 字符串成员之后的成员必须显式初始化。
+    fn parse_enum_definition(&mut self) -> Result<EnumDefinition, ParseError> {
+        let start = self.position();
+        let is_const = *self.peek() == Token::Const;
+        if is_const {
+            self.advance();
+        }
+        self.expect_token(&Token::Enum)?;
+
+        let name = match self.peek() {
+            Token::Identifier(name) => name.clone(),
+            _ => {
+                return Err(ParseError {
+                    message: "Expected enum name".to_string(),
                     span: self.span(start),
-                })
+                });
             },
-            Token::Const | Token::Let => {
-                // 形式: export const foo: number = 1;
-                let _var_token = self.peek().clone();
+        };
+        self.advance();
+        self.expect_token(&Token::LeftBrace)?;
+
+        let mut members: Vec<EnumMember> = Vec::new();
+        // 下一个未初始化成员的值，字符串成员之后为 None
+        let mut next_value = Some(0);
+        while *self.peek() != Token::RightBrace {
+            let member_start = self.position();
+            let member_name = match self.peek() {
+                Token::Identifier(name) => name.clone(),
+                _ => {
+                    return Err(ParseError {
+                        message: "Expected enum member name".to_string(),
+                        span: self.span(member_start),
+                    });
+                },
+            };
+            self.advance();
+            if members.iter().any(|m| m.name == member_name) {
+                return Err(ParseError {
+                    message: format!("Duplicate enum member '{}'", member_name),
+                    span: self.span(member_start),
+                });
+            }
+
+            let value = if *self.peek() == Token::Equals {
                 self.advance();
-                let var_name = match self.peek() {
-                    Token::Identifier(name) => {
-                        let n = name.clone();
-                        self.advance();
-                        n
-                    },
-                    _ => {
+                self.parse_enum_value()?
+            } else {
+                match next_value {
+                    Some(value) => EnumValue::Number(value),
+                    None => {
                         return Err(ParseError {
-                            message: "Expected variable name".to_string(),
-                            span: self.span(start),
+                            message: format!("Enum member '{}' must have initializer", member_name),
+                            span: self.span(member_start),
                         });
                     },
-                };
-                // 处理类型注解 (如果有)
-                if *self.peek() == Token::Colon {
-                    self.advance();
-                    // 跳过类型
-                    self.parse_type()?;
                 }
-                // 跳过 = 表达式
-                if *self.peek() == Token::Equals {
-                    self.advance();
-                    // 跳过表达式直到分号
-                    while *self.peek() != Token::SemiColon && *self.peek() != Token::Eof {
-                        self.advance();
-                    }
-                }
-                self.expect_token(&Token::SemiColon)?;
+            };
+            next_value = match &value {
+                EnumValue::Number(n) => Some(n + 1),
+                EnumValue::String(_) => None,
+            };
+            members.push(EnumMember { name: member_name, value, span: self.span(member_start) });
 
-                Ok(ExportDeclaration {
-                    kind: ExportKind::Named(vec![ExportSpecifier { name: var_name, alias: None }]),
+            if *self.peek() == Token::Comma {
+                self.advance();
+            } else if *self.peek() != Token::RightBrace {
+                return Err(ParseError {
+                    message: "Expected ',' or '}' in enum".to_string(),
+                    span: self.span(self.position()),
+                });
+            }
+        }
+        self.expect_token(&Token::RightBrace)?;
+
+        Ok(EnumDefinition { name, members, is_const, span: self.span(start) })
+    }
+
+    /// 解析枚举成员的初始化表达式 (数字或字符串字面量)
+    fn parse_enum_value(&mut self) -> Result<EnumValue, ParseError> {
+        let start = self.position();
+        let negative = *self.peek() == Token::Minus;
+        if negative {
+            self.advance();
+        }
+        let value = match self.peek().clone() {
+            Token::Number(n) => EnumValue::Number(if negative { -n } else { n }),
+            Token::StringLiteral(value) if !negative => EnumValue::String(value),
+            _ => {
+                return Err(ParseError {
+                    message: "Enum member initializer must be a number or string literal"
+                        .to_string(),
                     span: self.span(start),
+                });
+            },
+        };
+        self.advance();
+        Ok(value)
+    }
+
+    /// 解析类型别名: type Name = T;
+    fn parse_type_alias(&mut self) -> Result<TypeAlias, ParseError> {
+        let start = self.position();
+        self.expect_token(&Token::Type)?;
+
+        let name = match self.peek() {
+            Token::Identifier(name) => name.clone(),
+            _ => {
+                return Err(ParseError {
+                    message: "Expected type alias name".to_string(),
+                    span: self.span(start),
+                });
+            },
+        };
+        self.advance();
+        self.expect_token(&Token::Equals)?;
+        let ty = self.parse_type()?;
+        self.expect_token(&Token::SemiColon)?;
+
+        Ok(TypeAlias { name, ty, span: self.span(start) })
+    }
+
+    /// 解析可选的类型参数列表 `<T, U extends Shape>`
+    fn parse_type_parameters(&mut self) -> Result<Vec<TypeParameter>, ParseError> {
+        let mut parameters = Vec::new();
+        if *self.peek() != Token::LessThan {
+            return Ok(parameters);
+        }
+        self.advance();
+        loop {
+            let Token::Identifier(name) = self.peek().clone() else {
+                return Err(ParseError {
+                    message: format!("Expected type parameter name, got {:?}", self.peek()),
+                    span: self.span(self.position()),
+                });
+            };
+            self.advance();
+            let constraint = if *self.peek() == Token::Extends {
+                self.advance();
+                Some(self.parse_type()?)
+            } else {
+                None
+            };
+            parameters.push(TypeParameter { name, constraint });
+            if *self.peek() == Token::Comma {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.expect_type_arguments_end()?;
+        Ok(parameters)
+    }
+
+    /// 解析类型实参列表 `<number, string>`
+    fn parse_type_arguments(&mut self) -> Result<Vec<Type>, ParseError> {
+        self.expect_token(&Token::LessThan)?;
+        let mut arguments = vec![self.parse_type()?];
+        while *self.peek() == Token::Comma {
+            self.advance();
+            arguments.push(self.parse_type()?);
+        }
+        self.expect_type_arguments_end()?;
+        Ok(arguments)
+    }
+
+    /// 消耗类型参数列表结尾的 `>`
+    ///
+    /// 嵌套的泛型 `Box<Box<number>>` 结尾被词法分析为 `>>`，此时只消耗其中一个 `>`。
+    fn expect_type_arguments_end(&mut self) -> Result<(), ParseError> {
+        let rest = match self.peek() {
+            Token::GreaterThan => None,
+            Token::RightShift => Some(Token::GreaterThan),
+            Token::UnsignedRightShift => Some(Token::RightShift),
+            Token::GreaterThanOrEqual => Some(Token::Equals),
+            Token::RightShiftEquals => Some(Token::GreaterThanOrEqual),
+            _ => {
+                return Err(ParseError {
+                    message: format!("Expected '>', got {:?}", self.peek()),
+                    span: self.span(self.position()),
                 })
             },
-            _ => Err(ParseError {
-                message: "Invalid export syntax".to_string(),
-                span: self.span(start),
-            }),
+        };
+        match rest {
+            Some(token) => self.current = token,
+            None => self.advance(),
+        }
+        Ok(())
+    }
+
+    /// 表达式中尝试解析类型实参 `f<number>(x)`
+    ///
+    /// 只有类型实参列表之后紧跟 `(` 或 `{` 时才视为类型实参，否则恢复解析位置，
+    /// `<` 按小于运算符处理。
+    fn try_parse_type_arguments(&mut self) -> Option<Vec<Type>> {
+        let saved = (self.lexer.clone(), self.current.clone(), self.position);
+        match self.parse_type_arguments() {
+            Ok(arguments) if matches!(self.peek(), Token::LeftParen | Token::LeftBrace) => {
+                Some(arguments)
+            },
+            _ => {
+                (self.lexer, self.current, self.position) = saved;
+                None
+            },
         }
     }
 
@@ -558,6 +859,7 @@ impl<'a> Parser<'a> {
                 });
             },
         };
+        let type_parameters = self.parse_type_parameters()?;
 
         self.expect_token(&Token::LeftBrace)?;
 
@@ -628,7 +930,7 @@ impl<'a> Parser<'a> {
 
         self.expect_token(&Token::RightBrace)?;
 
-        Ok(InterfaceDefinition { name, fields, methods, span: self.span(start) })
+        Ok(InterfaceDefinition { name, type_parameters, fields, methods, span: self.span(start) })
     }
 
     /// 解析结构体定义
@@ -646,6 +948,7 @@ impl<'a> Parser<'a> {
             },
         };
         self.advance();
+        let type_parameters = self.parse_type_parameters()?;
 
         self.expect_token(&Token::LeftBrace)?;
 
@@ -677,10 +980,13 @@ impl<'a> Parser<'a> {
 
         Ok(StructDefinition {
             name,
+            type_parameters,
             fields,
             methods: Vec::new(),
             constructor: None,
             implements: Vec::new(),
+            extends: None,
+            is_class: false,
             span: self.span(start),
         })
     }
@@ -700,16 +1006,37 @@ impl<'a> Parser<'a> {
             },
         };
         self.advance();
+        let type_parameters = self.parse_type_parameters()?;
+
+        // 处理 extends 子句 (可选，单继承)
+        let extends = if *self.peek() == Token::Extends {
+            self.advance();
+            match self.peek() {
+                Token::Identifier(name) => {
+                    let name = name.clone();
+                    self.advance();
+                    Some(name)
+                },
+                _ => {
+                    return Err(ParseError {
+                        message: "Expected base class name after extends".to_string(),
+                        span: self.span(self.position()),
+                    })
+                },
+            }
+        } else {
+            None
+        };
 
         // 处理 implements 子句 (可选)
-        let implements_interfaces: Vec<String> = if *self.peek() == Token::Implements {
+        let implements_interfaces: Vec<Type> = if *self.peek() == Token::Implements {
             self.advance();
             let mut interfaces = Vec::new();
             loop {
                 match self.peek() {
-                    Token::Identifier(name) => {
-                        interfaces.push(name.clone());
-                        self.advance();
+                    Token::Identifier(_) => {
+                        // 泛型接口带类型实参 implements Container<number>
+                        interfaces.push(self.parse_primary_type()?);
                     },
                     _ => {
                         return Err(ParseError {
@@ -729,8 +1056,6 @@ impl<'a> Parser<'a> {
             Vec::new()
         };
 
-        let _ = implements_interfaces;
-
         self.expect_token(&Token::LeftBrace)?;
 
         let mut fields = Vec::new();
@@ -781,16 +1106,18 @@ impl<'a> Parser<'a> {
 
                     constructor = Some(Function {
                         name: format!("{}_constructor", name),
+                        type_parameters: Vec::new(),
                         parameters: params,
                         return_type,
                         body,
                         is_variadic: false,
+                        is_declare: false,
                         span: self.span(start),
                     });
                 },
-                Token::Identifier(method_name) => {
+                Token::Identifier(member_name) => {
                     // 可能是方法或字段
-                    let name = method_name.clone();
+                    let member_name = member_name.clone();
                     self.advance();
 
                     let peek_token = self.peek().clone();
@@ -827,18 +1154,20 @@ impl<'a> Parser<'a> {
                         let body = self.parse_block()?;
 
                         methods.push(Function {
-                            name: format!("{}_{}", name, name),
+                            name: format!("{}_{}", name, member_name),
+                            type_parameters: Vec::new(),
                             parameters: params,
                             return_type,
                             body,
                             is_variadic: false,
+                            is_declare: false,
                             span: self.span(start),
                         });
                     } else if peek_token == Token::Colon {
                         // 这是一个字段
                         self.advance();
                         let field_type = self.parse_type()?;
-                        fields.push(StructField { name, field_type });
+                        fields.push(StructField { name: member_name, field_type });
 
                         if *self.peek() == Token::Comma {
                             self.advance();
@@ -863,16 +1192,69 @@ impl<'a> Parser<'a> {
 
         Ok(StructDefinition {
             name,
+            type_parameters,
             fields,
             methods,
             constructor,
+            extends,
             implements: implements_interfaces,
+            is_class: true,
             span: self.span(start),
         })
     }
 
     /// 解析函数
     fn parse_function(&mut self) -> Result<Function, ParseError> {
+        let start = self.position();
+        let (name, type_parameters, parameters, return_type) = self.parse_function_signature()?;
+
+        // 函数体
+        let body = self.parse_block()?;
+
+        Ok(Function {
+            name,
+            type_parameters,
+            parameters,
+            return_type,
+            body,
+            span: self.span(start),
+            is_variadic: false,
+            is_declare: false,
+        })
+    }
+
+    /// 解析外部函数声明: declare function name(params): type;
+    fn parse_declare_function(&mut self) -> Result<Function, ParseError> {
+        let start = self.position();
+        self.expect_token(&Token::Declare)?;
+        if *self.peek() != Token::Function {
+            return Err(ParseError {
+                message: "Expected 'function' after 'declare'".to_string(),
+                span: self.span(start),
+            });
+        }
+        let (name, type_parameters, parameters, return_type) = self.parse_function_signature()?;
+        if *self.peek() == Token::SemiColon {
+            self.advance();
+        }
+
+        let span = self.span(start);
+        Ok(Function {
+            name,
+            type_parameters,
+            parameters,
+            return_type,
+            body: Statement::Block(Vec::new(), span.clone()),
+            span,
+            is_variadic: false,
+            is_declare: true,
+        })
+    }
+
+    /// 解析函数名、类型参数、参数列表和返回类型
+    fn parse_function_signature(
+        &mut self,
+    ) -> Result<(String, Vec<TypeParameter>, Vec<Parameter>, Type), ParseError> {
         let start = self.position();
         self.advance(); // 跳过 function
 
@@ -886,6 +1268,7 @@ impl<'a> Parser<'a> {
             },
         };
         self.advance();
+        let type_parameters = self.parse_type_parameters()?;
 
         // 参数列表
         self.expect_token(&Token::LeftParen)?;
@@ -921,21 +1304,37 @@ impl<'a> Parser<'a> {
             Type::Void
         };
 
-        // 函数体
-        let body = self.parse_block()?;
-
-        Ok(Function {
-            name,
-            parameters,
-            return_type,
-            body,
-            span: self.span(start),
-            is_variadic: false,
-        })
+        Ok((name, type_parameters, parameters, return_type))
     }
 
     /// 解析类型 (TypeScript 风格)
+    ///
+    /// 优先级从低到高: 联合 `A | B`，交叉 `A & B`，基本类型 (含后缀 `T[]`)。
     fn parse_type(&mut self) -> Result<Type, ParseError> {
+        // 允许前导 `|`，便于多行书写联合类型
+        if *self.peek() == Token::Pipe {
+            self.advance();
+        }
+        let mut types = vec![self.parse_intersection_type()?];
+        while *self.peek() == Token::Pipe {
+            self.advance();
+            types.push(self.parse_intersection_type()?);
+        }
+        Ok(if types.len() == 1 { types.remove(0) } else { Type::Union(types) })
+    }
+
+    /// 解析交叉类型 `A & B`
+    fn parse_intersection_type(&mut self) -> Result<Type, ParseError> {
+        let mut types = vec![self.parse_primary_type()?];
+        while *self.peek() == Token::Ampersand {
+            self.advance();
+            types.push(self.parse_primary_type()?);
+        }
+        Ok(if types.len() == 1 { types.remove(0) } else { Type::Intersection(types) })
+    }
+
+    /// 解析基本类型
+    fn parse_primary_type(&mut self) -> Result<Type, ParseError> {
         let start = self.position();
         let mut ty = match self.peek() {
             Token::NumberType => {
@@ -969,9 +1368,45 @@ impl<'a> Parser<'a> {
             Token::Identifier(type_name) => {
                 // 检查内置类型别名
                 match type_name.as_str() {
-                    "i32" | "i64" | "i16" | "i8" | "u32" | "u64" | "u16" | "u8" | "f32" | "f64" => {
+                    "i8" => {
+                        self.advance();
+                        Type::I8
+                    },
+                    "i16" => {
                         self.advance();
-                        Type::Number
+                        Type::I16
+                    },
+                    "i32" => {
+                        self.advance();
+                        Type::I32
+                    },
+                    "i64" => {
+                        self.advance();
+                        Type::I64
+                    },
+                    "u8" => {
+                        self.advance();
+                        Type::U8
+                    },
+                    "u16" => {
+                        self.advance();
+                        Type::U16
+                    },
+                    "u32" => {
+                        self.advance();
+                        Type::U32
+                    },
+                    "u64" => {
+                        self.advance();
+                        Type::U64
+                    },
+                    "f32" => {
+                        self.advance();
+                        Type::F32
+                    },
+                    "f64" => {
+                        self.advance();
+                        Type::F64
                     },
                     "bool" => {
                         self.advance();
@@ -982,10 +1417,14 @@ impl<'a> Parser<'a> {
                         Type::String
                     },
                     _ => {
-                        // 自定义类型名（struct 名称）
+                        // 自定义类型名（struct 名称），带类型实参时为泛型实例 Box<number>
                         let name = type_name.clone();
                         self.advance();
-                        Type::Struct(name)
+                        if *self.peek() == Token::LessThan {
+                            Type::Generic(name, self.parse_type_arguments()?)
+                        } else {
+                            Type::Struct(name)
+                        }
                     },
                 }
             },
@@ -993,15 +1432,47 @@ impl<'a> Parser<'a> {
                 // 数组类型 T[]
                 self.advance();
                 self.expect_token(&Token::RightBracket)?;
-                let element_type = self.parse_type()?;
+                let element_type = self.parse_primary_type()?;
                 return Ok(Type::Array(Box::new(element_type)));
             },
             Token::Star => {
                 // 指针类型 *T
                 self.advance();
-                let pointee_type = self.parse_type()?;
+                let pointee_type = self.parse_primary_type()?;
                 return Ok(Type::Pointer(Box::new(pointee_type)));
             },
+            Token::LeftParen if self.is_function_type_start() => {
+                // 函数类型 (x: number, y: string) => boolean
+                return self.parse_function_type();
+            },
+            Token::LeftParen => {
+                // 括号类型 (A | B)[]
+                self.advance();
+                let inner = self.parse_type()?;
+                self.expect_token(&Token::RightParen)?;
+                inner
+            },
+            Token::StringLiteral(value) => {
+                let literal = LiteralType::String(value.clone());
+                self.advance();
+                Type::Literal(literal)
+            },
+            Token::Number(value) => {
+                let literal = LiteralType::Number(*value);
+                self.advance();
+                Type::Literal(literal)
+            },
+            Token::Minus if matches!(self.peek_next(), Token::Number(_)) => {
+                self.advance();
+                let Token::Number(value) = *self.peek() else { unreachable!() };
+                self.advance();
+                Type::Literal(LiteralType::Number(-value))
+            },
+            Token::Boolean(value) => {
+                let literal = LiteralType::Boolean(*value);
+                self.advance();
+                Type::Literal(literal)
+            },
             _ => {
                 return Err(ParseError {
                     message: "Expected type".to_string(),
@@ -1020,6 +1491,63 @@ impl<'a> Parser<'a> {
         Ok(ty)
     }
 
+    /// 判断当前的 `(` 是否开始一个函数类型 (匹配的 `)` 之后紧跟 `=>`)
+    fn is_function_type_start(&self) -> bool {
+        let mut lexer = self.lexer.clone();
+        let mut depth = 1;
+        loop {
+            match lexer.next_token() {
+                Token::LeftParen => depth += 1,
+                Token::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return lexer.next_token() == Token::EqualsGreaterThan;
+                    }
+                },
+                Token::Eof => return false,
+                _ => {},
+            }
+        }
+    }
+
+    /// 解析函数类型 `(x: number, y: string) => boolean`
+    /// 参数名只用于可读性，省略类型的参数为 any
+    fn parse_function_type(&mut self) -> Result<Type, ParseError> {
+        self.expect_token(&Token::LeftParen)?;
+        let mut params = Vec::new();
+        while *self.peek() != Token::RightParen {
+            match self.peek() {
+                Token::Identifier(_) => self.advance(),
+                _ => {
+                    return Err(ParseError {
+                        message: format!("Expected parameter name, got {:?}", self.peek()),
+                        span: self.span(self.position()),
+                    })
+                },
+            }
+            let param_type = if *self.peek() == Token::Colon {
+                self.advance();
+                self.parse_type()?
+            } else {
+                Type::Any
+            };
+            params.push(param_type);
+
+            if *self.peek() == Token::Comma {
+                self.advance();
+            } else if *self.peek() != Token::RightParen {
+                return Err(ParseError {
+                    message: format!("Expected ',' or ')', got {:?}", self.peek()),
+                    span: self.span(self.position()),
+                });
+            }
+        }
+        self.expect_token(&Token::RightParen)?;
+        self.expect_token(&Token::EqualsGreaterThan)?;
+        let return_type = self.parse_type()?;
+        Ok(Type::Function(params, Box::new(return_type)))
+    }
+
     /// 解析语句
     fn parse_statement(&mut self) -> Result<Statement, ParseError> {
         let current_token = self.peek().clone();
@@ -1037,12 +1565,17 @@ impl<'a> Parser<'a> {
             Token::Break => {
                 let start = self.position();
                 self.advance();
-                Ok(Statement::Break(self.span(start)))
+                let label = self.parse_jump_label();
+                Ok(Statement::Break { label, span: self.span(start) })
             },
             Token::Continue => {
                 let start = self.position();
                 self.advance();
-                Ok(Statement::Continue(self.span(start)))
+                let label = self.parse_jump_label();
+                Ok(Statement::Continue { label, span: self.span(start) })
+            },
+            Token::Identifier(_) if self.peek_next() == Token::Colon => {
+                self.parse_labeled_statement()
             },
             Token::LeftBrace => self.parse_block_statement(),
             Token::SemiColon => {
@@ -1054,6 +1587,51 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// 解析 break / continue 后的可选标签和分号
+    ///
+    /// 与 TS 的自动分号插入一致，标签必须与关键字在同一行，下一行的标识符属于下一条语句。
+    fn parse_jump_label(&mut self) -> Option<String> {
+        let label = match self.peek() {
+            // 标识符不跨行，它结束的行即所在的行；self.position 是关键字结束的位置
+            Token::Identifier(name) if self.lexer.position().0 == self.position.0 => {
+                let name = name.clone();
+                self.advance();
+                Some(name)
+            },
+            _ => None,
+        };
+        if *self.peek() == Token::SemiColon {
+            self.advance();
+        }
+        label
+    }
+
+    /// 解析带标签的语句 (label: for (...) { ... })
+    /// 标签只能用于循环语句
+    fn parse_labeled_statement(&mut self) -> Result<Statement, ParseError> {
+        let start = self.position();
+        let name = match self.peek() {
+            Token::Identifier(name) => name.clone(),
+            _ => unreachable!(),
+        };
+        self.advance(); // 跳过标签名
+        self.expect_token(&Token::Colon)?;
+
+        let mut statement = self.parse_statement()?;
+        match &mut statement {
+            Statement::While { label, .. }
+            | Statement::For { label, .. }
+            | Statement::DoWhile { label, .. } => *label = Some(name),
+            _ => {
+                return Err(ParseError {
+                    message: format!("Label '{}' must be followed by a loop statement", name),
+                    span: self.span(start),
+                })
+            },
+        }
+        Ok(statement)
+    }
+
     /// 解析块语句
     fn parse_block(&mut self) -> Result<Statement, ParseError> {
         let start = self.position();
@@ -1080,13 +1658,13 @@ impl<'a> Parser<'a> {
     fn parse_variable_declaration(&mut self) -> Result<Statement, ParseError> {
         let start = self.position();
         let mutable = match self.peek() {
-            Token::Let => {
+            Token::Let | Token::Var => {
                 self.advance();
-                false
+                true
             },
             Token::Const => {
                 self.advance();
-                true
+                false
             },
             _ => unreachable!(),
         };
@@ -1161,7 +1739,7 @@ impl<'a> Parser<'a> {
 
         let body = Box::new(self.parse_statement()?);
 
-        Ok(Statement::While { condition, body, span: self.span(start) })
+        Ok(Statement::While { label: None, condition, body, span: self.span(start) })
     }
 
     /// 解析 for 语句
@@ -1184,7 +1762,14 @@ impl<'a> Parser<'a> {
 
         let body = Box::new(self.parse_statement()?);
 
-        Ok(Statement::For { initializer, condition, update, body, span: self.span(start) })
+        Ok(Statement::For {
+            label: None,
+            initializer,
+            condition,
+            update,
+            body,
+            span: self.span(start),
+        })
     }
 
     /// 解析 switch 语句
@@ -1192,7 +1777,15 @@ impl<'a> Parser<'a> {
         let start = self.position();
         self.advance(); // 跳过 switch
 
-        let value = Box::new(self.parse_expression()?);
+        // 括号中的值单独解析，避免 switch (c) { 中的标识符与 { 组成 struct 字面量
+        let value = if *self.peek() == Token::LeftParen {
+            self.advance();
+            let value = self.parse_expression()?;
+            self.expect_token(&Token::RightParen)?;
+            Box::new(value)
+        } else {
+            Box::new(self.parse_expression()?)
+        };
 
         self.expect_token(&Token::LeftBrace)?;
 
@@ -1222,11 +1815,27 @@ impl<'a> Parser<'a> {
                 self.advance();
                 SwitchPattern::Number(num)
             },
+            Token::StringLiteral(value) => {
+                let value = value.clone();
+                self.advance();
+                SwitchPattern::String(value)
+            },
             Token::Identifier(name) => {
                 let name_clone = name.clone();
                 self.advance();
-                // 检查是否是通配符 _
-                if name_clone == "_" {
+                if *self.peek() == Token::Dot {
+                    // 枚举成员: Color.Red
+                    self.advance();
+                    let Token::Identifier(member) = self.peek().clone() else {
+                        return Err(ParseError {
+                            message: "Expected enum member name".to_string(),
+                            span: self.span(start),
+                        });
+                    };
+                    self.advance();
+                    SwitchPattern::EnumMember(name_clone, member)
+                } else if name_clone == "_" {
+                    // 通配符 _
                     SwitchPattern::Wildcard
                 } else if name_clone == "default" {
                     SwitchPattern::Default
@@ -1295,7 +1904,7 @@ impl<'a> Parser<'a> {
             self.advance();
         }
 
-        Ok(Statement::DoWhile { body, condition, span: self.span(start) })
+        Ok(Statement::DoWhile { label: None, body, condition, span: self.span(start) })
     }
 
     /// 解析 throw 语句
@@ -1657,7 +2266,7 @@ impl<'a> Parser<'a> {
     /// 解析乘除运算
     fn parse_multiplication(&mut self) -> Result<Expression, ParseError> {
         let start = self.position();
-        let mut left = self.parse_unary()?;
+        let mut left = self.parse_cast()?;
 
         while let Token::Star | Token::Slash | Token::Percent | Token::Exponent =
             self.peek().clone()
@@ -1670,7 +2279,7 @@ impl<'a> Parser<'a> {
                 _ => unreachable!(),
             };
             self.advance();
-            let right = self.parse_unary()?;
+            let right = self.parse_cast()?;
             left = Expression::Binary {
                 op,
                 left: Box::new(left),
@@ -1682,6 +2291,20 @@ impl<'a> Parser<'a> {
         Ok(left)
     }
 
+    /// 解析类型转换 `expr as u8`，优先级高于乘除、低于一元运算
+    fn parse_cast(&mut self) -> Result<Expression, ParseError> {
+        let start = self.position();
+        let mut expr = self.parse_unary()?;
+
+        while let Token::As = self.peek() {
+            self.advance();
+            let target = self.parse_type()?;
+            expr = Expression::Cast { expression: Box::new(expr), target, span: self.span(start) };
+        }
+
+        Ok(expr)
+    }
+
     /// 解析一元运算
     fn parse_unary(&mut self) -> Result<Expression, ParseError> {
         let start = self.position();
@@ -1704,6 +2327,12 @@ impl<'a> Parser<'a> {
                     },
                 };
 
+                let type_arguments = if *self.peek() == Token::LessThan {
+                    self.parse_type_arguments()?
+                } else {
+                    Vec::new()
+                };
+
                 // 解析构造函数的参数
                 self.expect_token(&Token::LeftParen)?;
                 let mut args = Vec::new();
@@ -1715,7 +2344,7 @@ impl<'a> Parser<'a> {
                 }
                 self.expect_token(&Token::RightParen)?;
 
-                Ok(Expression::New { type_name, args, span: self.span(start) })
+                Ok(Expression::New { type_name, type_arguments, args, span: self.span(start) })
             },
             Token::Minus => {
                 self.advance();
@@ -1775,21 +2404,44 @@ impl<'a> Parser<'a> {
         loop {
             match self.peek() {
                 Token::LeftParen => {
-                    self.advance();
-                    let mut arguments = Vec::new();
-                    while *self.peek() != Token::RightParen {
-                        arguments.push(self.parse_expression()?);
-                        if *self.peek() == Token::Comma {
-                            self.advance();
-                        }
-                    }
-                    self.advance();
+                    let arguments = self.parse_call_arguments()?;
                     expr = Expression::Call {
                         callee: Box::new(expr),
+                        type_arguments: Vec::new(),
                         arguments,
                         span: self.span(start),
                     };
                 },
+                Token::LessThan
+                    if matches!(expr, Expression::Identifier(..) | Expression::Member { .. }) =>
+                {
+                    // 显式类型实参 identity<number>(x) 或 Box<number> { value: 1 }
+                    let Some(type_arguments) = self.try_parse_type_arguments() else {
+                        break;
+                    };
+                    if *self.peek() == Token::LeftParen {
+                        let arguments = self.parse_call_arguments()?;
+                        expr = Expression::Call {
+                            callee: Box::new(expr),
+                            type_arguments,
+                            arguments,
+                            span: self.span(start),
+                        };
+                    } else if let Expression::Identifier(type_name, _) = &expr {
+                        let fields = self.parse_struct_literal_fields()?;
+                        expr = Expression::StructLiteral {
+                            name: type_name.clone(),
+                            type_arguments,
+                            fields,
+                            span: self.span(start),
+                        };
+                    } else {
+                        return Err(ParseError {
+                            message: "Expected '(' after type arguments".to_string(),
+                            span: self.span(start),
+                        });
+                    }
+                },
                 Token::LeftBracket => {
                     self.advance();
                     let index = self.parse_expression()?;
@@ -1822,30 +2474,11 @@ impl<'a> Parser<'a> {
                     // 可能是 struct 字面量 { field: value, ... }
                     // 只有当 expr 是标识符（类型名）时才处理
                     if let Expression::Identifier(type_name, _) = &expr {
-                        // 检查是否是 struct 字面量
-                        self.advance(); // 跳过 {
-                        let mut fields = Vec::new();
-                        while *self.peek() != Token::RightBrace {
-                            let field_name = match self.peek() {
-                                Token::Identifier(name) => name.clone(),
-                                _ => {
-                                    return Err(ParseError {
-                                        message: "Expected field name".to_string(),
-                                        span: self.span(self.position()),
-                                    })
-                                },
-                            };
-                            self.advance();
-                            self.expect_token(&Token::Colon)?;
-                            let field_value = self.parse_expression()?;
-                            fields.push((field_name, field_value));
-                            if *self.peek() == Token::Comma {
-                                self.advance();
-                            }
-                        }
-                        self.expect_token(&Token::RightBrace)?;
+                        let type_name = type_name.clone();
+                        let fields = self.parse_struct_literal_fields()?;
                         expr = Expression::StructLiteral {
-                            name: type_name.clone(),
+                            name: type_name,
+                            type_arguments: Vec::new(),
                             fields,
                             span: self.span(start),
                         };
@@ -1861,6 +2494,46 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    /// 解析调用实参列表 `(a, b)`
+    fn parse_call_arguments(&mut self) -> Result<Vec<Expression>, ParseError> {
+        self.expect_token(&Token::LeftParen)?;
+        let mut arguments = Vec::new();
+        while *self.peek() != Token::RightParen {
+            arguments.push(self.parse_expression()?);
+            if *self.peek() == Token::Comma {
+                self.advance();
+            }
+        }
+        self.advance();
+        Ok(arguments)
+    }
+
+    /// 解析 struct 字面量的字段列表 `{ field: value, ... }`
+    fn parse_struct_literal_fields(&mut self) -> Result<Vec<(String, Expression)>, ParseError> {
+        self.expect_token(&Token::LeftBrace)?;
+        let mut fields = Vec::new();
+        while *self.peek() != Token::RightBrace {
+            let field_name = match self.peek() {
+                Token::Identifier(name) => name.clone(),
+                _ => {
+                    return Err(ParseError {
+                        message: "Expected field name".to_string(),
+                        span: self.span(self.position()),
+                    })
+                },
+            };
+            self.advance();
+            self.expect_token(&Token::Colon)?;
+            let field_value = self.parse_expression()?;
+            fields.push((field_name, field_value));
+            if *self.peek() == Token::Comma {
+                self.advance();
+            }
+        }
+        self.expect_token(&Token::RightBrace)?;
+        Ok(fields)
+    }
+
     /// 解析基本表达式
     fn parse_primary(&mut self) -> Result<Expression, ParseError> {
         let start = self.position();
@@ -1873,14 +2546,21 @@ impl<'a> Parser<'a> {
             Token::Float(f) => Ok(Expression::Float(f, self.span(start))),
             Token::StringLiteral(s) => Ok(Expression::String(s, self.span(start))),
             Token::Boolean(b) => Ok(Expression::Boolean(b, self.span(start))),
+            Token::Null => Ok(Expression::Null(self.span(start))),
+            Token::Undefined => Ok(Expression::Undefined(self.span(start))),
             Token::Identifier(name) => Ok(Expression::Identifier(name, self.span(start))),
             Token::This => Ok(Expression::This(self.span(start))),
+            Token::Super => Ok(Expression::Super(self.span(start))),
             Token::LeftParen => {
+                // 箭头函数 (a: number) => a + 1，否则为括号表达式
+                if let Some(arrow) = self.try_parse_arrow_function(start)? {
+                    return Ok(arrow);
+                }
                 let expr = self.parse_expression()?;
                 self.expect_token(&Token::RightParen)?;
                 Ok(expr)
             },
-            Token::Fn => {
+            Token::Fn | Token::Function => {
                 // 解析函数表达式 (闭包)
                 self.parse_function_expression(start)
             },
@@ -1923,6 +2603,7 @@ impl<'a> Parser<'a> {
                 // 对于没有类型前缀的 struct 字面量，使用空名称
                 Ok(Expression::StructLiteral {
                     name: String::new(),
+                    type_arguments: Vec::new(),
                     fields,
                     span: self.span(start),
                 })
@@ -1965,19 +2646,19 @@ impl<'a> Parser<'a> {
         }
         self.expect_token(&Token::RightParen)?;
 
-        // 返回类型 (可选)
+        // 返回类型 (可选，省略时由类型检查根据 return 推导)
         let return_type = if *self.peek() == Token::Colon {
             self.advance();
             Box::new(self.parse_type()?)
         } else {
-            Box::new(Type::Void)
+            Box::new(Type::Any)
         };
 
         // 函数体
         let body = self.parse_block()?;
 
-        // 闭包捕获 - 目前为空，后续可以实现捕获分析
-        let captures = Vec::new();
+        // 闭包捕获分析
+        let captures = analyze_captures(&parameters, &body);
 
         Ok(Expression::FunctionExpression {
             parameters,
@@ -1988,6 +2669,82 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// 尝试解析箭头函数，调用时已跳过左括号
+    ///
+    /// 在 `=>` 之前解析失败时回溯并返回 `None`，由调用方按括号表达式处理。
+    fn try_parse_arrow_function(
+        &mut self,
+        start: (usize, usize),
+    ) -> Result<Option<Expression>, ParseError> {
+        let saved = (self.lexer.clone(), self.current.clone(), self.position);
+        let signature = self.parse_arrow_signature();
+        let Some((parameters, return_type)) = signature else {
+            (self.lexer, self.current, self.position) = saved;
+            return Ok(None);
+        };
+
+        // 函数体：块或单个表达式
+        let body = if *self.peek() == Token::LeftBrace {
+            self.parse_block()?
+        } else {
+            let expr_start = self.position();
+            let expr = self.parse_assignment()?;
+            Statement::Block(
+                vec![Statement::Return(Some(expr), self.span(expr_start))],
+                self.span(expr_start),
+            )
+        };
+
+        let captures = analyze_captures(&parameters, &body);
+        Ok(Some(Expression::FunctionExpression {
+            parameters,
+            return_type: Box::new(return_type),
+            body: Box::new(body),
+            captures,
+            span: self.span(start),
+        }))
+    }
+
+    /// 解析箭头函数的参数列表、可选返回类型和 `=>`
+    /// 参数类型可省略，省略时为 any
+    fn parse_arrow_signature(&mut self) -> Option<(Vec<Parameter>, Type)> {
+        let mut parameters = Vec::new();
+        while *self.peek() != Token::RightParen {
+            let Token::Identifier(name) = self.peek().clone() else {
+                return None;
+            };
+            self.advance();
+
+            let type_annotation = if *self.peek() == Token::Colon {
+                self.advance();
+                self.parse_type().ok()?
+            } else {
+                Type::Any
+            };
+            parameters.push(Parameter { name, type_annotation });
+
+            match self.peek() {
+                Token::Comma => self.advance(),
+                Token::RightParen => {},
+                _ => return None,
+            }
+        }
+        self.advance(); // 跳过 )
+
+        let return_type = if *self.peek() == Token::Colon {
+            self.advance();
+            self.parse_type().ok()?
+        } else {
+            Type::Any
+        };
+
+        if *self.peek() != Token::EqualsGreaterThan {
+            return None;
+        }
+        self.advance();
+        Some((parameters, return_type))
+    }
+
     /// 期望特定 Token
     fn expect_token(&mut self, expected: &Token) -> Result<(), ParseError> {
         if self.check(expected) {
@@ -2025,6 +2782,292 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_labeled_loop() {
+        let source = "outer: for (let i = 0; i < 3; i = i + 1) { while (true) { break outer; } }";
+        let mut parser = Parser::new(source);
+        let program = parser.parse_program().unwrap();
+        let Statement::For { label, body, .. } = &program.statements[0] else {
+            panic!("expected for statement");
+        };
+        assert_eq!(label.as_deref(), Some("outer"));
+        let Statement::Block(stmts, _) = body.as_ref() else { panic!("expected block") };
+        let Statement::While { body, .. } = &stmts[0] else { panic!("expected while") };
+        let Statement::Block(stmts, _) = body.as_ref() else { panic!("expected block") };
+        assert!(matches!(&stmts[..], [Statement::Break { label: Some(l), .. }] if l == "outer"));
+
+        // 换行后的标识符不是标签
+        let source = "while (true) { continue\nfoo(); }";
+        let program = Parser::new(source).parse_program().unwrap();
+        let Statement::While { body, .. } = &program.statements[0] else {
+            panic!("expected while statement");
+        };
+        let Statement::Block(stmts, _) = body.as_ref() else { panic!("expected block") };
+        assert!(matches!(
+            &stmts[..],
+            [Statement::Continue { label: None, .. }, Statement::ExpressionStatement(_)]
+        ));
+    }
+
+    #[test]
+    fn test_function_expression_captures() {
+        let source = "let f = function(x: number): number { let y = x + a; b = y; return y; };";
+        let mut parser = Parser::new(source);
+        let program = parser.parse_program().unwrap();
+        let Statement::VariableDeclaration { initializer: Some(init), .. } = &program.statements[0]
+        else {
+            panic!("expected variable declaration");
+        };
+        let Expression::FunctionExpression { captures, .. } = init else {
+            panic!("expected function expression");
+        };
+        assert_eq!(
+            captures,
+            &vec![
+                Capture { name: "a".to_string(), assigned: false },
+                Capture { name: "b".to_string(), assigned: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_closure_cells() {
+        let source = "function counter(): number {
+                          let c = 0; let k = 1; let x = 1;
+                          let f = function (): number { c = c + 1; return c + k; };
+                          let g = function (): number { return x; };
+                          x = 10;
+                          return c;
+                      }
+                      class Point { x: number, scale(): number { let f = function (): number { return this.x; }; return 0; } }";
+        let program = Parser::new(source).parse_program().unwrap();
+        // 闭包之外的赋值同样要求共享单元，只读的 k 按值捕获
+        let func = &program.functions[0];
+        let cells = crate::cell_variables(&func.parameters, &[&func.body]);
+        assert_eq!(cells, ["c", "x"].iter().map(|s| s.to_string()).collect());
+
+        let method = &program.structs[0].methods[0];
+        let Statement::Block(stmts, _) = &method.body else { panic!("expected block") };
+        let Statement::VariableDeclaration {
+            initializer: Some(Expression::FunctionExpression { captures, .. }),
+            ..
+        } = &stmts[0]
+        else {
+            panic!("expected closure")
+        };
+        assert_eq!(captures, &vec![Capture { name: "this".to_string(), assigned: false }]);
+    }
+
+    #[test]
+    fn test_parse_arrow_function() {
+        let source = "let f = (a: number, b): number => a + b; let g = () => { return 1; }; let h = (1 + 2) * 3;";
+        let mut parser = Parser::new(source);
+        let program = parser.parse_program().unwrap();
+        let initializers: Vec<&Expression> = program
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::VariableDeclaration { initializer: Some(init), .. } => Some(init),
+                _ => None,
+            })
+            .collect();
+        let Expression::FunctionExpression { parameters, return_type, .. } = initializers[0] else {
+            panic!("expected arrow function");
+        };
+        assert_eq!(parameters[0].type_annotation, Type::Number);
+        assert_eq!(parameters[1].type_annotation, Type::Any);
+        assert_eq!(**return_type, Type::Number);
+        assert!(matches!(initializers[1], Expression::FunctionExpression { .. }));
+        assert!(matches!(initializers[2], Expression::Binary { .. }));
+    }
+
+    #[test]
+    fn test_parse_function_type() {
+        let source = "function apply(f: (x: number, y: string) => boolean): () => number[] { }";
+        let mut parser = Parser::new(source);
+        let program = parser.parse_program().unwrap();
+        let func = &program.functions[0];
+        assert_eq!(
+            func.parameters[0].type_annotation,
+            Type::Function(vec![Type::Number, Type::String], Box::new(Type::Boolean))
+        );
+        assert_eq!(
+            func.return_type,
+            Type::Function(vec![], Box::new(Type::Array(Box::new(Type::Number))))
+        );
+    }
+
+    #[test]
+    fn test_parse_class_extends() {
+        let source = "class Dog extends Animal { constructor(n: number) { super(n); } \
+                      speak(): number { return super.speak() + 1; } }";
+        let mut parser = Parser::new(source);
+        let program = parser.parse_program().unwrap();
+        let class = &program.structs[0];
+        assert_eq!(class.extends.as_deref(), Some("Animal"));
+        let Statement::Block(stmts, _) = &class.constructor.as_ref().unwrap().body else {
+            panic!("expected block");
+        };
+        let Statement::ExpressionStatement(Expression::Call { callee, .. }) = &stmts[0] else {
+            panic!("expected super call");
+        };
+        assert!(matches!(callee.as_ref(), Expression::Super(_)));
+    }
+
+    #[test]
+    fn test_parse_export_default() {
+        let source = "import helper, { other } from \"./helper\"; \
+                      export default function add(a: number, b: number): number { return a + b; }";
+        let program = Parser::new(source).parse_program().unwrap();
+        assert_eq!(program.imports[0].default.as_deref(), Some("helper"));
+        assert_eq!(program.imports[0].alias, None);
+        assert_eq!(program.functions[0].name, "add");
+        assert_eq!(program.exports[0].kind, ExportKind::Default("add".to_string()));
+
+        // 字面量保存为名为 default 的模块常量
+        let program = Parser::new("export default -1.5;").parse_program().unwrap();
+        assert!(matches!(
+            &program.statements[0],
+            Statement::VariableDeclaration { name, mutable: false, .. } if name == "default"
+        ));
+        assert_eq!(program.exports[0].kind, ExportKind::Default("default".to_string()));
+
+        // 其他表达式无法确定导出的类型
+        let error = Parser::new("function f(): number { return 1; }\nexport default f();")
+            .parse_program()
+            .unwrap_err();
+        assert!(error.message.contains("'export default' expects"));
+        assert_eq!(error.span.end.0, 2);
+    }
+
+    #[test]
+    fn test_parse_enum() {
+        let source = "enum Color { Red, Green = 5, Blue }\n\
+                      export const enum Dir { Up = \"UP\", Down = \"DOWN\" }\n\
+                      function f(c: Color): number { switch (c) { case Color.Red => 1; _ => 2; } return 0; }";
+        let program = Parser::new(source).parse_program().unwrap();
+        let values: Vec<&EnumValue> = program.enums[0].members.iter().map(|m| &m.value).collect();
+        assert_eq!(values, [&EnumValue::Number(0), &EnumValue::Number(5), &EnumValue::Number(6)]);
+        assert!(program.enums[1].is_const && program.enums[1].is_string());
+        assert!(
+            matches!(&program.exports[0].kind, ExportKind::Named(specs) if specs[0].name == "Dir")
+        );
+        let Statement::Block(stmts, _) = &program.functions[0].body else { panic!() };
+        let Statement::Switch { arms, .. } = &stmts[0] else { panic!("expected switch") };
+        assert_eq!(arms[0].pattern, SwitchPattern::EnumMember("Color".into(), "Red".into()));
+
+        // 字符串成员之后的成员必须显式初始化
+        assert!(Parser::new("enum E { A = \"a\", B }").parse_program().is_err());
+    }
+
+    #[test]
+    fn test_parse_type_alias() {
+        let source = "type Shape = | Circle | Square;\n\
+                      export type Pair = (number | string)[] & Named;\n\
+                      type Dir = \"up\" | -1 | true;\n\
+                      type F = (x: number) => string | null;";
+        let program = Parser::new(source).parse_program().unwrap();
+        let types: Vec<&Type> = program.type_aliases.iter().map(|a| &a.ty).collect();
+        let named = |n: &str| Type::Struct(n.to_string());
+        assert_eq!(types[0], &Type::Union(vec![named("Circle"), named("Square")]));
+        let elements = Type::Union(vec![Type::Number, Type::String]);
+        assert_eq!(
+            types[1],
+            &Type::Intersection(vec![Type::Array(Box::new(elements)), named("Named")])
+        );
+        assert_eq!(
+            types[2],
+            &Type::Union(vec![
+                Type::Literal(LiteralType::String("up".into())),
+                Type::Literal(LiteralType::Number(-1)),
+                Type::Literal(LiteralType::Boolean(true)),
+            ])
+        );
+        let ret = Type::Union(vec![Type::String, Type::Null]);
+        assert_eq!(types[3], &Type::Function(vec![Type::Number], Box::new(ret)));
+        assert!(
+            matches!(&program.exports[0].kind, ExportKind::Named(specs) if specs[0].name == "Pair")
+        );
+    }
+
+    #[test]
+    fn test_parse_declare_function() {
+        let source = "declare function std_io_println(s: string): number;\n\
+                      export declare function readln(): string;";
+        let program = Parser::new(source).parse_program().unwrap();
+        assert_eq!(program.functions.len(), 2);
+        assert!(program.functions.iter().all(|f| f.is_declare));
+        assert_eq!(program.functions[0].parameters[0].type_annotation, Type::String);
+        assert_eq!(program.functions[1].return_type, Type::String);
+        assert!(
+            matches!(&program.exports[0].kind, ExportKind::Named(specs) if specs[0].name == "readln")
+        );
+    }
+
+    #[test]
+    fn test_parse_generics() {
+        let source = "function first<T, U extends Named>(a: T, b: U): T { return a; }\n\
+                      class Box<T> implements Container<T> { value: T, }\n\
+                      function main(): void {\n\
+                          let b: Box<Box<number>> = new Box<Box<number>>(Box<number> { value: 1 });\n\
+                          first<number, Named>(1, n);\n\
+                          let c = a < b;\n\
+                      }";
+        let program = Parser::new(source).parse_program().unwrap();
+        let params = &program.functions[0].type_parameters;
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[1].constraint, Some(Type::Struct("Named".into())));
+        let class = &program.structs[0];
+        assert_eq!(class.type_parameters[0].name, "T");
+        let container = Type::Generic("Container".into(), vec![Type::Struct("T".into())]);
+        assert_eq!(class.implements, vec![container]);
+
+        let Statement::Block(stmts, _) = &program.functions[1].body else { panic!() };
+        let Statement::VariableDeclaration { type_annotation, initializer, .. } = &stmts[0] else {
+            panic!("expected declaration")
+        };
+        let inner = Type::Generic("Box".into(), vec![Type::Number]);
+        assert_eq!(type_annotation, &Some(Type::Generic("Box".into(), vec![inner.clone()])));
+        let Some(Expression::New { type_arguments, args, .. }) = initializer else { panic!() };
+        assert_eq!(type_arguments, &vec![inner]);
+        assert!(
+            matches!(&args[0], Expression::StructLiteral { type_arguments, .. } if type_arguments.len() == 1)
+        );
+        let Statement::ExpressionStatement(Expression::Call { type_arguments, .. }) = &stmts[1]
+        else {
+            panic!("expected call")
+        };
+        assert_eq!(type_arguments.len(), 2);
+        // 没有跟 ( 的 < 仍然是比较运算符
+        let Statement::VariableDeclaration { initializer: Some(Expression::Binary { .. }), .. } =
+            &stmts[2]
+        else {
+            panic!("expected comparison")
+        };
+    }
+
+    #[test]
+    fn test_parse_sized_numbers() {
+        let source = "function f(a: i64, b: f32): u8 { return -a as u8 * 2; }";
+        let program = Parser::new(source).parse_program().unwrap();
+        let func = &program.functions[0];
+        assert_eq!(func.parameters[0].type_annotation, Type::I64);
+        assert_eq!(func.parameters[1].type_annotation, Type::F32);
+        assert_eq!(func.return_type, Type::U8);
+
+        // as 的优先级高于乘法、低于一元运算: ((-a) as u8) * 2
+        let Statement::Block(stmts, _) = &func.body else { panic!() };
+        let Statement::Return(Some(Expression::Binary { op, left, .. }), _) = &stmts[0] else {
+            panic!("expected return")
+        };
+        assert_eq!(*op, BinaryOp::Multiply);
+        let Expression::Cast { expression, target, .. } = left.as_ref() else {
+            panic!("expected cast")
+        };
+        assert_eq!(*target, Type::U8);
+        assert!(matches!(expression.as_ref(), Expression::Unary { op: UnaryOp::Negate, .. }));
+    }
+
     #[test]
     fn test_parse_expression() {
         let source = "1 + 2 * 3";
@@ -0,0 +1,3039 @@
+//! 类型检查器
+//!
+//! 遍历 AST，为每个表达式推导类型并校验语句的类型约束。
+
+use std::collections::HashMap;
+
+use nexa_parser::ast::*;
+use nexa_parser::module::{ExportedSymbol, SymbolTable};
+
+use crate::types::{TypeError, TypeInfo};
+
+/// 函数签名
+#[derive(Debug, Clone)]
+struct Signature {
+    /// 参数类型，`None` 表示接受任意参数（内置输出函数）
+    params: Option<Vec<Type>>,
+    return_type: Type,
+    /// 泛型函数的类型参数
+    type_params: Vec<TypeParameter>,
+}
+
+/// 作用域中的变量信息
+#[derive(Debug, Clone)]
+struct VarInfo {
+    ty: Type,
+    mutable: bool,
+    /// 被条件收窄时变量的声明类型
+    narrowed_from: Option<Type>,
+}
+
+/// 条件成立与不成立时各变量收窄后的类型
+#[derive(Debug, Clone, Default)]
+struct Narrowing {
+    when_true: Vec<(String, Type)>,
+    when_false: Vec<(String, Type)>,
+}
+
+impl Narrowing {
+    /// 条件取反
+    fn negate(self) -> Self {
+        Self { when_true: self.when_false, when_false: self.when_true }
+    }
+}
+
+/// 枚举成员在运行时的表示
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EnumKind {
+    Number,
+    String,
+}
+
+/// 类型检查器
+pub struct TypeChecker {
+    /// struct / class 定义
+    structs: HashMap<String, StructDefinition>,
+    /// 接口定义
+    interfaces: HashMap<String, InterfaceDefinition>,
+    /// 枚举定义
+    enums: HashMap<String, EnumDefinition>,
+    /// 类型别名
+    aliases: HashMap<String, TypeAlias>,
+    /// 函数签名 (包括 `Class_method` 形式的方法)
+    functions: HashMap<String, Signature>,
+    /// 命名空间导入：命名空间名称 -> 模块的全部导出
+    namespaces: HashMap<String, HashMap<String, ExportedSymbol>>,
+    /// 变量作用域栈
+    scopes: Vec<HashMap<String, VarInfo>>,
+    /// 泛型定义的类型参数作用域栈：类型参数名 -> 约束
+    type_params: Vec<HashMap<String, Option<Type>>>,
+    /// 当前函数的返回类型
+    current_return: Option<Type>,
+    /// 省略返回类型的函数表达式中收集到的 return 类型及其位置，不带值的 return 记为 void
+    inferred_returns: Option<Vec<(Type, Span)>>,
+    /// 当前方法所属的类
+    current_class: Option<String>,
+    /// 当前函数内外层循环的标签栈 (未标注标签的循环为 None)
+    loop_labels: Vec<Option<String>>,
+    /// 收集到的错误
+    errors: Vec<TypeError>,
+    /// 推导结果
+    info: TypeInfo,
+}
+
+impl TypeChecker {
+    /// 创建新的类型检查器
+    pub fn new() -> Self {
+        let mut checker = Self {
+            structs: HashMap::new(),
+            interfaces: HashMap::new(),
+            enums: HashMap::new(),
+            aliases: HashMap::new(),
+            functions: HashMap::new(),
+            namespaces: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            type_params: Vec::new(),
+            current_return: None,
+            inferred_returns: None,
+            current_class: None,
+            loop_labels: Vec::new(),
+            errors: Vec::new(),
+            info: TypeInfo::default(),
+        };
+        checker.declare_builtins();
+        checker
+    }
+
+    /// 声明运行时提供的内置函数
+    fn declare_builtins(&mut self) {
+        let any_args =
+            |return_type: Type| Signature { params: None, return_type, type_params: Vec::new() };
+        let sig = |params: Vec<Type>, return_type: Type| Signature {
+            params: Some(params),
+            return_type,
+            type_params: Vec::new(),
+        };
+
+        self.functions.insert("println".to_string(), any_args(Type::Void));
+        self.functions.insert("print".to_string(), any_args(Type::Void));
+        self.functions.insert("console.log".to_string(), any_args(Type::Void));
+        self.functions.insert("readln".to_string(), sig(vec![], Type::String));
+        self.functions.insert("readln_i32".to_string(), sig(vec![], Type::Number));
+        self.functions.insert("puts".to_string(), sig(vec![Type::String], Type::Number));
+        self.functions.insert("strlen".to_string(), sig(vec![Type::String], Type::Number));
+        self.functions.insert("std_string_len".to_string(), sig(vec![Type::String], Type::Number));
+        self.functions.insert("std_string_copy".to_string(), sig(vec![Type::String], Type::String));
+        self.functions.insert(
+            "std_string_compare".to_string(),
+            sig(vec![Type::String, Type::String], Type::Number),
+        );
+        self.functions.insert(
+            "std_string_concat".to_string(),
+            sig(vec![Type::String, Type::String], Type::String),
+        );
+        self.functions
+            .insert("std_string_to_i32".to_string(), sig(vec![Type::String], Type::Number));
+        self.functions
+            .insert("std_string_from_i32".to_string(), sig(vec![Type::Number], Type::String));
+        self.functions.insert("std_math_abs".to_string(), sig(vec![Type::Number], Type::Number));
+        self.functions.insert(
+            "std_math_max".to_string(),
+            sig(vec![Type::Number, Type::Number], Type::Number),
+        );
+        self.functions.insert(
+            "std_math_min".to_string(),
+            sig(vec![Type::Number, Type::Number], Type::Number),
+        );
+    }
+
+    /// 注册导入的符号
+    pub fn add_imported_symbols(&mut self, symbols: &SymbolTable) {
+        for name in symbols.symbols() {
+            match symbols.find(&name) {
+                Some(ExportedSymbol::Function(func)) => {
+                    self.functions.insert(name, Self::signature_of(&func));
+                },
+                Some(ExportedSymbol::Variable(_, ty, mutable)) => {
+                    self.scopes[0].insert(name, VarInfo { ty, mutable, narrowed_from: None });
+                },
+                Some(ExportedSymbol::Struct(def)) => {
+                    self.structs.insert(name, def);
+                },
+                Some(ExportedSymbol::Interface(def)) => {
+                    self.interfaces.insert(name, def);
+                },
+                Some(ExportedSymbol::Enum(def)) => {
+                    self.enums.insert(name, def);
+                },
+                Some(ExportedSymbol::TypeAlias(def)) => {
+                    self.aliases.insert(name, def);
+                },
+                Some(ExportedSymbol::Constant(_)) => {
+                    let var = VarInfo { ty: Type::Any, mutable: false, narrowed_from: None };
+                    self.scopes[0].insert(name, var);
+                },
+                None => {},
+            }
+        }
+        for alias in symbols.namespaces() {
+            let Some(exports) = symbols.namespace(&alias) else { continue };
+            // 通过命名空间得到的对象同样需要类型定义
+            for symbol in exports.values() {
+                match symbol {
+                    ExportedSymbol::Struct(def) => {
+                        self.structs.entry(def.name.clone()).or_insert_with(|| def.clone());
+                    },
+                    ExportedSymbol::Interface(def) => {
+                        self.interfaces.entry(def.name.clone()).or_insert_with(|| def.clone());
+                    },
+                    ExportedSymbol::Enum(def) => {
+                        self.enums.entry(def.name.clone()).or_insert_with(|| def.clone());
+                    },
+                    ExportedSymbol::TypeAlias(def) => {
+                        self.aliases.entry(def.name.clone()).or_insert_with(|| def.clone());
+                    },
+                    _ => {},
+                }
+            }
+            // 重新导出的成员分散在各自定义模块的符号表中，按命名空间合并
+            self.namespaces.entry(alias).or_default().extend(exports.clone());
+        }
+    }
+
+    /// 检查整个程序
+    ///
+    /// 成功时返回每个表达式的类型信息，失败时返回所有类型错误。
+    pub fn check_program(&mut self, program: &Program) -> Result<TypeInfo, Vec<TypeError>> {
+        for interface in &program.interfaces {
+            self.interfaces.insert(interface.name.clone(), interface.clone());
+        }
+        for struct_def in &program.structs {
+            self.structs.insert(struct_def.name.clone(), struct_def.clone());
+        }
+        for enum_def in &program.enums {
+            // 成员在运行时统一表示为整数或字符串
+            let strings =
+                enum_def.members.iter().filter(|m| matches!(m.value, EnumValue::String(_)));
+            if enum_def.is_string() && strings.count() != enum_def.members.len() {
+                self.error(
+                    format!("Enum '{}' cannot mix numeric and string members", enum_def.name),
+                    &enum_def.span,
+                );
+            }
+            self.enums.insert(enum_def.name.clone(), enum_def.clone());
+        }
+        for alias in &program.type_aliases {
+            self.aliases.insert(alias.name.clone(), alias.clone());
+        }
+        self.check_aliases(program);
+
+        // 先登记所有签名，函数体中可以相互引用
+        for func in &program.functions {
+            self.functions.insert(func.name.clone(), Self::signature_of(func));
+        }
+        for struct_def in &program.structs {
+            for method in &struct_def.methods {
+                self.functions.insert(method.name.clone(), Self::signature_of(method));
+            }
+            if let Some(constructor) = &struct_def.constructor {
+                self.functions.insert(constructor.name.clone(), Self::signature_of(constructor));
+            }
+        }
+
+        // 校验声明中引用的类型
+        for struct_def in &program.structs {
+            self.check_inheritance(struct_def);
+            self.push_type_params(&struct_def.type_parameters, &struct_def.span);
+            self.check_implements(struct_def);
+            for field in &struct_def.fields {
+                self.check_type_exists(&field.field_type, &struct_def.span);
+            }
+            self.pop_type_params();
+        }
+        for interface in &program.interfaces {
+            self.push_type_params(&interface.type_parameters, &interface.span);
+            for field in &interface.fields {
+                self.check_type_exists(&field.field_type, &interface.span);
+            }
+            self.pop_type_params();
+        }
+
+        // 顶层语句在全局作用域中检查
+        for stmt in &program.statements {
+            self.check_statement(stmt);
+        }
+
+        for func in &program.functions {
+            self.check_function(func);
+        }
+        for struct_def in &program.structs {
+            self.current_class = Some(struct_def.name.clone());
+            self.push_type_params(&struct_def.type_parameters, &struct_def.span);
+            if let Some(constructor) = &struct_def.constructor {
+                self.check_function(constructor);
+            }
+            for method in &struct_def.methods {
+                self.check_function(method);
+            }
+            self.pop_type_params();
+            self.current_class = None;
+        }
+
+        if self.errors.is_empty() {
+            Ok(std::mem::take(&mut self.info))
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// 校验类型别名引用的类型存在且别名不直接引用自身
+    fn check_aliases(&mut self, program: &Program) {
+        let mut names: Vec<String> = self.aliases.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            let ty = Type::Struct(name.clone());
+            if self.alias_is_circular(&ty, &mut Vec::new()) {
+                let span = self.aliases[&name].span.clone();
+                self.error(format!("Type alias '{}' circularly references itself", name), &span);
+                // 展开循环别名会无限递归，出错后按 any 处理
+                if let Some(alias) = self.aliases.get_mut(&name) {
+                    alias.ty = Type::Any;
+                }
+            }
+        }
+        for alias in &program.type_aliases {
+            self.check_type_exists(&alias.ty, &alias.span);
+        }
+    }
+
+    /// 别名展开时是否会回到自身 (只跟随联合/交叉成员，数组等结构类型延迟展开)
+    fn alias_is_circular(&self, ty: &Type, visiting: &mut Vec<String>) -> bool {
+        match ty {
+            Type::Struct(name) => {
+                let Some(alias) = self.aliases.get(name) else { return false };
+                if visiting.contains(name) {
+                    return true;
+                }
+                visiting.push(name.clone());
+                let circular = self.alias_is_circular(&alias.ty, visiting);
+                visiting.pop();
+                circular
+            },
+            Type::Union(types) | Type::Intersection(types) => {
+                types.iter().any(|t| self.alias_is_circular(t, visiting))
+            },
+            _ => false,
+        }
+    }
+
+    /// 校验父类存在、继承无环，且字段不重名、重写方法签名一致
+    fn check_inheritance(&mut self, class: &StructDefinition) {
+        let Some(base) = &class.extends else { return };
+        match self.structs.get(base) {
+            Some(def) if def.is_class => {},
+            Some(_) => {
+                self.error(
+                    format!("'{}' is not a class and cannot be extended", base),
+                    &class.span,
+                );
+                return;
+            },
+            None => {
+                self.error(format!("Cannot find base class '{}'", base), &class.span);
+                return;
+            },
+        }
+        if self.is_subclass(base, &class.name) {
+            self.error(format!("Class '{}' circularly extends itself", class.name), &class.span);
+            return;
+        }
+
+        let mut errors = Vec::new();
+        let base_fields = self.all_fields(base);
+        for field in &class.fields {
+            if base_fields.iter().any(|f| f.name == field.name) {
+                errors.push(format!(
+                    "Field '{}' in '{}' conflicts with a field of base class '{}'",
+                    field.name, class.name, base
+                ));
+            }
+        }
+        for method in &class.methods {
+            let member = &method.name[class.name.len() + 1..];
+            let Some(overridden) = self.find_method(base, member) else { continue };
+            let same_params = method.parameters.len() == overridden.parameters.len()
+                && method
+                    .parameters
+                    .iter()
+                    .zip(&overridden.parameters)
+                    .all(|(a, b)| a.type_annotation == b.type_annotation);
+            if !same_params || method.return_type != overridden.return_type {
+                errors.push(format!(
+                    "Method '{}' in '{}' is not compatible with the overridden method in '{}'",
+                    member, class.name, base
+                ));
+            }
+        }
+        for message in errors {
+            self.error(message, &class.span);
+        }
+    }
+
+    /// 从函数定义提取签名
+    fn signature_of(func: &Function) -> Signature {
+        Signature {
+            params: Some(func.parameters.iter().map(|p| p.type_annotation.clone()).collect()),
+            return_type: func.return_type.clone(),
+            type_params: func.type_parameters.clone(),
+        }
+    }
+
+    /// 检查 break / continue 是否位于循环内，带标签时标签必须属于外层循环
+    fn check_jump(&mut self, keyword: &str, label: &Option<String>, span: &Span) {
+        match label {
+            None if self.loop_labels.is_empty() => self.error(
+                format!("A '{}' statement can only be used within an enclosing loop", keyword),
+                span,
+            ),
+            Some(label) if !self.loop_labels.iter().any(|l| l.as_ref() == Some(label)) => self
+                .error(
+                    format!(
+                        "A '{}' statement can only jump to a label of an enclosing loop, \
+                         cannot find label '{}'",
+                        keyword, label
+                    ),
+                    span,
+                ),
+            _ => {},
+        }
+    }
+
+    /// 检查函数体
+    fn check_function(&mut self, func: &Function) {
+        self.push_type_params(&func.type_parameters, &func.span);
+        self.push_scope();
+        for param in &func.parameters {
+            self.check_type_exists(&param.type_annotation, &func.span);
+            self.declare(&param.name, param.type_annotation.clone(), true);
+        }
+        self.check_type_exists(&func.return_type, &func.span);
+
+        // declare function 只有签名
+        if !func.is_declare {
+            let previous = self.current_return.replace(func.return_type.clone());
+            let loops = std::mem::take(&mut self.loop_labels);
+            self.check_statement(&func.body);
+            self.loop_labels = loops;
+            self.current_return = previous;
+        }
+        self.pop_scope();
+        self.pop_type_params();
+    }
+
+    // ============ 作用域 ============
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &str, ty: Type, mutable: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), VarInfo { ty, mutable, narrowed_from: None });
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&VarInfo> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut VarInfo> {
+        self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name))
+    }
+
+    /// 成员访问的对象是枚举时返回枚举的类型名和定义 (`Color.Red` 或 `ns.Color.Red`)
+    fn enum_of(&self, object: &Expression) -> Option<(String, EnumDefinition)> {
+        match object {
+            Expression::Identifier(name, _) if self.lookup(name).is_none() => {
+                self.enums.get(name).map(|def| (name.clone(), def.clone()))
+            },
+            Expression::Member { object, member, .. } => {
+                let ns = self.namespace_of(object)?;
+                match self.namespaces.get(&ns)?.get(member)? {
+                    ExportedSymbol::Enum(def) => Some((def.name.clone(), def.clone())),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// typeof 在运行时按值的类型或联合标签得出结果，`any` 的值不带类型标签
+    fn check_typeof_operand(&mut self, ty: &Type, span: &Span) {
+        if self.resolve(ty) == Type::Any {
+            self.error(
+                "typeof cannot be applied to a value of type 'any': it carries no runtime type tag"
+                    .to_string(),
+                span,
+            );
+        }
+    }
+
+    /// 展开顶层的类型别名
+    fn resolve(&self, ty: &Type) -> Type {
+        let mut ty = ty.clone();
+        while let Type::Struct(name) = &ty {
+            if self.type_param(name).is_some() {
+                break;
+            }
+            match self.aliases.get(name) {
+                Some(alias) => ty = alias.ty.clone(),
+                None => break,
+            }
+        }
+        ty
+    }
+
+    /// 类型是否为数值枚举或字符串枚举
+    fn enum_kind(&self, ty: &Type) -> Option<EnumKind> {
+        let Type::Struct(name) = self.resolve(ty) else { return None };
+        let name = &name;
+        let def = self.enums.get(name)?;
+        Some(if def.is_string() { EnumKind::String } else { EnumKind::Number })
+    }
+
+    /// `ns.member` 中未被变量遮蔽的命名空间导入
+    fn namespace_of(&self, object: &Expression) -> Option<String> {
+        match object {
+            Expression::Identifier(ns, _)
+                if self.lookup(ns).is_none() && self.namespaces.contains_key(ns) =>
+            {
+                Some(ns.clone())
+            },
+            _ => None,
+        }
+    }
+
+    /// 命名空间导入的成员，未导出时报错
+    fn namespace_member(&mut self, ns: &str, member: &str, span: &Span) -> Option<ExportedSymbol> {
+        let symbol = self.namespaces.get(ns).and_then(|exports| exports.get(member)).cloned();
+        if symbol.is_none() {
+            self.error(format!("Namespace '{}' has no exported member '{}'", ns, member), span);
+        }
+        symbol
+    }
+
+    fn error(&mut self, message: String, span: &Span) {
+        self.errors.push(TypeError { message, span: span.clone() });
+    }
+
+    // ============ 类型关系 ============
+
+    /// 检查类型注解中引用的名称是否存在
+    fn check_type_exists(&mut self, ty: &Type, span: &Span) {
+        match ty {
+            Type::Struct(name) if self.type_param(name).is_some() => {},
+            Type::Struct(name) if self.generic_params(name).is_some_and(|p| !p.is_empty()) => {
+                let count = self.generic_params(name).map_or(0, |p| p.len());
+                self.error(
+                    format!("Generic type '{}' requires {} type argument(s)", name, count),
+                    span,
+                );
+            },
+            Type::Generic(name, args) => {
+                for arg in args {
+                    self.check_type_exists(arg, span);
+                }
+                let Some(params) = self.generic_params(name).map(<[TypeParameter]>::to_vec) else {
+                    self.error(format!("Cannot find type '{}'", name), span);
+                    return;
+                };
+                if params.len() != args.len() {
+                    self.error(
+                        format!(
+                            "Generic type '{}' requires {} type argument(s)",
+                            name,
+                            params.len()
+                        ),
+                        span,
+                    );
+                    return;
+                }
+                self.check_constraints(&params, args, span);
+            },
+            Type::Struct(name)
+                if !self.structs.contains_key(name)
+                    && !self.interfaces.contains_key(name)
+                    && !self.enums.contains_key(name)
+                    && !self.aliases.contains_key(name) =>
+            {
+                self.error(format!("Cannot find type '{}'", name), span);
+            },
+            Type::Array(inner) | Type::Pointer(inner) => self.check_type_exists(inner, span),
+            Type::Function(params, ret) => {
+                for param in params {
+                    self.check_type_exists(param, span);
+                }
+                self.check_type_exists(ret, span);
+            },
+            Type::Object(fields) => {
+                for (_, field_type) in fields {
+                    self.check_type_exists(field_type, span);
+                }
+            },
+            Type::Union(types) | Type::Intersection(types) => {
+                for ty in types {
+                    self.check_type_exists(ty, span);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// 判断 `source` 类型的值能否赋给 `target` 类型
+    fn is_assignable(&self, target: &Type, source: &Type) -> bool {
+        if target == source {
+            return true;
+        }
+        let (target, source) = (&self.resolve(target), &self.resolve(source));
+        if target == source {
+            return true;
+        }
+        match (target, source) {
+            (Type::Any, _) | (_, Type::Any) | (_, Type::Never) => true,
+            // 联合类型的每个成员都必须能赋给目标
+            (_, Type::Union(types)) => types.iter().all(|s| self.is_assignable(target, s)),
+            (Type::Union(types), _) => types.iter().any(|t| self.is_assignable(t, source)),
+            (_, Type::Intersection(types)) => types.iter().any(|s| self.is_assignable(target, s)),
+            (Type::Intersection(types), _) => types.iter().all(|t| self.is_assignable(t, source)),
+            // 类型参数只能按其约束赋给其它类型
+            (_, Type::Struct(name)) if self.type_param(name).is_some() => {
+                match self.type_param(name) {
+                    Some(Some(constraint)) => self.is_assignable(target, constraint),
+                    _ => false,
+                }
+            },
+            // 字面量类型是其基础类型的子类型
+            (_, Type::Literal(literal)) => self.is_assignable(target, &literal.base_type()),
+            (Type::Void, Type::Undefined) => true,
+            // 数值类型之间只允许无损的隐式拓宽，其余需要 `as` 显式转换
+            (Type::F64, Type::F32) => true,
+            (_, _) if target.int_width().is_some() && source.int_width().is_some() => {
+                let (target_bits, target_signed) = target.int_width().unwrap_or((32, true));
+                let (source_bits, source_signed) = source.int_width().unwrap_or((32, true));
+                (target_bits == source_bits && target_signed == source_signed)
+                    || (target_bits > source_bits && (target_signed || !source_signed))
+            },
+            // 数值枚举与 number 可以互相赋值，字符串枚举只能赋给 string
+            (Type::Struct(_), _) if self.enum_kind(target).is_some() => {
+                self.enum_kind(target) == Some(EnumKind::Number) && *source == Type::Number
+            },
+            (Type::Number | Type::I32, _) if self.enum_kind(source) == Some(EnumKind::Number) => {
+                true
+            },
+            (Type::String, _) if self.enum_kind(source) == Some(EnumKind::String) => true,
+            (
+                Type::String
+                | Type::Struct(_)
+                | Type::Array(_)
+                | Type::Pointer(_)
+                | Type::Function(_, _)
+                | Type::Object(_)
+                | Type::Generic(..),
+                Type::Null | Type::Undefined,
+            ) => true,
+            (Type::Array(t), Type::Array(s)) => self.is_assignable(t, s),
+            (Type::Pointer(t), Type::Pointer(s)) => self.is_assignable(t, s),
+            // 接口值只能由 class 实例转换得到 (运行时需要对象指针和虚表)
+            (
+                Type::Struct(iface) | Type::Generic(iface, _),
+                Type::Struct(class) | Type::Generic(class, _),
+            ) if self.interfaces.contains_key(iface) => {
+                self.structs.get(class).is_some_and(|def| def.is_class)
+                    && self.missing_members(source, target).is_empty()
+            },
+            // 同一泛型的实例按类型实参逐个比较
+            (Type::Generic(name, args), Type::Generic(other, other_args)) => {
+                name == other
+                    && args.len() == other_args.len()
+                    && args.iter().zip(other_args).all(|(t, s)| self.is_assignable(t, s))
+            },
+            (Type::Struct(base), Type::Struct(class)) => self.is_subclass(class, base),
+            (Type::Struct(name), Type::Object(fields)) => match self.structs.get(name) {
+                Some(def) => {
+                    def.fields.len() == fields.len()
+                        && def.fields.iter().all(|field| {
+                            fields.iter().any(|(n, t)| {
+                                *n == field.name && self.is_assignable(&field.field_type, t)
+                            })
+                        })
+                },
+                None => false,
+            },
+            (Type::Function(target_params, target_ret), Type::Function(params, ret)) => {
+                target_params.len() == params.len()
+                    && target_params.iter().zip(params).all(|(t, s)| self.is_assignable(s, t))
+                    && self.is_assignable(target_ret, ret)
+            },
+            _ => false,
+        }
+    }
+
+    /// 类的继承链，从自身开始直到根类
+    fn class_chain(&self, class: &str) -> Vec<&StructDefinition> {
+        let mut chain: Vec<&StructDefinition> = Vec::new();
+        let mut current = self.structs.get(class);
+        while let Some(def) = current {
+            // 循环继承时在回到已访问的类处停止
+            if chain.iter().any(|d| d.name == def.name) {
+                break;
+            }
+            chain.push(def);
+            current = def.extends.as_ref().and_then(|base| self.structs.get(base));
+        }
+        chain
+    }
+
+    /// `class` 是否为 `base` 本身或其子类
+    fn is_subclass(&self, class: &str, base: &str) -> bool {
+        self.class_chain(class).iter().any(|def| def.name == base)
+    }
+
+    /// 类的全部字段，父类字段在前
+    fn all_fields(&self, class: &str) -> Vec<&StructField> {
+        self.class_chain(class).into_iter().rev().flat_map(|def| &def.fields).collect()
+    }
+
+    /// 沿继承链查找方法
+    fn find_method(&self, class: &str, member: &str) -> Option<&Function> {
+        self.class_chain(class).into_iter().find_map(|def| {
+            let qualified = format!("{}_{}", def.name, member);
+            def.methods.iter().find(|m| m.name == qualified)
+        })
+    }
+
+    /// 沿继承链查找构造函数
+    fn find_constructor(&self, class: &str) -> Option<&Function> {
+        self.class_chain(class).into_iter().find_map(|def| def.constructor.as_ref())
+    }
+
+    /// 列出类 `class` 相对接口 `iface` 缺失的成员
+    ///
+    /// 两者都可以是泛型实例，成员类型按各自的类型实参替换后比较。
+    fn missing_members(&self, class: &Type, iface: &Type) -> Vec<String> {
+        let (Some((class, class_args)), Some((iface, iface_args))) =
+            (self.named_type(class), self.named_type(iface))
+        else {
+            return Vec::new();
+        };
+        let Some(interface) = self.interfaces.get(&iface) else {
+            return Vec::new();
+        };
+
+        let fields = self.all_fields(&class);
+        let mut missing = Vec::new();
+        for field in &interface.fields {
+            let expected = field.field_type.substitute(&iface_args);
+            let found = fields.iter().any(|f| {
+                f.name == field.name
+                    && self.is_assignable(&expected, &f.field_type.substitute(&class_args))
+            });
+            if !found {
+                missing.push(field.name.clone());
+            }
+        }
+        for method in &interface.methods {
+            let expected =
+                Self::method_type(&method.parameters, &method.return_type).substitute(&iface_args);
+            let found = self.find_method(&class, &method.name).is_some_and(|m| {
+                let actual = Self::method_type(&m.parameters, &m.return_type);
+                self.is_assignable(&expected, &actual.substitute(&class_args))
+            });
+            if !found {
+                missing.push(format!("{}()", method.name));
+            }
+        }
+        missing
+    }
+
+    /// 方法的函数类型 (不含 this)
+    fn method_type(parameters: &[Parameter], return_type: &Type) -> Type {
+        Type::Function(
+            parameters.iter().map(|p| p.type_annotation.clone()).collect(),
+            Box::new(return_type.clone()),
+        )
+    }
+
+    /// 校验类声明实现的接口，列出缺失或类型不兼容的成员
+    fn check_implements(&mut self, class: &StructDefinition) {
+        for iface in &class.implements {
+            let (Type::Struct(name) | Type::Generic(name, _)) = iface else { continue };
+            if !self.interfaces.contains_key(name) {
+                self.error(format!("Cannot find interface '{}'", name), &class.span);
+                continue;
+            }
+            self.check_type_exists(iface, &class.span);
+            let missing = self.missing_members(&self.self_type(&class.name), iface);
+            if !missing.is_empty() {
+                self.error(
+                    format!(
+                        "Class '{}' incorrectly implements interface '{}': missing or incompatible members: {}",
+                        class.name,
+                        iface,
+                        missing.join(", ")
+                    ),
+                    &class.span,
+                );
+            }
+        }
+    }
+
+    /// 查找结构体/接口成员的类型
+    fn member_type(&self, type_name: &str, member: &str) -> Option<Type> {
+        if self.structs.contains_key(type_name) {
+            if let Some(field) = self.all_fields(type_name).into_iter().find(|f| f.name == member) {
+                return Some(field.field_type.clone());
+            }
+            if let Some(method) = self.find_method(type_name, member) {
+                return Some(Type::Function(
+                    method.parameters.iter().map(|p| p.type_annotation.clone()).collect(),
+                    Box::new(method.return_type.clone()),
+                ));
+            }
+        }
+        if let Some(interface) = self.interfaces.get(type_name) {
+            if let Some(field) = interface.fields.iter().find(|f| f.name == member) {
+                return Some(field.field_type.clone());
+            }
+            if let Some(method) = interface.methods.iter().find(|m| m.name == member) {
+                return Some(Type::Function(
+                    method.parameters.iter().map(|p| p.type_annotation.clone()).collect(),
+                    Box::new(method.return_type.clone()),
+                ));
+            }
+        }
+        None
+    }
+
+    fn is_numeric(&self, ty: &Type) -> bool {
+        match self.apparent_type(ty) {
+            Type::Any | Type::Literal(LiteralType::Number(_)) => true,
+            ty if ty.is_numeric() => true,
+            Type::Union(types) => types.iter().all(|t| self.is_numeric(t)),
+            _ => self.enum_kind(ty) == Some(EnumKind::Number),
+        }
+    }
+
+    /// 字符串、字符串字面量或字符串枚举
+    fn is_string(&self, ty: &Type) -> bool {
+        match self.apparent_type(ty) {
+            Type::String | Type::Literal(LiteralType::String(_)) => true,
+            Type::Union(types) => types.iter().all(|t| self.is_string(t)),
+            _ => self.enum_kind(ty) == Some(EnumKind::String),
+        }
+    }
+
+    /// 类型中是否出现字面量类型
+    fn mentions_literal(&self, ty: &Type) -> bool {
+        match self.resolve(ty) {
+            Type::Literal(_) => true,
+            Type::Union(types) | Type::Intersection(types) => {
+                types.iter().any(|t| self.mentions_literal(t))
+            },
+            _ => false,
+        }
+    }
+
+    /// 期望类型包含字面量类型时，字面量表达式取其字面量类型而不是基础类型；
+    /// 期望定长数值类型时，数值字面量取该数值类型
+    fn contextual_type(&mut self, expr: &Expression, ty: Type, expected: &Type) -> Type {
+        match Self::literal_type(expr) {
+            Some(literal) if self.mentions_literal(expected) => Type::Literal(literal),
+            _ => self.numeric_literal_type(expr, ty, expected),
+        }
+    }
+
+    /// 期望类型 (或联合类型中) 的数值类型，number 按 i32 检查字面量范围
+    fn expected_numeric(&self, expected: &Type) -> Option<Type> {
+        match self.resolve(expected) {
+            ty if ty.is_numeric() => Some(ty),
+            Type::Union(types) => types.iter().find_map(|t| self.expected_numeric(t)),
+            _ => None,
+        }
+    }
+
+    /// 数值字面量按期望的数值类型检查范围并重新记录类型，数组字面量逐个元素处理
+    fn numeric_literal_type(&mut self, expr: &Expression, ty: Type, expected: &Type) -> Type {
+        if let Expression::ArrayLiteral { elements, span } = expr {
+            let Type::Array(elem) = self.resolve(expected) else {
+                return ty;
+            };
+            let Type::Array(actual) = &ty else {
+                return ty;
+            };
+            if elements.is_empty() || self.expected_numeric(&elem).is_none() {
+                return ty;
+            }
+            let typed: Vec<Type> = elements
+                .iter()
+                .map(|e| self.numeric_literal_type(e, *actual.clone(), &elem))
+                .collect();
+            if typed.iter().all(|t| self.is_assignable(&elem, t)) {
+                self.info.record(span, Type::Array(elem.clone()));
+                return Type::Array(elem);
+            }
+            return ty;
+        }
+        if let Expression::Ternary { then_expr, else_expr, span, .. } = expr {
+            let then_type = self.numeric_literal_type(then_expr, ty.clone(), expected);
+            let else_type = self.numeric_literal_type(else_expr, ty.clone(), expected);
+            if then_type == else_type && then_type != ty {
+                self.info.record(span, then_type.clone());
+                return then_type;
+            }
+            return ty;
+        }
+        let (value, literal) = match expr {
+            Expression::Number(n, _) => (*n as f64, expr),
+            Expression::Float(f, _) => (*f, expr),
+            Expression::Unary { op: UnaryOp::Negate, operand, .. } => match operand.as_ref() {
+                Expression::Number(n, _) => (-(*n as f64), operand.as_ref()),
+                Expression::Float(f, _) => (-*f, operand.as_ref()),
+                _ => return ty,
+            },
+            _ => return ty,
+        };
+        let Some(target) = self.expected_numeric(expected) else {
+            return ty;
+        };
+        let is_float = matches!(literal, Expression::Float(..));
+        if let Some((bits, signed)) = target.int_width() {
+            if is_float {
+                return ty;
+            }
+            let (min, max) = if signed {
+                (-(2f64.powi(bits as i32 - 1)), 2f64.powi(bits as i32 - 1) - 1.0)
+            } else {
+                (0.0, 2f64.powi(bits as i32) - 1.0)
+            };
+            if value < min || value > max {
+                self.error(
+                    format!("Literal {} is out of range for type '{}'", value, target),
+                    expr.span(),
+                );
+            }
+        }
+        self.info.record(literal.span(), target.clone());
+        self.info.record(expr.span(), target.clone());
+        target
+    }
+
+    /// 参与算术运算时的数值类型：字面量、数值枚举和 any 视为 number
+    fn numeric_type(&self, ty: &Type) -> Type {
+        match self.apparent_type(ty) {
+            ty if ty.is_numeric() => ty,
+            Type::Union(types) => {
+                let members: Vec<Type> = types.iter().map(|t| self.numeric_type(t)).collect();
+                match members.first() {
+                    Some(first) if members.iter().all(|t| t == first) => first.clone(),
+                    _ => Type::Number,
+                }
+            },
+            _ => Type::Number,
+        }
+    }
+
+    /// 两个数值操作数的公共类型，定长类型不同时需要显式转换
+    fn common_numeric(&mut self, op: &BinaryOp, lhs: &Type, rhs: &Type, span: &Span) -> Type {
+        let (left, right) = (self.numeric_type(lhs), self.numeric_type(rhs));
+        let same = |a: &Type, b: &Type| a == b || matches!((a, b), (Type::Number, Type::I32));
+        if same(&left, &right) || same(&right, &left) {
+            // number 与 i32 相同，结果取定长的写法
+            return if left == Type::Number { right } else { left };
+        }
+        if *lhs == Type::Any || *rhs == Type::Any {
+            return if *lhs == Type::Any { right } else { left };
+        }
+        self.error(
+            format!(
+                "Operator {:?} cannot be applied to types '{}' and '{}' without an explicit 'as' conversion",
+                op, left, right
+            ),
+            span,
+        );
+        left
+    }
+
+    /// 字面量表达式对应的字面量类型
+    fn literal_type(expr: &Expression) -> Option<LiteralType> {
+        match expr {
+            Expression::Number(n, _) => Some(LiteralType::Number(*n)),
+            Expression::String(s, _) => Some(LiteralType::String(s.clone())),
+            Expression::Boolean(b, _) => Some(LiteralType::Boolean(*b)),
+            Expression::Unary { op: UnaryOp::Negate, operand, .. } => match operand.as_ref() {
+                Expression::Number(n, _) => Some(LiteralType::Number(-n)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// 把字面量类型放宽为基础类型 (无类型注解的变量)
+    fn widen(ty: Type) -> Type {
+        match ty {
+            Type::Literal(literal) => literal.base_type(),
+            Type::Array(elem) => Type::Array(Box::new(Self::widen(*elem))),
+            Type::Object(fields) => {
+                Type::Object(fields.into_iter().map(|(n, t)| (n, Self::widen(t))).collect())
+            },
+            other => other,
+        }
+    }
+
+    /// 合并为联合类型，去掉重复成员并展开嵌套的联合
+    fn union_of(types: Vec<Type>) -> Type {
+        let mut members: Vec<Type> = Vec::new();
+        for ty in types {
+            let nested = match ty {
+                Type::Union(nested) => nested,
+                other => vec![other],
+            };
+            for ty in nested {
+                if !members.contains(&ty) {
+                    members.push(ty);
+                }
+            }
+        }
+        if members.len() == 1 {
+            members.remove(0)
+        } else {
+            Type::Union(members)
+        }
+    }
+
+    // ============ 泛型 ============
+
+    /// 进入泛型定义的类型参数作用域，并校验约束中引用的类型
+    fn push_type_params(&mut self, params: &[TypeParameter], span: &Span) {
+        self.type_params
+            .push(params.iter().map(|p| (p.name.clone(), p.constraint.clone())).collect());
+        for param in params {
+            if let Some(constraint) = &param.constraint {
+                self.check_type_exists(constraint, span);
+            }
+        }
+    }
+
+    fn pop_type_params(&mut self) {
+        self.type_params.pop();
+    }
+
+    /// 作用域中的类型参数，返回其约束
+    fn type_param(&self, name: &str) -> Option<&Option<Type>> {
+        self.type_params.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// struct / class / 接口的类型参数
+    fn generic_params(&self, name: &str) -> Option<&[TypeParameter]> {
+        match self.structs.get(name) {
+            Some(def) => Some(&def.type_parameters),
+            None => self.interfaces.get(name).map(|def| def.type_parameters.as_slice()),
+        }
+    }
+
+    /// 命名类型的名称及类型参数到类型实参的映射 (`Box<number>` -> `Box`, `T: number`)
+    fn named_type(&self, ty: &Type) -> Option<(String, HashMap<String, Type>)> {
+        match self.resolve(ty) {
+            Type::Struct(name) => Some((name, HashMap::new())),
+            Type::Generic(name, args) => {
+                let bindings = TypeParameter::bind(self.generic_params(&name)?, &args);
+                Some((name, bindings))
+            },
+            _ => None,
+        }
+    }
+
+    /// 展开别名，类型参数按其约束处理 (无约束时保持不变)
+    fn apparent_type(&self, ty: &Type) -> Type {
+        match self.resolve(ty) {
+            Type::Struct(name) => match self.type_param(&name) {
+                Some(Some(constraint)) => self.apparent_type(constraint),
+                _ => Type::Struct(name),
+            },
+            other => other,
+        }
+    }
+
+    /// 类在自身定义内部的类型，泛型类以自己的类型参数实例化
+    fn self_type(&self, class: &str) -> Type {
+        match self.generic_params(class) {
+            Some(params) if !params.is_empty() => Type::Generic(
+                class.to_string(),
+                params.iter().map(|p| Type::Struct(p.name.clone())).collect(),
+            ),
+            _ => Type::Struct(class.to_string()),
+        }
+    }
+
+    /// 确定泛型的类型实参：显式给出，或者把 `expected` 中的类型与实参类型 `actual` 对齐推导
+    ///
+    /// 无法推导的类型参数按 any 处理。
+    fn type_arguments(
+        &mut self,
+        name: &str,
+        params: &[TypeParameter],
+        explicit: &[Type],
+        expected: &[Type],
+        actual: &[Type],
+        span: &Span,
+    ) -> Vec<Type> {
+        let arguments = if explicit.is_empty() {
+            let mut bindings = HashMap::new();
+            for (param, arg) in expected.iter().zip(actual) {
+                self.infer(param, &Self::widen(arg.clone()), params, &mut bindings);
+            }
+            params.iter().map(|p| bindings.remove(&p.name).unwrap_or(Type::Any)).collect()
+        } else {
+            for ty in explicit {
+                self.check_type_exists(ty, span);
+            }
+            if explicit.len() != params.len() {
+                self.error(
+                    format!(
+                        "'{}' expects {} type argument(s), but got {}",
+                        name,
+                        params.len(),
+                        explicit.len()
+                    ),
+                    span,
+                );
+                return vec![Type::Any; params.len()];
+            }
+            explicit.to_vec()
+        };
+        self.check_constraints(params, &arguments, span);
+        arguments
+    }
+
+    /// 校验类型实参满足类型参数的约束
+    fn check_constraints(&mut self, params: &[TypeParameter], arguments: &[Type], span: &Span) {
+        let bindings = TypeParameter::bind(params, arguments);
+        for (param, arg) in params.iter().zip(arguments) {
+            let Some(constraint) = &param.constraint else { continue };
+            let constraint = constraint.substitute(&bindings);
+            if !self.is_assignable(&constraint, arg) {
+                self.error(
+                    format!("Type '{}' does not satisfy the constraint '{}'", arg, constraint),
+                    span,
+                );
+            }
+        }
+    }
+
+    /// 非泛型的调用目标不接受类型实参
+    fn check_not_generic(&mut self, name: &str, type_arguments: &[Type], span: &Span) {
+        if !type_arguments.is_empty() {
+            self.error(format!("'{}' is not generic and takes no type arguments", name), span);
+        }
+    }
+
+    /// 对齐形参类型与实参类型，推导其中出现的类型参数
+    ///
+    /// 同一类型参数多次出现时取更宽的类型；互不兼容时保留先推导出的类型，由参数检查报错。
+    fn infer(
+        &self,
+        param: &Type,
+        arg: &Type,
+        params: &[TypeParameter],
+        bindings: &mut HashMap<String, Type>,
+    ) {
+        let is_param =
+            |ty: &Type| matches!(ty, Type::Struct(n) if params.iter().any(|p| p.name == *n));
+        match (param, arg) {
+            (Type::Struct(name), _) if is_param(param) => {
+                if matches!(arg, Type::Null | Type::Undefined | Type::Never) {
+                    return;
+                }
+                let inferred = match bindings.get(name) {
+                    Some(bound) if self.is_assignable(bound, arg) => return,
+                    Some(bound) if !self.is_assignable(arg, bound) => return,
+                    _ => arg.clone(),
+                };
+                bindings.insert(name.clone(), inferred);
+            },
+            (Type::Array(p), Type::Array(a)) | (Type::Pointer(p), Type::Pointer(a)) => {
+                self.infer(p, a, params, bindings)
+            },
+            (Type::Function(ps, p_ret), Type::Function(args, a_ret)) => {
+                for (p, a) in ps.iter().zip(args) {
+                    self.infer(p, a, params, bindings);
+                }
+                self.infer(p_ret, a_ret, params, bindings);
+            },
+            (Type::Generic(name, ps), Type::Generic(other, args)) if name == other => {
+                for (p, a) in ps.iter().zip(args) {
+                    self.infer(p, a, params, bindings);
+                }
+            },
+            // `T | null`：实参不属于其它成员时推导为其中的类型参数
+            (Type::Union(members), _) => {
+                let (vars, others): (Vec<&Type>, Vec<&Type>) =
+                    members.iter().partition(|t| is_param(t));
+                if others.iter().any(|t| self.is_assignable(t, arg)) {
+                    return;
+                }
+                if let [var] = vars.as_slice() {
+                    self.infer(var, arg, params, bindings);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // ============ 语句 ============
+
+    /// 检查语句
+    fn check_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Block(stmts, _) => {
+                self.push_scope();
+                for s in stmts {
+                    self.check_statement(s);
+                }
+                self.pop_scope();
+            },
+            Statement::VariableDeclaration {
+                name,
+                type_annotation,
+                initializer,
+                mutable,
+                span,
+            } => {
+                let init_type = initializer.as_ref().map(|init| self.check_expression(init));
+                let declared = match (type_annotation, init_type) {
+                    (Some(annotation), Some(init_type)) => {
+                        self.check_type_exists(annotation, span);
+                        let init = initializer.as_ref().expect("initializer");
+                        let init_type = self.contextual_type(init, init_type, annotation);
+                        if !self.is_assignable(annotation, &init_type) {
+                            self.error(
+                                format!(
+                                    "Type '{}' is not assignable to type '{}'",
+                                    init_type, annotation
+                                ),
+                                span,
+                            );
+                        }
+                        annotation.clone()
+                    },
+                    (Some(annotation), None) => {
+                        self.check_type_exists(annotation, span);
+                        annotation.clone()
+                    },
+                    (None, Some(Type::Null | Type::Undefined)) | (None, None) => Type::Any,
+                    (None, Some(init_type)) => Self::widen(init_type),
+                };
+                self.info.record_binding(span, declared.clone());
+                self.declare(name, declared, *mutable);
+            },
+            Statement::Assignment { target, value, span } => {
+                self.check_assignment(target, value, span);
+            },
+            Statement::ExpressionStatement(expr) => {
+                self.check_expression(expr);
+            },
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.check_expression(condition);
+                let narrowing = self.narrow(condition);
+                self.check_narrowed(then_branch, &narrowing.when_true);
+                if let Some(else_branch) = else_branch {
+                    self.check_narrowed(else_branch, &narrowing.when_false);
+                }
+                // 一个分支总是跳出时，if 之后的语句只能从另一个分支到达
+                let else_exits = else_branch.as_ref().is_some_and(|e| Self::always_exits(e));
+                if Self::always_exits(then_branch) && !else_exits {
+                    self.apply_narrowing(&narrowing.when_false);
+                } else if else_exits && !Self::always_exits(then_branch) {
+                    self.apply_narrowing(&narrowing.when_true);
+                }
+            },
+            Statement::While { label, condition, body, .. } => {
+                self.check_expression(condition);
+                let narrowing = self.narrow(condition);
+                self.loop_labels.push(label.clone());
+                self.check_narrowed(body, &narrowing.when_true);
+                self.loop_labels.pop();
+            },
+            Statement::DoWhile { label, body, condition, .. } => {
+                self.loop_labels.push(label.clone());
+                self.check_statement(body);
+                self.loop_labels.pop();
+                self.check_expression(condition);
+            },
+            Statement::For { label, initializer, condition, update, body, .. } => {
+                self.push_scope();
+                self.check_statement(initializer);
+                if let Some(condition) = condition {
+                    self.check_expression(condition);
+                }
+                if let Some(update) = update {
+                    self.check_expression(update);
+                }
+                self.loop_labels.push(label.clone());
+                self.check_statement(body);
+                self.loop_labels.pop();
+                self.pop_scope();
+            },
+            Statement::Switch { value, arms, .. } => {
+                let value_type = self.check_expression(value);
+                for arm in arms {
+                    if let SwitchPattern::Identifier(name) = &arm.pattern {
+                        // 以分支的 Span 记录变量类型，联合类型的 switch 按它放入联合后比较
+                        match self.lookup(name).map(|info| info.ty.clone()) {
+                            Some(ty) => self.info.record(&arm.span, ty),
+                            None => self.error(format!("Cannot find name '{}'", name), &arm.span),
+                        }
+                    }
+                    if let SwitchPattern::EnumMember(enum_name, member) = &arm.pattern {
+                        self.check_enum_pattern(enum_name, member, &value_type, &arm.span);
+                    }
+                    let literal = match &arm.pattern {
+                        SwitchPattern::Number(n) => Some(LiteralType::Number(*n)),
+                        SwitchPattern::String(s) => Some(LiteralType::String(s.clone())),
+                        _ => None,
+                    };
+                    let facts = match literal {
+                        Some(literal) => {
+                            let pattern = Type::Literal(literal.clone());
+                            if !self.is_assignable(&value_type, &pattern)
+                                && !self.is_assignable(&pattern, &value_type)
+                            {
+                                self.error(
+                                    format!(
+                                        "Type '{}' is not comparable to type '{}'",
+                                        pattern, value_type
+                                    ),
+                                    &arm.span,
+                                );
+                            }
+                            // 按判别属性或值本身收窄
+                            self.narrow_by_literal(value, &literal).when_true
+                        },
+                        None => Vec::new(),
+                    };
+                    self.check_narrowed(&arm.body, &facts);
+                }
+            },
+            Statement::Return(value, span) => {
+                let value_type = value.as_ref().map(|v| {
+                    let ty = self.check_expression(v);
+                    match self.current_return.clone() {
+                        Some(expected) => self.contextual_type(v, ty, &expected),
+                        None => ty,
+                    }
+                });
+                if let Some(returns) = &mut self.inferred_returns {
+                    returns.push((value_type.clone().unwrap_or(Type::Void), span.clone()));
+                }
+                let Some(expected) = self.current_return.clone() else {
+                    self.error(
+                        "A 'return' statement can only be used within a function body".to_string(),
+                        span,
+                    );
+                    return;
+                };
+                match value_type {
+                    Some(actual) => {
+                        if expected == Type::Void && actual != Type::Void {
+                            self.error(
+                                format!(
+                                    "Type '{}' is not assignable to return type 'void'",
+                                    actual
+                                ),
+                                span,
+                            );
+                        } else if !self.is_assignable(&expected, &actual) {
+                            self.error(
+                                format!(
+                                    "Type '{}' is not assignable to return type '{}'",
+                                    actual, expected
+                                ),
+                                span,
+                            );
+                        }
+                    },
+                    None => {
+                        if !matches!(expected, Type::Void | Type::Any | Type::Undefined) {
+                            self.error(
+                                format!(
+                                    "A function whose declared type is '{}' must return a value",
+                                    expected
+                                ),
+                                span,
+                            );
+                        }
+                    },
+                }
+            },
+            Statement::Break { label, span } => self.check_jump("break", label, span),
+            Statement::Continue { label, span } => self.check_jump("continue", label, span),
+            Statement::Empty(_) => {},
+            Statement::Throw { value, span } => {
+                // 运行时只能抛出字符串和 number (转换为字符串)，与 catch 变量的 string 类型一致
+                let ty = self.check_expression(value);
+                let throwable = self.resolve(&ty) != Type::Any
+                    && (self.is_string(&ty) || self.is_assignable(&Type::Number, &ty));
+                if !throwable {
+                    self.error(
+                        format!(
+                            "A thrown value must be of type 'string' or 'number', got '{}'",
+                            ty
+                        ),
+                        span,
+                    );
+                }
+            },
+            Statement::TryCatchFinally {
+                try_body, catch_var, catch_body, finally_body, ..
+            } => {
+                self.check_statement(try_body);
+                if let Some(catch_body) = catch_body {
+                    self.push_scope();
+                    if let Some(var) = catch_var {
+                        // 运行时异常值统一表示为字符串
+                        self.declare(var, Type::String, true);
+                    }
+                    self.check_statement(catch_body);
+                    self.pop_scope();
+                }
+                if let Some(finally_body) = finally_body {
+                    self.check_statement(finally_body);
+                }
+            },
+        }
+    }
+
+    /// 检查赋值的目标与值
+    fn check_assignment(&mut self, target: &Expression, value: &Expression, span: &Span) -> Type {
+        let value_type = self.check_expression(value);
+
+        if let Expression::Identifier(name, _) = target {
+            // 赋值后变量恢复为声明类型
+            if let Some(var) = self.lookup_mut(name) {
+                if let Some(declared) = var.narrowed_from.take() {
+                    var.ty = declared;
+                }
+            }
+            if let Some(var) = self.lookup(name) {
+                if !var.mutable {
+                    self.error(
+                        format!("Cannot assign to '{}' because it is a constant", name),
+                        span,
+                    );
+                }
+            }
+        } else if let Expression::Member { object, member, .. } = target {
+            if let Some((name, _)) = self.enum_of(object) {
+                self.error(
+                    format!(
+                        "Cannot assign to '{}.{}' because it is a read-only property",
+                        name, member
+                    ),
+                    span,
+                );
+            }
+            // 命名空间导入的成员只有可变变量可以赋值
+            if let Some(ns) = self.namespace_of(object) {
+                let reason = match self.namespaces.get(&ns).and_then(|exports| exports.get(member))
+                {
+                    Some(ExportedSymbol::Variable(_, _, false) | ExportedSymbol::Constant(_)) => {
+                        Some("a constant")
+                    },
+                    Some(
+                        ExportedSymbol::Function(_)
+                        | ExportedSymbol::Struct(_)
+                        | ExportedSymbol::Interface(_)
+                        | ExportedSymbol::Enum(_)
+                        | ExportedSymbol::TypeAlias(_),
+                    ) => Some("not a variable"),
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    self.error(
+                        format!("Cannot assign to '{}.{}' because it is {}", ns, member, reason),
+                        span,
+                    );
+                }
+            }
+        } else if !matches!(target, Expression::Index { .. }) {
+            self.error("Invalid assignment target".to_string(), span);
+        }
+
+        let target_type = self.check_expression(target);
+        let value_type = self.contextual_type(value, value_type, &target_type);
+        if !self.is_assignable(&target_type, &value_type) {
+            self.error(
+                format!("Type '{}' is not assignable to type '{}'", value_type, target_type),
+                span,
+            );
+        }
+        value_type
+    }
+
+    /// 由函数体中的 return 推导省略的返回类型
+    ///
+    /// 以第一个带值的 return 为准，其余 return 的值必须可以赋值给它，
+    /// 且不能与不带值的 return 混用。
+    fn infer_return_type(&mut self, returns: &[(Type, Span)]) -> Type {
+        let Some(inferred) =
+            returns.iter().find(|(ty, _)| *ty != Type::Void).map(|(ty, _)| Self::widen(ty.clone()))
+        else {
+            return Type::Void;
+        };
+        for (ty, span) in returns {
+            if *ty == Type::Void {
+                self.error(
+                    format!(
+                        "A 'return' without a value cannot be mixed with returns of type '{}'",
+                        inferred
+                    ),
+                    span,
+                );
+            } else if !self.is_assignable(&inferred, ty) {
+                self.error(
+                    format!(
+                        "Type '{}' is not assignable to the inferred return type '{}'",
+                        ty, inferred
+                    ),
+                    span,
+                );
+            }
+        }
+        inferred
+    }
+
+    // ============ 类型收窄 ============
+
+    /// 在收窄后的作用域中检查语句
+    fn check_narrowed(&mut self, stmt: &Statement, facts: &[(String, Type)]) {
+        self.push_scope();
+        self.apply_narrowing(facts);
+        self.check_statement(stmt);
+        self.pop_scope();
+    }
+
+    /// 在收窄后的作用域中检查表达式
+    fn check_narrowed_expression(&mut self, expr: &Expression, facts: &[(String, Type)]) -> Type {
+        self.push_scope();
+        self.apply_narrowing(facts);
+        let ty = self.check_expression(expr);
+        self.pop_scope();
+        ty
+    }
+
+    /// 在当前作用域中以收窄后的类型遮蔽变量，保留其声明类型
+    fn apply_narrowing(&mut self, facts: &[(String, Type)]) {
+        for (name, ty) in facts {
+            let Some(var) = self.lookup(name) else { continue };
+            let declared = var.narrowed_from.clone().unwrap_or_else(|| var.ty.clone());
+            let var =
+                VarInfo { ty: ty.clone(), mutable: var.mutable, narrowed_from: Some(declared) };
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert(name.clone(), var);
+            }
+        }
+    }
+
+    /// 语句执行后是否总是跳出 (return / throw / break / continue)
+    fn always_exits(stmt: &Statement) -> bool {
+        match stmt {
+            Statement::Return(..)
+            | Statement::Throw { .. }
+            | Statement::Break { .. }
+            | Statement::Continue { .. } => true,
+            Statement::Block(stmts, _) => stmts.iter().any(Self::always_exits),
+            Statement::If { then_branch, else_branch: Some(else_branch), .. } => {
+                Self::always_exits(then_branch) && Self::always_exits(else_branch)
+            },
+            _ => false,
+        }
+    }
+
+    /// 分析条件表达式对变量类型的收窄
+    ///
+    /// 支持 `typeof x === "number"`、`x instanceof C`、`x !== null`、
+    /// 判别属性 `x.kind === "circle"`、`"prop" in x`，以及它们的 `!`、`&&`、`||` 组合。
+    fn narrow(&self, condition: &Expression) -> Narrowing {
+        match condition {
+            Expression::Unary { op: UnaryOp::LogicalNot, operand, .. } => {
+                self.narrow(operand).negate()
+            },
+            Expression::Binary { op: BinaryOp::LogicalAnd, left, right, .. } => {
+                let mut when_true = self.narrow(left).when_true;
+                when_true.extend(self.narrow(right).when_true);
+                Narrowing { when_true, when_false: Vec::new() }
+            },
+            Expression::Binary { op: BinaryOp::LogicalOr, left, right, .. } => {
+                let mut when_false = self.narrow(left).when_false;
+                when_false.extend(self.narrow(right).when_false);
+                Narrowing { when_true: Vec::new(), when_false }
+            },
+            Expression::Binary {
+                op:
+                    op @ (BinaryOp::Equals
+                    | BinaryOp::NotEquals
+                    | BinaryOp::StrictEquals
+                    | BinaryOp::StrictNotEquals),
+                left,
+                right,
+                ..
+            } => {
+                let loose = matches!(op, BinaryOp::Equals | BinaryOp::NotEquals);
+                let narrowing = self
+                    .narrow_equality(left, right, loose)
+                    .or_else(|| self.narrow_equality(right, left, loose))
+                    .unwrap_or_default();
+                if matches!(op, BinaryOp::NotEquals | BinaryOp::StrictNotEquals) {
+                    narrowing.negate()
+                } else {
+                    narrowing
+                }
+            },
+            Expression::Instanceof { left, right, .. } => {
+                let (Expression::Identifier(name, _), Expression::Identifier(class, _)) =
+                    (left.as_ref(), right.as_ref())
+                else {
+                    return Narrowing::default();
+                };
+                let Some(ty) = self.lookup(name).map(|v| v.ty.clone()) else {
+                    return Narrowing::default();
+                };
+                let instance = |t: &Type| match self.resolve(t) {
+                    Type::Struct(n) => self.is_subclass(&n, class),
+                    _ => false,
+                };
+                // 接口或父类类型的值在条件成立时收窄为该类
+                let when_true = match self.filter_type(&ty, instance) {
+                    Type::Never => Type::Struct(class.clone()),
+                    narrowed => narrowed,
+                };
+                let when_false = self.filter_type(&ty, |t| !instance(t));
+                Narrowing {
+                    when_true: vec![(name.clone(), when_true)],
+                    when_false: self.union_fact(name, &ty, when_false),
+                }
+            },
+            Expression::In { left, right, .. } => {
+                let (Expression::String(property, _), Expression::Identifier(name, _)) =
+                    (left.as_ref(), right.as_ref())
+                else {
+                    return Narrowing::default();
+                };
+                let Some(ty) = self.lookup(name).map(|v| v.ty.clone()) else {
+                    return Narrowing::default();
+                };
+                let has = |t: &Type| self.property_type(t, property).is_some();
+                Narrowing {
+                    when_true: self.union_fact(name, &ty, self.filter_type(&ty, has)),
+                    when_false: self.union_fact(name, &ty, self.filter_type(&ty, |t| !has(t))),
+                }
+            },
+            // 真值判断排除 null / undefined
+            Expression::Identifier(name, _) => {
+                let Some(ty) = self.lookup(name).map(|v| v.ty.clone()) else {
+                    return Narrowing::default();
+                };
+                let present = self.filter_type(&ty, |t| !matches!(t, Type::Null | Type::Undefined));
+                Narrowing { when_true: self.union_fact(name, &ty, present), when_false: Vec::new() }
+            },
+            _ => Narrowing::default(),
+        }
+    }
+
+    /// `subject == other` 形式的收窄 (subject 为 typeof、变量或判别属性)
+    fn narrow_equality(
+        &self,
+        subject: &Expression,
+        other: &Expression,
+        loose: bool,
+    ) -> Option<Narrowing> {
+        match (subject, other) {
+            (Expression::Typeof { operand, .. }, Expression::String(kind, _))
+            | (
+                Expression::Unary { op: UnaryOp::Typeof, operand, .. },
+                Expression::String(kind, _),
+            ) => {
+                let Expression::Identifier(name, _) = operand.as_ref() else { return None };
+                let ty = self.lookup(name)?.ty.clone();
+                let is_kind = |t: &Type| self.typeof_name(t) == Some(kind.as_str());
+                Some(Narrowing {
+                    when_true: self.union_fact(name, &ty, self.filter_type(&ty, is_kind)),
+                    when_false: self.union_fact(name, &ty, self.filter_type(&ty, |t| !is_kind(t))),
+                })
+            },
+            (Expression::Identifier(name, _), Expression::Null(_) | Expression::Undefined(_)) => {
+                let ty = self.lookup(name)?.ty.clone();
+                // == null 同时匹配 null 和 undefined
+                let is_null = |t: &Type| match (t, other) {
+                    (Type::Null, Expression::Null(_))
+                    | (Type::Undefined, Expression::Undefined(_)) => true,
+                    (Type::Null | Type::Undefined, _) => loose,
+                    _ => false,
+                };
+                Some(Narrowing {
+                    when_true: self.union_fact(name, &ty, self.filter_type(&ty, is_null)),
+                    when_false: self.union_fact(name, &ty, self.filter_type(&ty, |t| !is_null(t))),
+                })
+            },
+            (_, _) => {
+                let literal = Self::literal_type(other)?;
+                Some(self.narrow_by_literal(subject, &literal))
+            },
+        }
+    }
+
+    /// 与字面量比较时的收窄：`x === "a"` 收窄变量本身，`x.kind === "a"` 按判别属性收窄
+    fn narrow_by_literal(&self, subject: &Expression, literal: &LiteralType) -> Narrowing {
+        let pattern = Type::Literal(literal.clone());
+        match subject {
+            Expression::Identifier(name, _) => {
+                let Some(ty) = self.lookup(name).map(|v| v.ty.clone()) else {
+                    return Narrowing::default();
+                };
+                let matches = |t: &Type| self.is_assignable(t, &pattern);
+                Narrowing {
+                    when_true: self.union_fact(name, &ty, self.filter_type(&ty, matches)),
+                    when_false: self.union_fact(
+                        name,
+                        &ty,
+                        self.filter_type(&ty, |t| self.resolve(t) != pattern),
+                    ),
+                }
+            },
+            Expression::Member { object, member, .. } => {
+                let Expression::Identifier(name, _) = object.as_ref() else {
+                    return Narrowing::default();
+                };
+                let Some(ty) = self.lookup(name).map(|v| v.ty.clone()) else {
+                    return Narrowing::default();
+                };
+                let discriminant = |t: &Type| self.property_type(t, member);
+                let matches =
+                    |t: &Type| discriminant(t).is_some_and(|d| self.is_assignable(&d, &pattern));
+                let excluded =
+                    |t: &Type| discriminant(t).is_some_and(|d| self.resolve(&d) == pattern);
+                Narrowing {
+                    when_true: self.union_fact(name, &ty, self.filter_type(&ty, matches)),
+                    when_false: self.union_fact(name, &ty, self.filter_type(&ty, |t| !excluded(t))),
+                }
+            },
+            _ => Narrowing::default(),
+        }
+    }
+
+    /// 只有联合类型的变量会被收窄 (其他类型收窄后不会更精确)
+    fn union_fact(&self, name: &str, declared: &Type, narrowed: Type) -> Vec<(String, Type)> {
+        if matches!(self.resolve(declared), Type::Union(_)) {
+            vec![(name.to_string(), narrowed)]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// 联合类型展开为成员 (嵌套的联合别名一并展开)
+    fn union_member_types(&self, ty: &Type) -> Vec<Type> {
+        match self.resolve(ty) {
+            Type::Union(types) => types.iter().flat_map(|t| self.union_member_types(t)).collect(),
+            _ => vec![ty.clone()],
+        }
+    }
+
+    /// 保留满足条件的联合成员，没有成员时为 never
+    fn filter_type(&self, ty: &Type, keep: impl Fn(&Type) -> bool) -> Type {
+        let members: Vec<Type> =
+            self.union_member_types(ty).into_iter().filter(|t| keep(t)).collect();
+        if members.is_empty() {
+            Type::Never
+        } else {
+            Self::union_of(members)
+        }
+    }
+
+    /// 类型在运行时的 typeof 结果
+    fn typeof_name(&self, ty: &Type) -> Option<&'static str> {
+        if let Some(kind) = self.enum_kind(ty) {
+            return Some(if kind == EnumKind::Number { "number" } else { "string" });
+        }
+        match self.resolve(ty) {
+            Type::Literal(LiteralType::Number(_)) => Some("number"),
+            ty if ty.is_numeric() => Some("number"),
+            Type::String | Type::Literal(LiteralType::String(_)) => Some("string"),
+            Type::Boolean | Type::Literal(LiteralType::Boolean(_)) => Some("boolean"),
+            Type::Undefined | Type::Void => Some("undefined"),
+            Type::Function(..) => Some("function"),
+            Type::Null | Type::Struct(_) | Type::Object(_) | Type::Array(_) | Type::Pointer(_) => {
+                Some("object")
+            },
+            _ => None,
+        }
+    }
+
+    /// 检查 switch 中的枚举成员模式
+    fn check_enum_pattern(&mut self, enum_name: &str, member: &str, value: &Type, span: &Span) {
+        let Some(def) = self.enums.get(enum_name) else {
+            self.error(format!("Cannot find enum '{}'", enum_name), span);
+            return;
+        };
+        if def.member(member).is_none() {
+            self.error(
+                format!("Property '{}' does not exist on enum '{}'", member, enum_name),
+                span,
+            );
+            return;
+        }
+        let pattern = Type::Struct(enum_name.to_string());
+        if !self.is_assignable(value, &pattern) && !self.is_assignable(&pattern, value) {
+            self.error(format!("Type '{}' is not comparable to type '{}'", enum_name, value), span);
+        }
+    }
+
+    // ============ 表达式 ============
+
+    /// 检查表达式并返回其类型，同时记录到类型信息中
+    fn check_expression(&mut self, expr: &Expression) -> Type {
+        let ty = self.infer_expression(expr);
+        self.info.record(expr.span(), ty.clone());
+        ty
+    }
+
+    fn infer_expression(&mut self, expr: &Expression) -> Type {
+        match expr {
+            Expression::Number(_, _) => Type::Number,
+            Expression::Float(_, _) => Type::F64,
+            Expression::String(_, _) => Type::String,
+            Expression::Boolean(_, _) => Type::Boolean,
+            Expression::Null(_) => Type::Null,
+            Expression::Undefined(_) => Type::Undefined,
+            Expression::Identifier(name, span) => {
+                if let Some(var) = self.lookup(name) {
+                    let ty = var.ty.clone();
+                    if let Some(declared) = var.narrowed_from.clone() {
+                        self.info.record_declared(span, declared);
+                    }
+                    ty
+                } else if let Some(sig) = self.functions.get(name).cloned() {
+                    if !sig.type_params.is_empty() {
+                        self.error(
+                            format!("Generic function '{}' can only be called directly", name),
+                            span,
+                        );
+                        return Type::Any;
+                    }
+                    match sig.params {
+                        Some(params) => Type::Function(params, Box::new(sig.return_type)),
+                        None => Type::Any,
+                    }
+                } else if let Some(def) = self.enums.get(name) {
+                    let message = if def.is_const {
+                        "'const' enums can only be used in property access expressions"
+                    } else {
+                        "Enums can only be used in property access expressions"
+                    };
+                    self.error(message.to_string(), span);
+                    Type::Any
+                } else {
+                    self.error(format!("Cannot find name '{}'", name), span);
+                    Type::Any
+                }
+            },
+            Expression::This(span) => match &self.current_class {
+                Some(class) => self.self_type(class),
+                None => {
+                    self.error("'this' can only be used inside a class".to_string(), span);
+                    Type::Any
+                },
+            },
+            Expression::Super(span) => {
+                let base = self
+                    .current_class
+                    .as_ref()
+                    .and_then(|class| self.structs.get(class).and_then(|def| def.extends.clone()));
+                match base {
+                    Some(base) => Type::Struct(base),
+                    None => {
+                        self.error(
+                            "'super' can only be used inside a class that extends another class"
+                                .to_string(),
+                            span,
+                        );
+                        Type::Any
+                    },
+                }
+            },
+            Expression::Assignment { target, value, span } => {
+                self.check_assignment(target, value, span)
+            },
+            Expression::Binary {
+                op: op @ (BinaryOp::LogicalAnd | BinaryOp::LogicalOr),
+                left,
+                right,
+                span,
+            } => {
+                // 右侧只在左侧为真 (&&) 或为假 (||) 时求值
+                let lhs = self.check_expression(left);
+                let narrowing = self.narrow(left);
+                let facts = match op {
+                    BinaryOp::LogicalAnd => narrowing.when_true,
+                    _ => narrowing.when_false,
+                };
+                let rhs = self.check_narrowed_expression(right, &facts);
+                self.binary_result(op, &lhs, &rhs, span)
+            },
+            Expression::Binary { op, left, right, span } => {
+                let mut lhs = self.check_expression(left);
+                let mut rhs = self.check_expression(right);
+                if matches!(
+                    op,
+                    BinaryOp::Equals
+                        | BinaryOp::NotEquals
+                        | BinaryOp::StrictEquals
+                        | BinaryOp::StrictNotEquals
+                ) {
+                    // 与字面量类型比较时，字面量按字面量类型检查重叠
+                    rhs = self.contextual_type(right, rhs, &lhs);
+                    lhs = self.contextual_type(left, lhs, &rhs);
+                } else if !matches!(op, BinaryOp::Concat) {
+                    // 数值字面量取另一侧的定长数值类型
+                    rhs = self.numeric_literal_type(right, rhs, &lhs);
+                    lhs = self.numeric_literal_type(left, lhs, &rhs);
+                }
+                self.binary_result(op, &lhs, &rhs, span)
+            },
+            Expression::Unary { op, operand, span } => {
+                let ty = self.check_expression(operand);
+                match op {
+                    UnaryOp::Negate | UnaryOp::BitNot => {
+                        let numeric = self.numeric_type(&ty);
+                        let is_float = *op == UnaryOp::BitNot && numeric.float_width().is_some();
+                        if !self.is_numeric(&ty) || is_float {
+                            self.error(
+                                format!("Operator {:?} cannot be applied to type '{}'", op, ty),
+                                span,
+                            );
+                        }
+                        numeric
+                    },
+                    UnaryOp::LogicalNot | UnaryOp::Delete => Type::Boolean,
+                    UnaryOp::Typeof => {
+                        self.check_typeof_operand(&ty, span);
+                        Type::String
+                    },
+                    UnaryOp::AddressOf => Type::Pointer(Box::new(ty)),
+                    UnaryOp::Dereference => match ty {
+                        Type::Pointer(inner) => *inner,
+                        Type::Any => Type::Any,
+                        other => {
+                            self.error(format!("Cannot dereference type '{}'", other), span);
+                            Type::Any
+                        },
+                    },
+                }
+            },
+            Expression::Call { callee, type_arguments, arguments, span } => {
+                self.check_call(callee, type_arguments, arguments, span)
+            },
+            Expression::Index { array, index, span } => {
+                let array_type = self.check_expression(array);
+                let index_type = self.check_expression(index);
+                if !self.is_numeric(&index_type) {
+                    self.error(
+                        format!("Type '{}' cannot be used as an index type", index_type),
+                        span,
+                    );
+                }
+                match array_type {
+                    Type::Array(elem) | Type::Pointer(elem) => *elem,
+                    Type::String => Type::String,
+                    Type::Any => Type::Any,
+                    other => {
+                        self.error(format!("Type '{}' cannot be indexed", other), span);
+                        Type::Any
+                    },
+                }
+            },
+            Expression::Member { object, member, span } => self.check_member(object, member, span),
+            Expression::New { type_name, type_arguments, args, span } => {
+                let arg_types: Vec<Type> = args.iter().map(|a| self.check_expression(a)).collect();
+                let Some(def) = self.structs.get(type_name) else {
+                    self.error(format!("Cannot find class '{}'", type_name), span);
+                    return Type::Any;
+                };
+                let params = def.type_parameters.clone();
+                if params.is_empty() {
+                    self.check_not_generic(type_name, type_arguments, span);
+                    self.check_constructor_call(type_name, args, &arg_types, &HashMap::new(), span);
+                    return Type::Struct(type_name.clone());
+                }
+                // 泛型类按构造函数参数推导类型实参
+                let expected: Vec<Type> = self
+                    .find_constructor(type_name)
+                    .map(|c| c.parameters.iter().map(|p| p.type_annotation.clone()).collect())
+                    .unwrap_or_default();
+                let arguments = self.type_arguments(
+                    type_name,
+                    &params,
+                    type_arguments,
+                    &expected,
+                    &arg_types,
+                    span,
+                );
+                let bindings = TypeParameter::bind(&params, &arguments);
+                self.check_constructor_call(type_name, args, &arg_types, &bindings, span);
+                Type::Generic(type_name.clone(), arguments)
+            },
+            Expression::StructLiteral { name, type_arguments, fields, span } => {
+                let field_types: Vec<(String, Type)> = fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), self.check_expression(value)))
+                    .collect();
+                if name.is_empty() {
+                    // 对象字面量保留字段的字面量类型，以便赋给带字面量字段的类型
+                    let field_types = fields
+                        .iter()
+                        .zip(field_types)
+                        .map(|((_, value), (field, ty))| match Self::literal_type(value) {
+                            Some(literal) => (field, Type::Literal(literal)),
+                            None => (field, ty),
+                        })
+                        .collect();
+                    return Type::Object(field_types);
+                }
+                let Some(def) = self.structs.get(name).cloned() else {
+                    self.error(format!("Cannot find struct '{}'", name), span);
+                    return Type::Any;
+                };
+                // 泛型 struct 按字段值推导类型实参
+                let arguments = if def.type_parameters.is_empty() {
+                    self.check_not_generic(name, type_arguments, span);
+                    Vec::new()
+                } else {
+                    let (expected, actual): (Vec<Type>, Vec<Type>) = field_types
+                        .iter()
+                        .filter_map(|(field, ty)| {
+                            let def_field = def.fields.iter().find(|f| f.name == *field)?;
+                            Some((def_field.field_type.clone(), ty.clone()))
+                        })
+                        .unzip();
+                    let params = &def.type_parameters;
+                    self.type_arguments(name, params, type_arguments, &expected, &actual, span)
+                };
+                let bindings = TypeParameter::bind(&def.type_parameters, &arguments);
+                for ((_, value), (field, ty)) in fields.iter().zip(&field_types) {
+                    match def.fields.iter().find(|f| f.name == *field) {
+                        Some(expected) => {
+                            let expected = expected.field_type.substitute(&bindings);
+                            let ty = &self.contextual_type(value, ty.clone(), &expected);
+                            if !self.is_assignable(&expected, ty) {
+                                self.error(
+                                    format!(
+                                        "Type '{}' is not assignable to type '{}' of field '{}'",
+                                        ty, expected, field
+                                    ),
+                                    span,
+                                );
+                            }
+                        },
+                        None => self.error(
+                            format!("Field '{}' does not exist on struct '{}'", field, name),
+                            span,
+                        ),
+                    }
+                }
+                for expected in &def.fields {
+                    if !field_types.iter().any(|(field, _)| *field == expected.name) {
+                        self.error(
+                            format!("Missing field '{}' in struct '{}'", expected.name, name),
+                            span,
+                        );
+                    }
+                }
+                if arguments.is_empty() {
+                    Type::Struct(name.clone())
+                } else {
+                    Type::Generic(name.clone(), arguments)
+                }
+            },
+            Expression::FunctionExpression { parameters, return_type, body, span, .. } => {
+                self.push_scope();
+                for param in parameters {
+                    self.check_type_exists(&param.type_annotation, span);
+                    self.declare(&param.name, param.type_annotation.clone(), true);
+                }
+                // 省略返回类型 (any) 时根据 return 语句推导
+                let infer = **return_type == Type::Any;
+                let previous_inferred =
+                    std::mem::replace(&mut self.inferred_returns, infer.then(Vec::new));
+                let previous = self.current_return.replace((**return_type).clone());
+                // 函数体内的 break / continue 不能跳出到外层函数的循环
+                let loops = std::mem::take(&mut self.loop_labels);
+                self.check_statement(body);
+                self.loop_labels = loops;
+                self.current_return = previous;
+                let inferred = std::mem::replace(&mut self.inferred_returns, previous_inferred);
+                self.pop_scope();
+
+                let return_type = match inferred {
+                    Some(returns) => self.infer_return_type(&returns),
+                    None => (**return_type).clone(),
+                };
+                Type::Function(
+                    parameters.iter().map(|p| p.type_annotation.clone()).collect(),
+                    Box::new(return_type),
+                )
+            },
+            Expression::ArrayLiteral { elements, span } => {
+                let types: Vec<Type> = elements.iter().map(|e| self.check_expression(e)).collect();
+                let Some(first) = types.first().cloned() else {
+                    return Type::Array(Box::new(Type::Any));
+                };
+                for ty in &types[1..] {
+                    if !self.is_assignable(&first, ty) {
+                        self.error(
+                            format!("Array element of type '{}' does not match '{}'", ty, first),
+                            span,
+                        );
+                    }
+                }
+                Type::Array(Box::new(first))
+            },
+            Expression::Ternary { condition, then_expr, else_expr, span } => {
+                self.check_expression(condition);
+                let narrowing = self.narrow(condition);
+                let then_type = self.check_narrowed_expression(then_expr, &narrowing.when_true);
+                let else_type = self.check_narrowed_expression(else_expr, &narrowing.when_false);
+                if self.is_assignable(&then_type, &else_type) {
+                    then_type
+                } else if self.is_assignable(&else_type, &then_type) {
+                    else_type
+                } else {
+                    self.error(
+                        format!(
+                            "Conditional branches have incompatible types '{}' and '{}'",
+                            then_type, else_type
+                        ),
+                        span,
+                    );
+                    then_type
+                }
+            },
+            Expression::Cast { expression, target, span } => {
+                let source = self.check_expression(expression);
+                self.check_type_exists(target, span);
+                // 数值 (含数值枚举和布尔值) 之间可以任意转换，其余只能转换为可赋值的类型
+                let numeric = (self.is_numeric(&source) || self.resolve(&source) == Type::Boolean)
+                    && self.is_numeric(target);
+                if !numeric && !self.is_assignable(target, &source) {
+                    self.error(
+                        format!(
+                            "Conversion of type '{}' to type '{}' is not supported",
+                            source, target
+                        ),
+                        span,
+                    );
+                }
+                target.clone()
+            },
+            Expression::Typeof { operand, span } => {
+                let ty = self.check_expression(operand);
+                self.check_typeof_operand(&ty, span);
+                Type::String
+            },
+            Expression::Instanceof { left, right, .. } | Expression::In { left, right, .. } => {
+                self.check_expression(left);
+                if !matches!(right.as_ref(), Expression::Identifier(name, _) if self.structs.contains_key(name))
+                {
+                    self.check_expression(right);
+                }
+                Type::Boolean
+            },
+            Expression::Delete { operand, .. } => {
+                self.check_expression(operand);
+                Type::Boolean
+            },
+            Expression::NullishCoalescing { left, right, .. } => {
+                let left_type = self.check_expression(left);
+                let right_type = self.check_expression(right);
+                match self.resolve(&left_type) {
+                    Type::Null | Type::Undefined => right_type,
+                    // 去掉联合类型中的 null / undefined 成员
+                    Type::Union(types) => {
+                        let mut present: Vec<Type> = types
+                            .into_iter()
+                            .filter(|t| !matches!(t, Type::Null | Type::Undefined))
+                            .collect();
+                        present.push(right_type);
+                        Self::union_of(present)
+                    },
+                    _ => left_type,
+                }
+            },
+            Expression::OptionalChain { base, chains, .. } => {
+                self.check_expression(base);
+                for chain in chains {
+                    match chain {
+                        OptionalChainItem::Call(args) => {
+                            for arg in args {
+                                self.check_expression(arg);
+                            }
+                        },
+                        OptionalChainItem::Index(index) => {
+                            self.check_expression(index);
+                        },
+                        OptionalChainItem::Member(_) => {},
+                    }
+                }
+                Type::Any
+            },
+        }
+    }
+
+    /// 推导二元运算结果类型
+    fn binary_result(&mut self, op: &BinaryOp, lhs: &Type, rhs: &Type, span: &Span) -> Type {
+        match op {
+            BinaryOp::Add => {
+                if self.is_string(lhs) || self.is_string(rhs) {
+                    Type::String
+                } else if self.is_numeric(lhs) && self.is_numeric(rhs) {
+                    self.common_numeric(op, lhs, rhs, span)
+                } else {
+                    self.error(
+                        format!("Operator '+' cannot be applied to types '{}' and '{}'", lhs, rhs),
+                        span,
+                    );
+                    Type::Any
+                }
+            },
+            BinaryOp::Concat => Type::String,
+            BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulo
+            | BinaryOp::Exponent
+            | BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::LeftShift
+            | BinaryOp::RightShift
+            | BinaryOp::UnsignedRightShift => {
+                if !self.is_numeric(lhs) || !self.is_numeric(rhs) {
+                    self.error(
+                        format!(
+                            "Operator {:?} cannot be applied to types '{}' and '{}'",
+                            op, lhs, rhs
+                        ),
+                        span,
+                    );
+                    return Type::Number;
+                }
+                let ty = self.common_numeric(op, lhs, rhs, span);
+                // 位运算和移位只适用于整数
+                let bitwise = !matches!(
+                    op,
+                    BinaryOp::Subtract
+                        | BinaryOp::Multiply
+                        | BinaryOp::Divide
+                        | BinaryOp::Modulo
+                        | BinaryOp::Exponent
+                );
+                if bitwise && ty.float_width().is_some() {
+                    self.error(
+                        format!("Operator {:?} cannot be applied to type '{}'", op, ty),
+                        span,
+                    );
+                }
+                ty
+            },
+            BinaryOp::LessThan
+            | BinaryOp::LessThanOrEqual
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterThanOrEqual => {
+                let comparable = (self.is_numeric(lhs) && self.is_numeric(rhs))
+                    || ((self.is_string(lhs) || *lhs == Type::Any)
+                        && (self.is_string(rhs) || *rhs == Type::Any));
+                if !comparable {
+                    self.error(format!("Cannot compare types '{}' and '{}'", lhs, rhs), span);
+                } else if self.is_numeric(lhs) && self.is_numeric(rhs) {
+                    self.common_numeric(op, lhs, rhs, span);
+                }
+                Type::Boolean
+            },
+            BinaryOp::Equals
+            | BinaryOp::NotEquals
+            | BinaryOp::StrictEquals
+            | BinaryOp::StrictNotEquals => {
+                if !self.is_assignable(lhs, rhs) && !self.is_assignable(rhs, lhs) {
+                    self.error(format!("Types '{}' and '{}' have no overlap", lhs, rhs), span);
+                } else if self.resolve(lhs).is_numeric() && self.resolve(rhs).is_numeric() {
+                    // 不同宽度的数值比较同样需要显式转换
+                    self.common_numeric(op, lhs, rhs, span);
+                }
+                Type::Boolean
+            },
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr => Type::Boolean,
+        }
+    }
+
+    /// 检查函数调用
+    fn check_call(
+        &mut self,
+        callee: &Expression,
+        type_arguments: &[Type],
+        arguments: &[Expression],
+        span: &Span,
+    ) -> Type {
+        let arg_types: Vec<Type> = arguments.iter().map(|a| self.check_expression(a)).collect();
+
+        // super(...) 调用父类构造函数
+        if let Expression::Super(_) = callee {
+            if let Type::Struct(base) = self.check_expression(callee) {
+                self.check_constructor_call(&base, arguments, &arg_types, &HashMap::new(), span);
+            }
+            return Type::Void;
+        }
+
+        // 直接按名称调用已知函数
+        if let Expression::Identifier(name, _) = callee {
+            if self.lookup(name).is_none() {
+                if let Some(sig) = self.functions.get(name).cloned() {
+                    let (params, return_type) = match sig.params {
+                        // 泛型函数：确定类型实参后替换签名中的类型参数
+                        Some(params) if !sig.type_params.is_empty() => {
+                            let type_params = &sig.type_params;
+                            let args = self.type_arguments(
+                                name,
+                                type_params,
+                                type_arguments,
+                                &params,
+                                &arg_types,
+                                span,
+                            );
+                            let bindings = TypeParameter::bind(type_params, &args);
+                            self.info.record_instantiation(span, args);
+                            let params = params.iter().map(|p| p.substitute(&bindings)).collect();
+                            (Some(params), sig.return_type.substitute(&bindings))
+                        },
+                        params => {
+                            self.check_not_generic(name, type_arguments, span);
+                            (params, sig.return_type)
+                        },
+                    };
+                    self.info.record(
+                        callee.span(),
+                        params
+                            .clone()
+                            .map(|p| Type::Function(p, Box::new(return_type.clone())))
+                            .unwrap_or(Type::Any),
+                    );
+                    if let Some(params) = &params {
+                        self.check_arguments(name, params, arguments, &arg_types, span);
+                    }
+                    return return_type;
+                }
+            }
+        }
+        self.check_not_generic("function", type_arguments, span);
+
+        // console.log 等命名空间形式的内置调用
+        if let Expression::Member { object, member, .. } = callee {
+            if let Expression::Identifier(ns, _) = object.as_ref() {
+                if self.lookup(ns).is_none() {
+                    let qualified = format!("{}.{}", ns, member);
+                    if let Some(sig) = self.functions.get(&qualified).cloned() {
+                        if let Some(params) = &sig.params {
+                            self.check_arguments(&qualified, params, arguments, &arg_types, span);
+                        }
+                        return sig.return_type;
+                    }
+                }
+            }
+        }
+
+        match self.check_expression(callee) {
+            Type::Function(params, ret) => {
+                let name = match callee {
+                    Expression::Member { member, .. } => member.clone(),
+                    _ => "function".to_string(),
+                };
+                self.check_arguments(&name, &params, arguments, &arg_types, span);
+                *ret
+            },
+            Type::Any => Type::Any,
+            other => {
+                self.error(format!("Type '{}' is not callable", other), span);
+                Type::Any
+            },
+        }
+    }
+
+    /// 检查构造函数参数，未定义构造函数时沿继承链使用父类的构造函数
+    ///
+    /// `bindings` 为泛型类的类型实参。
+    fn check_constructor_call(
+        &mut self,
+        class: &str,
+        arguments: &[Expression],
+        arg_types: &[Type],
+        bindings: &HashMap<String, Type>,
+        span: &Span,
+    ) {
+        let (name, params) = match self.find_constructor(class) {
+            Some(constructor) => (
+                constructor.name.clone(),
+                constructor
+                    .parameters
+                    .iter()
+                    .map(|p| p.type_annotation.substitute(bindings))
+                    .collect(),
+            ),
+            None => (format!("{}_constructor", class), Vec::new()),
+        };
+        self.check_arguments(&name, &params, arguments, arg_types, span);
+    }
+
+    /// 检查调用参数的个数与类型
+    fn check_arguments(
+        &mut self,
+        name: &str,
+        params: &[Type],
+        arguments: &[Expression],
+        args: &[Type],
+        span: &Span,
+    ) {
+        if params.len() != args.len() {
+            self.error(
+                format!("'{}' expects {} argument(s), but got {}", name, params.len(), args.len()),
+                span,
+            );
+            return;
+        }
+        for (i, ((param, arg), expr)) in params.iter().zip(args).zip(arguments).enumerate() {
+            let arg = &self.contextual_type(expr, arg.clone(), param);
+            if !self.is_assignable(param, arg) {
+                self.error(
+                    format!(
+                        "Argument {} of '{}': type '{}' is not assignable to parameter of type '{}'",
+                        i + 1,
+                        name,
+                        arg,
+                        param
+                    ),
+                    span,
+                );
+            }
+        }
+    }
+
+    /// 检查成员访问
+    fn check_member(&mut self, object: &Expression, member: &str, span: &Span) -> Type {
+        // 枚举成员的类型是枚举本身
+        if let Some((name, def)) = self.enum_of(object) {
+            if def.member(member).is_none() {
+                self.error(
+                    format!("Property '{}' does not exist on enum '{}'", member, name),
+                    span,
+                );
+                return Type::Any;
+            }
+            return Type::Struct(name);
+        }
+
+        if let Some(ns) = self.namespace_of(object) {
+            return match self.namespace_member(&ns, member, span) {
+                Some(ExportedSymbol::Function(func)) => {
+                    let sig = Self::signature_of(&func);
+                    Type::Function(sig.params.unwrap_or_default(), Box::new(sig.return_type))
+                },
+                Some(ExportedSymbol::Variable(_, ty, _)) => ty,
+                Some(ExportedSymbol::Enum(EnumDefinition { name, .. })) => {
+                    self.error(
+                        format!(
+                            "Enum '{}.{}' can only be used in property access expressions",
+                            ns, name
+                        ),
+                        span,
+                    );
+                    Type::Any
+                },
+                Some(ExportedSymbol::Struct(StructDefinition { name, .. }))
+                | Some(ExportedSymbol::Interface(InterfaceDefinition { name, .. }))
+                | Some(ExportedSymbol::TypeAlias(TypeAlias { name, .. })) => {
+                    self.error(
+                        format!("'{}.{}' is a type and cannot be used as a value", ns, name),
+                        span,
+                    );
+                    Type::Any
+                },
+                Some(ExportedSymbol::Constant(_)) | None => Type::Any,
+            };
+        }
+
+        // 未声明的标识符作为命名空间（如 io.println），暂不解析
+        if let Expression::Identifier(ns, _) = object {
+            if self.lookup(ns).is_none() && !self.functions.contains_key(ns) {
+                return Type::Any;
+            }
+        }
+
+        let object_type = self.check_expression(object);
+        match self.property_type(&object_type, member) {
+            Some(ty) => ty,
+            None => {
+                let message = match self.resolve(&object_type) {
+                    Type::Object(_) => format!("Property '{}' does not exist on object", member),
+                    _ => format!("Property '{}' does not exist on type '{}'", member, object_type),
+                };
+                self.error(message, span);
+                Type::Any
+            },
+        }
+    }
+
+    /// 类型上属性的类型
+    ///
+    /// 联合类型的每个成员都必须有该属性，结果为各成员属性类型的联合；
+    /// 交叉类型取第一个有该属性的成员。
+    fn property_type(&self, ty: &Type, member: &str) -> Option<Type> {
+        match self.apparent_type(ty) {
+            Type::Struct(name) => self.member_type(&name, member),
+            Type::Generic(name, args) => {
+                let bindings = TypeParameter::bind(self.generic_params(&name)?, &args);
+                self.member_type(&name, member).map(|ty| ty.substitute(&bindings))
+            },
+            Type::Object(fields) => fields.into_iter().find(|(n, _)| n == member).map(|(_, t)| t),
+            Type::Array(_) | Type::String if member == "length" => Some(Type::Number),
+            Type::Literal(literal) => self.property_type(&literal.base_type(), member),
+            Type::Any => Some(Type::Any),
+            Type::Union(types) => {
+                let members: Option<Vec<Type>> =
+                    types.iter().map(|t| self.property_type(t, member)).collect();
+                members.map(Self::union_of)
+            },
+            Type::Intersection(types) => types.iter().find_map(|t| self.property_type(t, member)),
+            _ => None,
+        }
+    }
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexa_parser::Parser;
+
+    fn check(source: &str) -> Result<TypeInfo, Vec<TypeError>> {
+        let program = Parser::new(source).parse_program().expect("parse failed");
+        TypeChecker::new().check_program(&program)
+    }
+
+    #[test]
+    fn test_well_typed_program() {
+        let source = "function add(a: number, b: number): number { return a + b; }
+            function main(): void { let x: number = add(1, 2); println(x); }";
+        assert!(check(source).is_ok());
+    }
+
+    #[test]
+    fn test_assignment_mismatch() {
+        let errors = check("function main(): void { let s: string = 1; }").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("not assignable"));
+    }
+
+    #[test]
+    fn test_call_arguments() {
+        let source = "function f(a: number): number { return a; }
+            function main(): void { f(\"x\"); f(1, 2); }";
+        let errors = check(source).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_return_type() {
+        let errors = check("function f(): string { return 1; }").unwrap_err();
+        assert!(errors[0].message.contains("return type"));
+    }
+
+    #[test]
+    fn test_struct_field_access() {
+        let source = "struct P { x: number, y: number }
+            function f(p: P): number { return p.x; }
+            function g(p: P): number { return p.z; }";
+        let errors = check(source).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("'z'"));
+    }
+
+    #[test]
+    fn test_const_assignment() {
+        let errors = check("function main(): void { const a: number = 1; a = 2; }").unwrap_err();
+        assert!(errors[0].message.contains("constant"));
+    }
+
+    #[test]
+    fn test_unions_and_literal_types() {
+        let source = "struct Circle { kind: \"circle\", radius: number }
+                      struct Square { kind: \"square\", size: number }
+                      type Shape = Circle | Square;
+                      type Dir = \"up\" | \"down\";
+                      function kindOf(s: Shape): string { return s.kind; }
+                      function main(): void {
+                          let d: Dir = \"up\";
+                          d = \"down\";
+                          let s: Shape = Circle { kind: \"circle\", radius: 1 };
+                          let same: boolean = s.kind == \"square\";
+                          let n: number | null = null;
+                          let m: number = n ?? 0;
+                      }";
+        assert!(check(source).is_ok(), "{:?}", check(source).unwrap_err());
+
+        let source = "type Loop = Loop | number;
+                      struct Circle { kind: \"circle\", radius: number }
+                      struct Square { kind: \"square\", size: number }
+                      type Shape = Circle | Square;
+                      function main(): void {
+                          let d: \"up\" | \"down\" = \"left\";
+                          let s: Shape = Square { kind: \"square\", size: 1 };
+                          let r: number = s.radius;
+                          let other: boolean = s.kind == \"triangle\";
+                          let n: number | null = null;
+                          let x: number = n;
+                      }";
+        let errors = check(source).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 5, "{:?}", messages);
+        assert!(messages[0].contains("circularly references itself"));
+        assert!(messages[1].contains("'\"left\"' is not assignable"));
+        assert!(messages[2].contains("'radius' does not exist on type 'Shape'"));
+        assert!(messages[3].contains("no overlap"));
+        assert!(messages[4].contains("'number | null' is not assignable to type 'number'"));
+    }
+
+    #[test]
+    fn test_jumps_outside_loops() {
+        let source = "function main(): void {
+                let i: number = 0;
+                while (i < 3) { i = i + 1; if (i == 2) { continue; } break; }
+                do { break; } while (true);
+                for (let j: number = 0; j < 3; j = j + 1) { if (j == 1) { break; } }
+                outer: while (true) { do { continue outer; } while (true); }
+            }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+
+        let errors = check(
+            "function main(): void {
+                break;
+                while (true) { let f = function (): void { continue; }; break; }
+                outer: while (true) { let f = function (): void { while (true) { break outer; } }; }
+                inner: while (true) { }
+                while (true) { continue inner; }
+            }",
+        )
+        .unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert!(messages[2].contains("cannot find label 'outer'"));
+        assert!(messages[3].contains("'continue' statement can only jump to a label"));
+        assert!(messages[0].contains("'break' statement can only be used within an enclosing loop"));
+        assert!(messages[1].contains("'continue' statement"));
+        assert_eq!(errors[0].span.start.0, 1);
+    }
+
+    #[test]
+    fn test_throw_values() {
+        let source = "enum Code { A = 1 }
+            function main(): void {
+                try { throw \"boom\"; } catch (e) { let message: string = e; }
+                try { throw 42; } catch (e) { throw e; }
+                throw Code.A;
+            }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+
+        let errors = check(
+            "class Error { }
+            function main(): void {
+                throw new Error();
+                throw main;
+                throw 1.5;
+            }",
+        )
+        .unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].contains("must be of type 'string' or 'number', got 'Error'"));
+        assert!(messages[1].contains("got '() => void'"));
+        assert!(messages[2].contains("got 'f64'"));
+    }
+
+    #[test]
+    fn test_narrowing() {
+        let source = "struct Circle { kind: \"circle\", radius: number }
+                      struct Square { kind: \"square\", size: number }
+                      type Shape = Circle | Square;
+                      class Animal { legs: number, constructor(legs: number) { this.legs = legs; } }
+                      class Dog extends Animal {
+                          tricks: number,
+                          constructor(t: number) { super(4); this.tricks = t; }
+                      }
+                      function f(v: number | string, s: Shape, a: Animal, n: number | null): number {
+                          if (typeof v === \"string\") { let t: string = v; } else { let m: number = v; }
+                          if (s.kind === \"circle\") { let r: number = s.radius; }
+                          if (\"size\" in s) { let z: number = s.size; }
+                          if (a instanceof Dog) { let k: number = a.tricks; }
+                          let total: number = 0;
+                          switch (s.kind) { case \"square\" => total = s.size; }
+                          switch (v) { case 1 => total = 1; case \"one\" => total = 2; }
+                          if (n === null) { return 0; }
+                          return n;
+                      }";
+        assert!(check(source).is_ok(), "{:?}", check(source).unwrap_err());
+
+        let source = "function f(v: number | string, n: number | null): number {
+                          if (typeof v === \"number\") { v = \"text\"; let m: number = v; }
+                          let s: string = v;
+                          if (n !== null) { let x: number = n; }
+                          return n;
+                      }";
+        let errors = check(source).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].contains("'number | string' is not assignable to type 'number'"));
+        assert!(messages[1].contains("'number | string' is not assignable to type 'string'"));
+        assert!(messages[2].contains("'number | null' is not assignable to return type 'number'"));
+
+        let errors = check("function f(x: any): string { return typeof x; }").unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].message.contains("typeof cannot be applied to a value of type 'any'"));
+    }
+
+    #[test]
+    fn test_enums() {
+        let source = "enum Color { Red, Green = 5 }
+                      const enum Dir { Up = \"UP\", Down = \"DOWN\" }
+                      function main(): void {
+                          let c: Color = Color.Green;
+                          let n: number = c + 1;
+                          let s: string = Dir.Up;
+                          switch (c) { case Color.Red => n = 0; _ => n = 1; }
+                      }";
+        assert!(check(source).is_ok());
+
+        let source = "enum Color { Red }
+                      const enum Dir { Up = \"UP\" }
+                      function main(): void {
+                          let d: Dir = \"UP\";
+                          let x = Color.Blue;
+                          Color.Red = 1;
+                          let e = Dir;
+                          switch (d) { case Color.Red => x = 0; }
+                      }";
+        let errors = check(source).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 5, "{:?}", messages);
+        assert!(messages[0].contains("not assignable"));
+        assert!(messages[1].contains("'Blue' does not exist on enum 'Color'"));
+        assert!(messages[2].contains("read-only"));
+        assert!(messages[3].contains("'const' enums"));
+        assert!(messages[4].contains("not comparable"));
+    }
+
+    #[test]
+    fn test_arrow_return_type_inferred() {
+        let source = "function main(): void {
+                let inc = (x: number) => x + 1;
+                let n: number = inc(1);
+                let s: string = inc(2);
+            }";
+        let errors = check(source).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("'number' is not assignable to type 'string'"));
+
+        // 每个 return 都按第一个带值的 return 推导出的类型检查
+        let source = "function main(): void {
+                let pick = (b: boolean) => { if (b) { return 1; } return 2; };
+                let n: number = pick(true);
+            }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+        let errors = check(
+            "function main(): void {
+                let f = (b: boolean) => { if (b) { return 1; } return \"x\"; };
+            }",
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0]
+            .message
+            .contains("'string' is not assignable to the inferred return type 'number'"));
+    }
+
+    #[test]
+    fn test_arrow_mixed_bare_return() {
+        let errors = check(
+            "function main(): void {
+                let f = (b: boolean) => { if (b) { return; } return 1; };
+            }",
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0]
+            .message
+            .contains("without a value cannot be mixed with returns of type 'number'"));
+    }
+
+    #[test]
+    fn test_class_constructor_and_methods() {
+        let source = "class Point { x: number, y: number,
+                constructor(x: number, y: number) { this.x = x; this.y = y; }
+                getX(): number { return this.x; }
+                moveBy(dx: number): void { this.x = this.x + dx; } }
+            function main(): void { let p: Point = new Point(1, 2); p.moveBy(3); println(p.getX()); }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+
+        let errors = check(
+            "class Point { x: number,
+                constructor(x: number) { this.x = x; }
+                rename(): void { this.x = \"a\"; } }
+            function main(): void { let p: Point = new Point(1, 2); let s: string = p.getX(); }",
+        )
+        .unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].contains("expects 1 argument(s), but got 2"));
+        assert!(messages[1].contains("Property 'getX' does not exist on type 'Point'"));
+        assert!(messages[2].contains("'string' is not assignable to type 'number'"));
+    }
+
+    #[test]
+    fn test_class_inheritance() {
+        let source = "class A { x: number, constructor(x: number) { this.x = x; }
+                value(): number { return this.x; } }
+            class B extends A { y: number, constructor(x: number) { super(x); this.y = 1; }
+                value(): number { return super.value() + this.y; } }
+            function main(): void { let a: A = new B(1); println(a.value() + a.x); }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+
+        let errors = check(
+            "class A { f(): number { return 1; } }
+            class B extends A { f(): string { return \"\"; } }",
+        )
+        .unwrap_err();
+        assert!(errors[0].message.contains("not compatible"));
+    }
+
+    #[test]
+    fn test_interface_conformance() {
+        let source = "interface Shape { name: string, area(): number }
+            class Square implements Shape { name: string, side: number,
+                area(): number { return this.side * this.side; } }
+            function draw(s: Shape): number { return s.area(); }
+            function main(): void { println(draw(new Square())); }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+
+        let errors = check(
+            "interface Shape { name: string, area(): number }
+            class Bad implements Shape { area(): string { return \"\"; } }",
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("name, area()"), "{}", errors[0].message);
+    }
+
+    #[test]
+    fn test_generics() {
+        let source = "interface Container<T> { read(): T }
+            class Box<T> implements Container<T> { value: T,
+                constructor(value: T) { this.value = value; }
+                read(): T { return this.value; } }
+            struct Pair<A, B> { first: A, second: B, }
+            function identity<T>(x: T): T { return x; }
+            function map<T, U>(items: T[], f: (x: T) => U): U[] { return []; }
+            function longest<T extends string>(a: T, b: T): T { return a; }
+            function unwrap<T>(c: Container<T>): T { return c.read(); }
+            function main(): void {
+                let n: number = identity(1) + identity<number>(2);
+                let b: Box<string> = new Box(\"hi\");
+                let s: string = b.read() + unwrap(b);
+                let p = Pair { first: 1, second: \"two\" };
+                let second: string = p.second;
+                let lengths: number[] = map([\"a\"], (x: string) => 1);
+                println(longest(\"a\", \"bc\"));
+            }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+
+        let errors = check(
+            "function identity<T>(x: T): T { return x; }
+            function longest<T extends string>(a: T): T { return a; }
+            function bad<T>(x: T): number { return x; }
+            class Box<T> { value: T, }
+            function main(): void {
+                let s: string = identity(1);
+                longest(1);
+                identity<number, string>(1);
+                let b: Box = null;
+            }",
+        )
+        .unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 5, "{:?}", messages);
+        assert!(messages[0].contains("'T' is not assignable to return type 'number'"));
+        assert!(messages[1].contains("'number' is not assignable to type 'string'"));
+        assert!(messages[2].contains("does not satisfy the constraint 'string'"));
+        assert!(messages[3].contains("expects 1 type argument(s), but got 2"));
+        assert!(messages[4].contains("requires 1 type argument(s)"));
+    }
+
+    #[test]
+    fn test_enum_casts() {
+        let source = "enum Color { Red, Green = 5 }
+            function main(): void {
+                let small: u8 = 5;
+                let c: Color = small as Color;
+                let n: i32 = Color.Green as i32;
+                let b: u8 = c as u8;
+            }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+
+        let errors = check(
+            "enum Dir { Up = \"UP\" }
+            function main(): void { let d = 1 as Dir; let n = Dir.Up as i32; }",
+        )
+        .unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].contains("Conversion of type 'number' to type 'Dir'"));
+        assert!(messages[1].contains("Conversion of type 'Dir' to type 'i32'"));
+    }
+
+    #[test]
+    fn test_sized_numbers() {
+        let source = "function hash(bytes: u8[], seed: u32): u32 {
+                let h: u32 = seed;
+                let i: number = 0;
+                while (i < bytes.length) {
+                    h = (h ^ (bytes[i] as u32)) * 16777619;
+                    i = i + 1;
+                }
+                return h >>> 1;
+            }
+            function main(): void {
+                let small: i8 = -128;
+                let wide: i64 = small;
+                let big: i64 = 5000000000;
+                let ratio: f32 = 0.5;
+                let precise: f64 = ratio * 2;
+                let n: number = 7;
+                let exact: i32 = n;
+                let truncated: u8 = (300 as u8) + 1;
+                let h: u32 = hash([1, 2, 3], 2166136261);
+                let back: i32 = precise as i32;
+            }";
+        assert!(check(source).is_ok(), "{:?}", check(source).err());
+
+        let errors = check(
+            "function main(): void {
+                let a: u8 = 256;
+                let c: number = 2147483648;
+                let b: i32 = 1.5;
+                let x: u8 = 1;
+                let y: i32 = 2;
+                let sum = x + y;
+                let narrowed: u8 = y;
+                let f: f64 = 1.0;
+                let bits = f & 1;
+                let s = \"1\" as u8;
+            }",
+        )
+        .unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 7, "{:?}", messages);
+        assert!(messages[0].contains("Literal 256 is out of range for type 'u8'"));
+        assert!(messages[1].contains("Literal 2147483648 is out of range for type 'number'"));
+        assert!(messages[2].contains("'f64' is not assignable to type 'i32'"));
+        assert!(messages[3].contains("types 'u8' and 'i32' without an explicit 'as' conversion"));
+        assert!(messages[4].contains("'i32' is not assignable to type 'u8'"));
+        assert!(messages[5].contains("BitAnd cannot be applied to type 'f64'"));
+        assert!(messages[6].contains("Conversion of type 'string' to type 'u8'"));
+    }
+
+    #[test]
+    fn test_namespace_import() {
+        let module = Parser::new(
+            "function twice(n: number): number { return n * 2; } let count: number = 0;",
+        )
+        .parse_program()
+        .unwrap();
+        let mut exports = HashMap::new();
+        exports.insert(
+            "twice".to_string(),
+            ExportedSymbol::Function(Box::new(module.functions[0].clone())),
+        );
+        exports.insert(
+            "count".to_string(),
+            ExportedSymbol::Variable("count".to_string(), Type::Number, true),
+        );
+        let mut symbols = SymbolTable::new();
+        symbols.add_namespace("util".to_string(), exports);
+
+        let source = "function main(): void {
+            let n: number = util.twice(util.count); util.count = n; util.twice(\"x\"); util.missing(); }";
+        let mut checker = TypeChecker::new();
+        checker.add_imported_symbols(&symbols);
+        let errors =
+            checker.check_program(&Parser::new(source).parse_program().unwrap()).unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].message.contains("Argument 1 of 'twice'"));
+        assert!(errors[1].message.contains("no exported member 'missing'"));
+    }
+
+    #[test]
+    fn test_expression_types_recorded() {
+        let source = "function main(): void { let s: string = \"a\" + 1; }";
+        let program = Parser::new(source).parse_program().unwrap();
+        let info = TypeChecker::new().check_program(&program).unwrap();
+        let Statement::Block(stmts, _) = &program.functions[0].body else { panic!() };
+        let Statement::VariableDeclaration { initializer: Some(init), .. } = &stmts[0] else {
+            panic!()
+        };
+        assert_eq!(info.type_of(init), Some(&Type::String));
+    }
+
+    #[test]
+    fn test_type_info_merged_across_programs() {
+        // 两个程序中位置相同的节点编号不同，合并后各自的类型互不覆盖
+        let first = Parser::new("let v = \"a\";").parse_program().unwrap();
+        let second = Parser::new("let v = 1;").parse_program().unwrap();
+        let mut info = TypeChecker::new().check_program(&first).unwrap();
+        info.extend(TypeChecker::new().check_program(&second).unwrap());
+        let binding = |program: &Program| {
+            let Statement::VariableDeclaration { initializer: Some(init), span, .. } =
+                &program.statements[0]
+            else {
+                panic!()
+            };
+            (init.clone(), span.clone())
+        };
+        let (init, span) = binding(&first);
+        assert_eq!(info.type_of(&init), Some(&Type::String));
+        assert_eq!(info.binding_type_of(&span), Some(&Type::String));
+        let (init, span) = binding(&second);
+        assert_eq!(info.type_of(&init), Some(&Type::Number));
+        assert_eq!(info.binding_type_of(&span), Some(&Type::Number));
+    }
+}
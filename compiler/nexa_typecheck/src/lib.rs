@@ -0,0 +1,10 @@
+//! Nexa 类型检查模块
+//!
+//! 位于解析器与代码生成之间：为每个表达式推导类型，
+//! 校验赋值、调用参数、返回值与字段访问，并以带 Span 的错误报告问题。
+
+mod checker;
+mod types;
+
+pub use checker::TypeChecker;
+pub use types::{TypeError, TypeInfo};
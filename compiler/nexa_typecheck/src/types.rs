@@ -0,0 +1,86 @@
+//! 类型检查结果
+//!
+//! 检查通过后产出的类型信息，以及检查失败时的错误定义。
+
+use std::collections::HashMap;
+use std::fmt;
+
+use nexa_parser::ast::{Expression, NodeId, Span, Type};
+
+/// 类型错误
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.span.start.0, self.span.start.1, self.message)
+    }
+}
+
+/// 类型检查结果
+///
+/// 以语法树节点的编号为键记录推导出的类型，供代码生成查询。
+/// 节点编号在所有模块中唯一，各模块的结果可以合并在一起查询。
+#[derive(Debug, Clone, Default)]
+pub struct TypeInfo {
+    expr_types: HashMap<NodeId, Type>,
+    /// 被条件收窄的变量引用 -> 变量的声明类型
+    declared_types: HashMap<NodeId, Type>,
+    /// 变量声明 -> 变量的类型
+    binding_types: HashMap<NodeId, Type>,
+    /// 泛型函数调用 -> 推导或显式给出的类型实参
+    instantiations: HashMap<NodeId, Vec<Type>>,
+}
+
+impl TypeInfo {
+    /// 查询表达式的类型
+    pub fn type_of(&self, expr: &Expression) -> Option<&Type> {
+        self.expr_types.get(&expr.span().id)
+    }
+
+    /// 查询被收窄的变量引用在收窄前的声明类型
+    pub fn declared_type_of(&self, expr: &Expression) -> Option<&Type> {
+        self.declared_types.get(&expr.span().id)
+    }
+
+    /// 查询变量声明 (以声明语句的 Span 标识) 的类型
+    pub fn binding_type_of(&self, declaration: &Span) -> Option<&Type> {
+        self.binding_types.get(&declaration.id)
+    }
+
+    /// 查询泛型函数调用的类型实参
+    pub fn instantiation_of(&self, call: &Expression) -> Option<&Vec<Type>> {
+        self.instantiations.get(&call.span().id)
+    }
+
+    /// 并入另一个模块的检查结果，导入的泛型函数在导入方实例化时按定义所在模块的结果查询
+    pub fn extend(&mut self, other: TypeInfo) {
+        self.expr_types.extend(other.expr_types);
+        self.declared_types.extend(other.declared_types);
+        self.binding_types.extend(other.binding_types);
+        self.instantiations.extend(other.instantiations);
+    }
+
+    /// 记录表达式的类型
+    pub(crate) fn record(&mut self, span: &Span, ty: Type) {
+        self.expr_types.insert(span.id, ty);
+    }
+
+    /// 记录被收窄的变量引用的声明类型
+    pub(crate) fn record_declared(&mut self, span: &Span, ty: Type) {
+        self.declared_types.insert(span.id, ty);
+    }
+
+    /// 记录变量声明的类型
+    pub(crate) fn record_binding(&mut self, span: &Span, ty: Type) {
+        self.binding_types.insert(span.id, ty);
+    }
+
+    /// 记录泛型函数调用的类型实参
+    pub(crate) fn record_instantiation(&mut self, span: &Span, arguments: Vec<Type>) {
+        self.instantiations.insert(span.id, arguments);
+    }
+}
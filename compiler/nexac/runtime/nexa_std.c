@@ -5,6 +5,7 @@
 #include <stdlib.h>
 #include <string.h>
 #include <stdarg.h>
+#include <setjmp.h>
 
 // ============ 基础 IO 函数 ============
 
@@ -100,8 +101,11 @@ char* std_string_copy(const char* src) {
     return strdup(src);
 }
 
-// 字符串比较
+// 字符串比较 (可空字符串的空指针只与空指针相等)
 int std_string_compare(const char* s1, const char* s2) {
+    if (s1 == s2) return 0;
+    if (!s1) return -1;
+    if (!s2) return 1;
     return strcmp(s1, s2);
 }
 
@@ -190,3 +194,57 @@ double std_math_pow(double base, double exp) {
 double std_math_sqrt(double n) {
     return sqrt(n);
 }
+
+// ============ 异常处理 ============
+// 每个 try 块在运行时压入一个帧，生成的代码对帧中的 jmp_buf 调用 setjmp，
+// throw 通过 longjmp 跳回最近的帧。异常值统一保存为字符串。
+
+typedef struct NexaTryFrame {
+    jmp_buf env;
+    struct NexaTryFrame* prev;
+} NexaTryFrame;
+
+static NexaTryFrame* nexa_try_top = NULL;
+static const char* nexa_exception = NULL;
+
+// 进入 try 块 - 压入异常帧，返回供 setjmp 使用的 jmp_buf
+void* nexa_try_push(void) {
+    NexaTryFrame* frame = malloc(sizeof(NexaTryFrame));
+    frame->prev = nexa_try_top;
+    nexa_try_top = frame;
+    return frame->env;
+}
+
+// 离开 try 块 (正常结束或已跳转回来) - 弹出异常帧
+void nexa_try_pop(void) {
+    NexaTryFrame* frame = nexa_try_top;
+    if (frame) {
+        nexa_try_top = frame->prev;
+        free(frame);
+    }
+}
+
+// 抛出异常 - 没有 try 帧时打印异常并以非零状态退出
+void nexa_throw(const char* value) {
+    nexa_exception = value;
+    if (!nexa_try_top) {
+        fprintf(stderr, "Uncaught exception: %s\n", value ? value : "null");
+        exit(1);
+    }
+    longjmp(nexa_try_top->env, 1);
+}
+
+// 抛出整数异常
+void nexa_throw_i32(int value) {
+    nexa_throw(std_string_from_i32(value));
+}
+
+// 重新抛出当前异常 (finally 执行完毕后继续传播)
+void nexa_rethrow(void) {
+    nexa_throw(nexa_exception);
+}
+
+// 获取当前异常值 (绑定到 catch 变量)
+const char* nexa_exception_value(void) {
+    return nexa_exception;
+}
@@ -6,13 +6,26 @@ use inkwell::context::Context;
 use inkwell::targets::Target;
 use inkwell::OptimizationLevel;
 use nexa_codegen::CodeGenerator;
-use nexa_parser::{ModuleLoader, Parser, Program};
+use nexa_parser::ast::Span;
+use nexa_parser::{ImportStep, Module, ModuleError, ModuleLoader, Parser, Program, SymbolTable};
+use nexa_typecheck::{TypeChecker, TypeInfo};
+use std::collections::HashMap;
+use std::path::Path;
 use std::process::Command;
 
-/// 打印解析错误，包含源码位置上下文
-fn print_parse_error(source: &str, error: &nexa_parser::ParseError) {
-    let span = &error.span;
-    eprintln!("error: {}", error.message);
+/// 编译器内置的标准库模块 (模块路径, 源码)，以 `import { println } from "std/io"` 导入
+const STD_MODULES: &[(&str, &str)] = &[
+    ("std/io", include_str!("../std/io.ts")),
+    ("std/math", include_str!("../std/math.ts")),
+    ("std/string", include_str!("../std/string.ts")),
+];
+
+/// 运行时库源码，生成可执行文件时编译并链接
+const RUNTIME_SOURCE: &str = include_str!("../runtime/nexa_std.c");
+
+/// 打印源码错误（解析错误、类型错误），包含源码位置上下文
+fn print_source_error(source: &str, message: &str, span: &Span) {
+    eprintln!("error: {}", message);
     eprintln!("  --> source:{}:{}", span.start.0 + 1, span.start.1 + 1);
 
     // 显示错误行的上下文
@@ -36,6 +49,8 @@ struct CliOptions {
     output_file: Option<String>,
     opt_level: OptimizationLevel,
     target_triple: Option<String>,
+    /// 额外的模块搜索路径 (-I / --module-path)
+    module_paths: Vec<String>,
     output_type: OutputType,
     debug_ast: bool,
     #[allow(dead_code)]
@@ -66,8 +81,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Nexa compiler v0.1.0");
     println!("Compiling: {}", cli.source_file);
 
-    // 创建模块加载器
+    // 创建模块加载器：裸说明符先匹配内置的标准库，再依次在 -I 指定的路径和
+    // 源文件所在目录中查找，模块路径也相对这些目录计算
     let mut module_loader = ModuleLoader::new();
+    for (module_path, module_source) in STD_MODULES {
+        module_loader.add_bundled_module(module_path, module_source);
+    }
+    for path in &cli.module_paths {
+        module_loader.add_search_path(path.clone());
+    }
+    let source_path = Path::new(&cli.source_file);
+    let source_dir = source_path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    module_loader
+        .add_search_path(source_dir.unwrap_or(Path::new(".")).to_string_lossy().into_owned());
 
     // 读取源文件
     let source = std::fs::read_to_string(&cli.source_file)?;
@@ -77,31 +103,65 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let program = match parser.parse_program() {
         Ok(p) => p,
         Err(e) => {
-            print_parse_error(&source, &e);
+            print_source_error(&source, &e.message, &e.span);
             return Err(format!("Parse error: {}", e.message).into());
         },
     };
 
     // 加载并解析导入的模块
-    let mut imported_symbols: Option<nexa_parser::module::SymbolTable> = None;
+    let mut imported_symbols: HashMap<String, SymbolTable> = HashMap::new();
     if !program.imports.is_empty() {
         println!("Loading {} module(s)...", program.imports.len());
-        match module_loader.resolve_imports(&program) {
+        match module_loader.resolve_imports(&program, source_path) {
             Ok(import_symbols_map) => {
-                println!("Loaded modules: {:?}", import_symbols_map.keys().collect::<Vec<_>>());
-                // 将第一个模块的符号表传递给代码生成器
-                if let Some((_, symbols)) = import_symbols_map.iter().next() {
-                    imported_symbols = Some(symbols.clone());
-                }
+                let mut paths: Vec<&String> = import_symbols_map.keys().collect();
+                paths.sort();
+                println!("Loaded modules: {:?}", paths);
+                // 每个模块的符号表以模块路径为键传递给类型检查和代码生成
+                imported_symbols = import_symbols_map;
+            },
+            Err(ModuleError::AmbiguousImport(message)) => {
+                return Err(format!("Import error: {}", message).into());
+            },
+            Err(ModuleError::SymbolNotFound(name, step)) => {
+                print_missing_export(&module_loader, &name, &step);
+                return Err("Import error: imported name is not exported".into());
             },
+            Err(ModuleError::UntypedExport(name, file_path, span)) => {
+                print_untyped_export(&module_loader, &name, &file_path, &span);
+                return Err("Import error: exported variable needs a type annotation".into());
+            },
+            Err(ModuleError::OutsideModuleRoots(file)) => {
+                return Err(outside_roots_error(&file));
+            },
+            // 导入模块的函数体需要一起编译，缺失的模块无法链接
             Err(e) => {
-                eprintln!("Warning: Failed to resolve imports: {:?}", e);
+                return Err(format!("Failed to resolve imports: {:?}", e).into());
             },
         }
     }
 
     println!("Parsed {} functions", program.functions.len());
 
+    // 类型检查
+    let mut checker = TypeChecker::new();
+    for symbols in imported_symbols.values() {
+        checker.add_imported_symbols(symbols);
+    }
+    let type_info = match checker.check_program(&program) {
+        Ok(info) => info,
+        Err(errors) => {
+            for error in &errors {
+                print_source_error(&source, &error.message, &error.span);
+            }
+            return Err(format!("{} type error(s)", errors.len()).into());
+        },
+    };
+
+    // 导入图中的每个模块单独做类型检查，随后与入口模块一起编译链接
+    let (dependencies, entry_path) =
+        check_dependencies(&mut module_loader, &cli.source_file, &program)?;
+
     // 调试：打印 AST
     if cli.debug_ast {
         println!("\n=== AST ===");
@@ -112,11 +172,130 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // 编译并执行
-    compile_and_execute(&program, &cli, imported_symbols)?;
+    compile_and_execute(&program, &cli, imported_symbols, type_info, dependencies, entry_path)?;
 
     Ok(())
 }
 
+/// 导入图中与入口模块一起编译的模块
+struct Dependency {
+    module: Module,
+    imported_symbols: HashMap<String, SymbolTable>,
+    type_info: TypeInfo,
+}
+
+/// 打印循环导入链以及环上每条 import 的位置
+fn print_import_cycle(module_loader: &ModuleLoader, steps: &[ImportStep]) {
+    let mut chain: Vec<&str> = steps.iter().map(|step| step.file_path.as_str()).collect();
+    if let Some(first) = chain.first().copied() {
+        chain.push(first);
+    }
+    eprintln!("error: circular import: {}", chain.join(" -> "));
+    for step in steps {
+        let source = module_loader.read_source(Path::new(&step.file_path)).unwrap_or_default();
+        let message = format!("'{}' imports '{}' here", step.file_path, step.module_path);
+        print_source_error(&source, &message, &step.span);
+    }
+}
+
+/// 打印导入未导出名称的 import 位置
+fn print_missing_export(module_loader: &ModuleLoader, name: &str, step: &ImportStep) {
+    let source = module_loader.read_source(Path::new(&step.file_path)).unwrap_or_default();
+    let message = format!("Module '{}' has no exported member '{}'", step.module_path, name);
+    print_source_error(&source, &message, &step.span);
+}
+
+/// 报告缺少类型注解的导出变量
+fn print_untyped_export(module_loader: &ModuleLoader, name: &str, file_path: &str, span: &Span) {
+    let source = module_loader.read_source(Path::new(file_path)).unwrap_or_default();
+    let message = format!(
+        "Exported variable '{}' needs a type annotation: its type cannot be inferred from the initializer",
+        name
+    );
+    print_source_error(&source, &message, span);
+}
+
+/// 模块文件不在入口文件所在目录和 -I 路径下时的错误
+fn outside_roots_error(file: &str) -> Box<dyn std::error::Error> {
+    format!(
+        "Import error: module '{}' is outside the entry file's directory and every -I path; \
+         add its root directory with -I",
+        file
+    )
+    .into()
+}
+
+/// 加载入口模块的整个导入图并逐个做类型检查，结果按依赖顺序排列
+///
+/// 同时返回入口模块处在循环导入上时被导入使用的模块路径。
+fn check_dependencies(
+    module_loader: &mut ModuleLoader,
+    entry_file: &str,
+    program: &Program,
+) -> Result<(Vec<Dependency>, Option<String>), Box<dyn std::error::Error>> {
+    let graph = match module_loader.load_import_graph(entry_file, program) {
+        Ok(graph) => graph,
+        Err(ModuleError::CircularDependency(steps)) => {
+            print_import_cycle(module_loader, &steps);
+            return Err("Circular import: only functions and classes may be imported \
+                        along an import cycle"
+                .into());
+        },
+        Err(ModuleError::UntypedExport(name, file_path, span)) => {
+            print_untyped_export(module_loader, &name, &file_path, &span);
+            return Err("Import error: exported variable needs a type annotation".into());
+        },
+        Err(ModuleError::OutsideModuleRoots(file)) => return Err(outside_roots_error(&file)),
+        Err(e) => return Err(format!("Failed to load modules: {:?}", e).into()),
+    };
+
+    let mut dependencies = Vec::new();
+    for module in graph.modules {
+        let imported_symbols =
+            match module_loader.resolve_imports(&module.program, Path::new(&module.file_path)) {
+                Ok(symbols) => symbols,
+                Err(ModuleError::SymbolNotFound(name, step)) => {
+                    print_missing_export(module_loader, &name, &step);
+                    return Err(format!(
+                        "Import error in module '{}': imported name is not exported",
+                        module.path
+                    )
+                    .into());
+                },
+                Err(ModuleError::UntypedExport(name, file_path, span)) => {
+                    print_untyped_export(module_loader, &name, &file_path, &span);
+                    return Err("Import error: exported variable needs a type annotation".into());
+                },
+                Err(e) => {
+                    return Err(
+                        format!("Failed to resolve imports of '{}': {:?}", module.path, e).into()
+                    );
+                },
+            };
+
+        let mut checker = TypeChecker::new();
+        for symbols in imported_symbols.values() {
+            checker.add_imported_symbols(symbols);
+        }
+        let type_info = match checker.check_program(&module.program) {
+            Ok(info) => info,
+            Err(errors) => {
+                let source = module_loader
+                    .read_source(Path::new(&module.file_path))
+                    .map_err(|e| format!("Failed to read '{}': {:?}", module.file_path, e))?;
+                for error in &errors {
+                    print_source_error(&source, &error.message, &error.span);
+                }
+                return Err(
+                    format!("{} type error(s) in module '{}'", errors.len(), module.path).into()
+                );
+            },
+        };
+        dependencies.push(Dependency { module, imported_symbols, type_info });
+    }
+    Ok((dependencies, graph.entry_path))
+}
+
 /// 打印使用说明
 fn print_usage(program_name: &str) {
     println!("Usage: {} <source_file> [options]", program_name);
@@ -125,6 +304,7 @@ fn print_usage(program_name: &str) {
     println!("  -o, --output <file>    Output file name");
     println!("  --opt-level <level>    Optimization level: 0, 1, 2, 3, s, z (default: 0)");
     println!("  --target <triple>      Target triple (e.g., x86_64-linux-gnu)");
+    println!("  -I, --module-path <dir> Add a search path for bare module imports");
     println!("  --output-type <type>   Output type: exe, llvm-ir, asm, obj (default: exe)");
     println!("  -d, --debug            Print debug information (AST)");
     println!("  --emit-llvm            Emit LLVM IR");
@@ -143,6 +323,7 @@ fn parse_args(args: &[String]) -> Result<CliOptions, Box<dyn std::error::Error>>
     let mut output_file = None;
     let mut opt_level = OptimizationLevel::None;
     let mut target_triple = None;
+    let mut module_paths = Vec::new();
     let mut output_type = OutputType::Exe;
     let mut debug_ast = false;
     let mut emit_llvm = false;
@@ -190,6 +371,14 @@ fn parse_args(args: &[String]) -> Result<CliOptions, Box<dyn std::error::Error>>
                     return Err("Missing target triple".into());
                 }
             },
+            "-I" | "--module-path" => {
+                if i + 1 < args.len() {
+                    module_paths.push(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    return Err("Missing module path".into());
+                }
+            },
             "--output-type" => {
                 if i + 1 < args.len() {
                     output_type = match args[i + 1].as_str() {
@@ -242,6 +431,7 @@ fn parse_args(args: &[String]) -> Result<CliOptions, Box<dyn std::error::Error>>
         output_file,
         opt_level,
         target_triple,
+        module_paths,
         output_type,
         debug_ast,
         emit_llvm,
@@ -253,7 +443,10 @@ fn parse_args(args: &[String]) -> Result<CliOptions, Box<dyn std::error::Error>>
 fn compile_and_execute(
     program: &Program,
     cli: &CliOptions,
-    imported_symbols: Option<nexa_parser::module::SymbolTable>,
+    imported_symbols: HashMap<String, SymbolTable>,
+    mut type_info: TypeInfo,
+    mut dependencies: Vec<Dependency>,
+    entry_path: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // 初始化 LLVM 目标
     Target::initialize_native(&Default::default())?;
@@ -262,14 +455,19 @@ fn compile_and_execute(
     let context = Context::create();
     let mut codegen = CodeGenerator::new(&context, "nexa_module");
 
-    // 设置导入的符号
-    if let Some(symbols) = imported_symbols {
-        // 将符号表转换为 HashMap 格式
-        let mut imported: std::collections::HashMap<String, nexa_parser::module::SymbolTable> =
-            std::collections::HashMap::new();
-        imported.insert("main".to_string(), symbols);
-        codegen.set_imported_symbols(imported);
+    // 设置导入的符号，main 开始时按依赖顺序执行各模块的顶层语句
+    codegen.set_imported_symbols(imported_symbols);
+    let paths: Vec<String> = dependencies.iter().map(|d| d.module.path.clone()).collect();
+    codegen.set_dependencies(&paths);
+    if let Some(path) = entry_path {
+        codegen.set_entry_module_path(path);
+    }
+
+    // 设置类型检查结果：节点编号在所有模块中唯一，合并后导入的泛型函数体也能查到类型
+    for dependency in &mut dependencies {
+        type_info.extend(std::mem::take(&mut dependency.type_info));
     }
+    codegen.set_type_info(type_info.clone());
 
     // 声明内置函数
     codegen.declare_builtin_functions();
@@ -282,6 +480,22 @@ fn compile_and_execute(
     // 获取生成的模块
     let module = codegen.into_module();
 
+    // 依赖模块各自生成后链接进入口模块
+    for dependency in dependencies {
+        let path = dependency.module.path;
+        let mut codegen = CodeGenerator::new(&context, &path);
+        codegen.set_module_path(path.clone());
+        codegen.set_imported_symbols(dependency.imported_symbols);
+        codegen.set_type_info(type_info.clone());
+        codegen.declare_builtin_functions();
+        codegen
+            .generate_program(&dependency.module.program)
+            .map_err(|e| format!("Code generation error in module '{}': {}", path, e.message))?;
+        module
+            .link_in_module(codegen.into_module())
+            .map_err(|e| format!("Failed to link module '{}': {}", path, e))?;
+    }
+
     // 验证模块
     module.verify().map_err(|e| format!("Module verification failed: {}", e))?;
 
@@ -371,10 +585,9 @@ fn compile_and_execute(
 
                 // 编译运行时库
                 let runtime_obj = temp_dir.join("nexa_std.o");
-                // 从当前工作目录查找运行时文件
-                let runtime_c = std::env::current_dir()
-                    .map_err(|e| format!("Failed to get current dir: {}", e))?
-                    .join("compiler/nexac/runtime/nexa_std.c");
+                // 运行时源码内置在编译器中，不依赖当前工作目录
+                let runtime_c = temp_dir.join("nexa_std.c");
+                std::fs::write(&runtime_c, RUNTIME_SOURCE)?;
 
                 let compile_status = Command::new("clang")
                     .args(["-c", "-O2", "-o"])
@@ -416,6 +629,7 @@ fn compile_and_execute(
 
                 let _ = std::fs::remove_file(&object_file);
                 let _ = std::fs::remove_file(&runtime_obj);
+                let _ = std::fs::remove_file(&runtime_c);
 
                 if link_status.success() {
                     println!("\nCompiled to executable: {}", output);
@@ -448,3 +662,27 @@ fn host_target_triple() -> String {
 
     format!("{llvm_arch}-{vendor}-{env}")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_std_modules_type_check() {
+        // 入口模块导入全部内置模块，按编译时的流程逐个类型检查
+        let source: String = STD_MODULES
+            .iter()
+            .enumerate()
+            .map(|(i, (path, _))| format!("import * as m{} from \"{}\";\n", i, path))
+            .collect();
+        let program = Parser::new(&source).parse_program().unwrap();
+        let mut module_loader = ModuleLoader::new();
+        for (module_path, module_source) in STD_MODULES {
+            module_loader.add_bundled_module(module_path, module_source);
+        }
+        // 入口文件路径只用于定位相对导入，借用本文件的路径
+        let entry = concat!(env!("CARGO_MANIFEST_DIR"), "/src/main.rs");
+        let (dependencies, _) = check_dependencies(&mut module_loader, entry, &program).unwrap();
+        assert_eq!(dependencies.len(), STD_MODULES.len());
+    }
+}
@@ -0,0 +1,31 @@
+// Nexa 标准库 - io 模块
+// 提供输入输出功能
+
+// 运行时提供的函数 (compiler/nexac/runtime/nexa_std.c)
+declare function std_io_println(s: string): number;
+declare function std_io_print(s: string): number;
+declare function std_io_readln(): string;
+declare function std_io_println_i32(n: number): number;
+
+export const VERSION: string = "1.0.0";
+export const MAX_BUFFER: number = 1024;
+
+// 打印字符串并换行
+export function println(s: string): void {
+    std_io_println(s);
+}
+
+// 打印字符串不换行
+export function print(s: string): void {
+    std_io_print(s);
+}
+
+// 读取一行输入
+export function readln(): string {
+    return std_io_readln();
+}
+
+// 打印整数并换行
+export function printNumber(n: number): void {
+    std_io_println_i32(n);
+}
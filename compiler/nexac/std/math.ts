@@ -21,5 +21,5 @@ export function divide(a: number, b: number): number {
     return a / b;
 }
 
-export const PI: number = 3.14159;
-export const E: number = 2.71828;
+export const PI: f64 = 3.14159;
+export const E: f64 = 2.71828;
@@ -1,19 +1,24 @@
 // Nexa 标准库 - string 模块
 // 提供字符串处理功能
 
+// 运行时提供的函数 (compiler/nexac/runtime/nexa_std.c)
+declare function std_string_len(s: string): number;
+declare function std_string_concat(a: string, b: string): string;
+declare function std_string_compare(a: string, b: string): number;
+
 // 获取字符串长度
 export function len(s: string): number {
-    return 0;
+    return std_string_len(s);
 }
 
 // 字符串拼接
 export function concat(a: string, b: string): string {
-    return "";
+    return std_string_concat(a, b);
 }
 
 // 字符串比较
 export function equals(a: string, b: string): boolean {
-    return true;
+    return std_string_compare(a, b) == 0;
 }
 
 // 子字符串
@@ -1,5 +0,0 @@
-// Nexa 标准库 - io 模块
-// 提供输入输出功能
-
-const VERSION: string = "1.0.0";
-const MAX_BUFFER: number = 1024;
@@ -25,6 +25,6 @@ function main(): void {
     
     // 演示输入功能
     println("Enter a number:");
-    let input: number = readln();
+    let input: string = readln();
     println("You entered: " + input);
 }
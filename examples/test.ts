@@ -1,3 +1,3 @@
 // 测试模块
-const foo: number = 42;
-const bar: number = 100;
+export const foo: number = 42;
+export const bar: number = 100;